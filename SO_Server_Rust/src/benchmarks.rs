@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Tareas de CPU ajustables para experimentos de carga (/primes, /factorize, /matrix, /montecarlo, /sort, /collatz).
+// Cada una recibe `size` y reporta su propio tiempo de còmputo, sin contar la cola ni la generaciòn de datos
+
+pub const DEFAULT_SEED: u64 = 42;
+pub const MAX_PRIMES: u64 = 200_000_000;
+pub const MAX_MATRIX: usize = 1500;
+pub const MAX_SAMPLES: u64 = 2_000_000_000;
+// Con 10^15 la divisiòn de prueba llega a lo sumo a ~3·10^7, unos milisegundos por solicitud
+pub const MAX_FACTORIZE: u64 = 1_000_000_000_000_000;
+// Cada elemento ocupa 8 bytes: 40 MB por solicitud como màximo
+pub const MAX_SORT: usize = 5_000_000;
+pub const MAX_COLLATZ: u64 = 100_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Benchmark {
+    // Cantidad de primos <= n (criba de Eratòstenes)
+    Primes(u64),
+    // Factorizaciòn por divisiòn de prueba
+    Factorize(u64),
+    MatrixMultiply { size: usize, seed: u64 },
+    MonteCarloPi { samples: u64, seed: u64 },
+    Sort { size: usize, seed: u64 },
+    // Nùmero menor a n con la secuencia de Collatz màs larga
+    Collatz(u64),
+}
+
+pub const BENCHMARK_NAMES: [&str; 6] = ["primes", "factorize", "matrix", "montecarlo", "sort", "collatz"];
+
+// Devuelve None si `name` no es una tarea de CPU, asì /simulate y /loadtest pueden reutilizarla
+pub fn parse_benchmark(name: &str, params: &HashMap<String, String>) -> Option<Result<Benchmark, ApiError>> {
+    if !BENCHMARK_NAMES.contains(&name) {
+        return None;
+    }
+
+    let seed = match params.get("seed").map(|s| s.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => return Some(Err(ApiError::not_positive_integer("seed"))),
+        None => DEFAULT_SEED,
+    };
+    let size = |max: u64| -> Result<u64, ApiError> {
+        let size = params.get("size").ok_or(ApiError::missing("size"))?;
+        size.parse::<u64>().ok().filter(|s| (1..=max).contains(s))
+            .ok_or(ApiError::not_in_range("size", 1, max))
+    };
+
+    Some(match name {
+        "primes" => size(MAX_PRIMES).map(Benchmark::Primes),
+        "factorize" => size(MAX_FACTORIZE).map(Benchmark::Factorize),
+        "matrix" => size(MAX_MATRIX as u64).map(|n| Benchmark::MatrixMultiply { size: n as usize, seed }),
+        "montecarlo" => size(MAX_SAMPLES).map(|n| Benchmark::MonteCarloPi { samples: n, seed }),
+        "sort" => size(MAX_SORT as u64).map(|n| Benchmark::Sort { size: n as usize, seed }),
+        _ => size(MAX_COLLATZ).map(Benchmark::Collatz),
+    })
+}
+
+pub fn run_benchmark(benchmark: &Benchmark) -> Message {
+    match *benchmark {
+        Benchmark::Primes(n) => {
+            let start = Instant::now();
+            let count = count_primes(n);
+            Message::key("bench_primes").arg("count", count).arg("n", n).arg("ms", format!("{:.2}", ms(start)))
+        }
+        Benchmark::Factorize(n) => {
+            let start = Instant::now();
+            let factors = factorize(n);
+            // 1 no tiene factores primos: se muestra como el producto vacìo
+            let factors = if factors.is_empty() { "1".to_string() } else { factors.iter().map(|f| f.to_string()).collect::<Vec<_>>().join(" x ") };
+            format!("{} = {} ({:.2} ms)", n, factors, ms(start)).into()
+        }
+        Benchmark::MatrixMultiply { size, seed } => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let a: Vec<f64> = (0..size * size).map(|_| rng.random::<f64>()).collect();
+            let b: Vec<f64> = (0..size * size).map(|_| rng.random::<f64>()).collect();
+
+            let start = Instant::now();
+            let c = multiply(&a, &b, size);
+            let elapsed = ms(start);
+            let trace: f64 = (0..size).map(|i| c[i * size + i]).sum();
+            let gflops = 2.0 * (size as f64).powi(3) / (elapsed / 1000.0) / 1e9;
+            Message::key("bench_matrix")
+                .arg("size", size)
+                .arg("trace", format!("{:.6}", trace))
+                .arg("ms", format!("{:.2}", elapsed))
+                .arg("gflops", format!("{:.2}", gflops))
+        }
+        Benchmark::MonteCarloPi { samples, seed } => {
+            let start = Instant::now();
+            let pi = monte_carlo_pi(samples, seed);
+            Message::key("bench_pi")
+                .arg("pi", format!("{:.6}", pi))
+                .arg("samples", samples)
+                .arg("error", format!("{:.6}", (pi - std::f64::consts::PI).abs()))
+                .arg("ms", format!("{:.2}", ms(start)))
+        }
+        Benchmark::Sort { size, seed } => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut values: Vec<u64> = (0..size).map(|_| rng.random()).collect();
+
+            let start = Instant::now();
+            values.sort_unstable();
+            let elapsed = ms(start);
+            Message::key("bench_sort")
+                .arg("size", size)
+                .arg("min", values[0])
+                .arg("median", values[size / 2])
+                .arg("max", values[size - 1])
+                .arg("ms", format!("{:.2}", elapsed))
+        }
+        Benchmark::Collatz(limit) => {
+            let start = Instant::now();
+            let (number, steps) = longest_collatz(limit);
+            Message::key("bench_collatz").arg("limit", limit).arg("number", number).arg("steps", steps).arg("ms", format!("{:.2}", ms(start)))
+        }
+    }
+}
+
+fn ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+// Criba solo sobre los impares: el ìndice i representa al nùmero 2i + 1
+pub fn count_primes(n: u64) -> u64 {
+    if n < 2 {
+        return 0;
+    }
+    let half = n.div_ceil(2) as usize;
+    let mut composite = vec![false; half];
+    let mut i = 1;
+    while (2 * i + 1) * (2 * i + 1) <= n as usize {
+        if !composite[i] {
+            let p = 2 * i + 1;
+            let mut j = p * p / 2;
+            while j < half {
+                composite[j] = true;
+                j += p;
+            }
+        }
+        i += 1;
+    }
+    // El ìndice 0 es el 1, que no es primo, pero en su lugar se cuenta el 2
+    composite.iter().filter(|c| !**c).count() as u64
+}
+
+pub fn factorize(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    for p in [2, 3] {
+        while n > 1 && n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    let mut d: u64 = 5;
+    while d.checked_mul(d).is_some_and(|sq| sq <= n) {
+        for candidate in [d, d + 2] {
+            while n.is_multiple_of(candidate) {
+                factors.push(candidate);
+                n /= candidate;
+            }
+        }
+        d += 6;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+// Orden i-k-j para recorrer las filas de b de forma contigua
+fn multiply(a: &[f64], b: &[f64], n: usize) -> Vec<f64> {
+    let mut c = vec![0.0; n * n];
+    for i in 0..n {
+        for k in 0..n {
+            let aik = a[i * n + k];
+            let row = &b[k * n..(k + 1) * n];
+            for (cij, bkj) in c[i * n..(i + 1) * n].iter_mut().zip(row) {
+                *cij += aik * bkj;
+            }
+        }
+    }
+    c
+}
+
+fn monte_carlo_pi(samples: u64, seed: u64) -> f64 {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inside = (0..samples).filter(|_| {
+        let (x, y): (f64, f64) = (rng.random(), rng.random());
+        x * x + y * y <= 1.0
+    }).count();
+    4.0 * inside as f64 / samples as f64
+}
+
+fn longest_collatz(limit: u64) -> (u64, u64) {
+    let mut best = (1, 0);
+    for start in 1..limit.max(2) {
+        let mut n = start;
+        let mut steps = 0;
+        while n != 1 {
+            n = if n.is_multiple_of(2) { n / 2 } else { 3 * n + 1 };
+            steps += 1;
+        }
+        if steps > best.1 {
+            best = (start, steps);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{count_primes, factorize, longest_collatz, monte_carlo_pi, multiply, parse_benchmark, run_benchmark, Benchmark};
+
+    #[test]
+    fn test_primes_and_factorization() {
+        assert_eq!([0, 1, 2, 3, 10, 100, 1_000_000].map(count_primes), [0, 0, 1, 2, 4, 25, 78498]);
+        assert_eq!(factorize(360), vec![2, 2, 2, 3, 3, 5]);
+        assert_eq!(factorize(600851475143), vec![71, 839, 1471, 6857]);
+        assert_eq!(factorize(1_000_000_007), vec![1_000_000_007]);
+        assert!(factorize(1).is_empty());
+    }
+
+    #[test]
+    fn test_matrix_pi_and_collatz() {
+        let identity = vec![1.0, 0.0, 0.0, 1.0];
+        assert_eq!(multiply(&[1.0, 2.0, 3.0, 4.0], &identity, 2), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(multiply(&[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0, 7.0, 8.0], 2), vec![19.0, 22.0, 43.0, 50.0]);
+
+        // Con la misma semilla el resultado se repite
+        let pi = monte_carlo_pi(200_000, 7);
+        assert!((pi - std::f64::consts::PI).abs() < 0.02);
+        assert_eq!(pi, monte_carlo_pi(200_000, 7));
+
+        assert_eq!(longest_collatz(10), (9, 19));
+        assert_eq!(longest_collatz(100_000), (77031, 350));
+    }
+
+    #[test]
+    fn test_parse_and_run() {
+        let mut params = HashMap::new();
+        params.insert("size".to_string(), "1000".to_string());
+        assert_eq!(parse_benchmark("sort", &params), Some(Ok(Benchmark::Sort { size: 1000, seed: 42 })));
+        assert!(parse_benchmark("reverse", &params).is_none());
+
+        params.insert("size".to_string(), "0".to_string());
+        assert!(parse_benchmark("primes", &params).unwrap().is_err());
+        params.insert("size".to_string(), "5000".to_string());
+        assert!(parse_benchmark("matrix", &params).unwrap().is_err());
+        params.insert("size".to_string(), u64::MAX.to_string());
+        assert!(parse_benchmark("factorize", &params).unwrap().is_err());
+        params.insert("size".to_string(), "50000000".to_string());
+        assert!(parse_benchmark("sort", &params).unwrap().is_err());
+
+        let report = run_benchmark(&Benchmark::Sort { size: 10, seed: 1 }).to_string();
+        assert!(report.starts_with("10 elementos ordenados") && report.ends_with("ms)"));
+        assert!(run_benchmark(&Benchmark::MatrixMultiply { size: 8, seed: 1 }).to_string().contains("GFLOPS"));
+    }
+}
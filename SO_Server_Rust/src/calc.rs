@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Evaluador de expresiones aritmèticas para /calc?expr=...
+// Descenso recursivo sobre los caracteres, sin eval ni còdigo externo. Los errores indican la posiciòn
+// (ìndice del carácter, desde 0) y hay lìmites de largo, anidamiento y exponentes
+
+pub const MAX_EXPR_LEN: usize = 4096;
+pub const MAX_DEPTH: usize = 64;
+pub const MAX_INT_EXPONENT: i64 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    fn as_f64(self) -> f64 {
+        match self {
+            Value::Int(i) => i as f64,
+            Value::Float(f) => f,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Value> {
+        match text.parse::<i64>() {
+            Ok(i) => Some(Value::Int(i)),
+            Err(_) => text.parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::Float),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+// Variables desde el query: /calc?expr=x*y&x=3&y=2.5 (todo paràmetro salvo `expr`)
+pub fn parse_variables(params: &HashMap<String, String>) -> Result<HashMap<String, Value>, ApiError> {
+    let mut variables = HashMap::new();
+    for (name, value) in params.iter().filter(|(name, _)| name.as_str() != "expr") {
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || CONSTANTS.iter().any(|(c, _)| c == name) {
+            return Err(ApiError::invalid(name, Message::key("calc_invalid_variable").arg("name", name)));
+        }
+        let value = Value::parse(value).ok_or_else(|| ApiError::invalid(name, Message::key("number_expected").arg("field", name)))?;
+        variables.insert(name.clone(), value);
+    }
+    Ok(variables)
+}
+
+const CONSTANTS: [(&str, f64); 3] = [("pi", std::f64::consts::PI), ("e", std::f64::consts::E), ("tau", std::f64::consts::TAU)];
+
+pub fn evaluate(expr: &str, variables: &HashMap<String, Value>) -> Result<Value, Message> {
+    if expr.chars().count() > MAX_EXPR_LEN {
+        return Err(Message::key("calc_too_long").arg("max", MAX_EXPR_LEN));
+    }
+
+    let mut parser = Parser { chars: expr.chars().collect(), pos: 0, depth: 0, variables };
+    let value = parser.expression()?;
+    parser.skip_spaces();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error(unexpected(parser.chars[parser.pos])));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+    variables: &'a HashMap<String, Value>,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: Message) -> Message {
+        self.error_at(self.pos, msg)
+    }
+
+    fn error_at(&self, pos: usize, msg: Message) -> Message {
+        Message::key("invalid_at").arg("pos", pos).arg_message("error", msg)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_spaces();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Cada nivel de paréntesis, funciòn o signo unario cuenta para el lìmite de anidamiento
+    fn enter(&mut self) -> Result<(), Message> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(Message::key("calc_max_depth").arg("max", MAX_DEPTH)));
+        }
+        Ok(())
+    }
+
+    // expresiòn := tèrmino (('+' | '-') tèrmino)*
+    fn expression(&mut self) -> Result<Value, Message> {
+        let mut value = self.term()?;
+        loop {
+            let pos = self.pos;
+            value = if self.eat('+') {
+                let rhs = self.term()?;
+                arithmetic(value, rhs, i64::checked_add, |a, b| a + b).ok_or_else(|| self.error_at(pos, Message::key("calc_add_overflow")))?
+            } else if self.eat('-') {
+                let rhs = self.term()?;
+                arithmetic(value, rhs, i64::checked_sub, |a, b| a - b).ok_or_else(|| self.error_at(pos, Message::key("calc_sub_overflow")))?
+            } else {
+                return Ok(value);
+            };
+        }
+    }
+
+    // tèrmino := unario (('*' | '/' | '%') unario)*
+    fn term(&mut self) -> Result<Value, Message> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_spaces();
+            let pos = self.pos;
+            // '**' es potencia, no multiplicaciòn
+            if self.chars.get(pos) == Some(&'*') && self.chars.get(pos + 1) != Some(&'*') {
+                self.pos += 1;
+                let rhs = self.unary()?;
+                value = arithmetic(value, rhs, i64::checked_mul, |a, b| a * b).ok_or_else(|| self.error_at(pos, Message::key("calc_mul_overflow")))?;
+            } else if self.eat('/') {
+                let rhs = self.unary()?;
+                value = divide(value, rhs).map_err(|msg| self.error_at(pos, msg))?;
+            } else if self.eat('%') {
+                let rhs = self.unary()?;
+                value = remainder(value, rhs).map_err(|msg| self.error_at(pos, msg))?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // unario := ('-' | '+') unario | potencia
+    fn unary(&mut self) -> Result<Value, Message> {
+        let pos = self.pos;
+        if self.eat('-') {
+            self.enter()?;
+            let value = self.unary()?;
+            self.depth -= 1;
+            return match value {
+                Value::Int(i) => i.checked_neg().map(Value::Int).ok_or_else(|| self.error_at(pos, Message::key("calc_neg_overflow"))),
+                Value::Float(f) => Ok(Value::Float(-f)),
+            };
+        }
+        if self.eat('+') {
+            self.enter()?;
+            let value = self.unary();
+            self.depth -= 1;
+            return value;
+        }
+        self.power()
+    }
+
+    // potencia := primario (('^' | '**') unario)?   asociativa a la derecha: 2^3^2 = 2^9
+    fn power(&mut self) -> Result<Value, Message> {
+        let base = self.primary()?;
+        self.skip_spaces();
+        let pos = self.pos;
+        let operator = match (self.chars.get(pos), self.chars.get(pos + 1)) {
+            (Some('^'), _) => 1,
+            (Some('*'), Some('*')) => 2,
+            _ => return Ok(base),
+        };
+        self.pos += operator;
+        self.enter()?;
+        let exponent = self.unary()?;
+        self.depth -= 1;
+        pow(base, exponent).map_err(|msg| self.error_at(pos, msg))
+    }
+
+    // primario := nùmero | '(' expresiòn ')' | identificador | identificador '(' argumentos ')'
+    fn primary(&mut self) -> Result<Value, Message> {
+        let pos = match self.peek() {
+            Some(_) => self.pos,
+            None => return Err(self.error(Message::key("calc_unexpected_end"))),
+        };
+        let c = self.chars[pos];
+
+        if c == '(' {
+            self.pos += 1;
+            self.enter()?;
+            let value = self.expression()?;
+            if !self.eat(')') {
+                return Err(self.error(Message::key("calc_expected").arg("expected", "')'")));
+            }
+            self.depth -= 1;
+            return Ok(value);
+        }
+        if c.is_ascii_digit() || c == '.' {
+            return self.number();
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let name = self.identifier();
+            if self.eat('(') {
+                self.enter()?;
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(self.error(Message::key("calc_expected").arg("expected", "',' | ')'")));
+                        }
+                    }
+                }
+                self.depth -= 1;
+                return call(&name, &args).map_err(|msg| self.error_at(pos, msg));
+            }
+            if let Some(value) = self.variables.get(&name) {
+                return Ok(*value);
+            }
+            return match CONSTANTS.iter().find(|(constant, _)| *constant == name) {
+                Some((_, value)) => Ok(Value::Float(*value)),
+                None => Err(self.error_at(pos, Message::key("calc_unknown_variable").arg("name", name))),
+            };
+        }
+        Err(self.error(unexpected(c)))
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // Enteros, decimales y notaciòn cientìfica (1e3 es float)
+    fn number(&mut self) -> Result<Value, Message> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            while parser.chars.get(parser.pos).is_some_and(|c| c.is_ascii_digit()) {
+                parser.pos += 1;
+            }
+        };
+        digits(self);
+        let mut float = false;
+        if self.chars.get(self.pos) == Some(&'.') {
+            float = true;
+            self.pos += 1;
+            digits(self);
+        }
+        if self.chars.get(self.pos).is_some_and(|c| *c == 'e' || *c == 'E')
+            && (self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit())
+                || (self.chars.get(self.pos + 1).is_some_and(|c| *c == '+' || *c == '-') && self.chars.get(self.pos + 2).is_some_and(|c| c.is_ascii_digit())))
+        {
+            float = true;
+            self.pos += 2;
+            digits(self);
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if float {
+            text.parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::Float).ok_or_else(|| self.error_at(start, Message::key("calc_invalid_number").arg("number", &text)))
+        } else {
+            text.parse::<i64>().map(Value::Int).map_err(|_| self.error_at(start, Message::key("calc_integer_too_large").arg("number", &text)))
+        }
+    }
+}
+
+fn unexpected(c: char) -> Message {
+    Message::key("unexpected_char").arg("char", c)
+}
+
+// Enteros con aritmètica verificada; si alguno es float el resultado es float
+fn arithmetic(a: Value, b: Value, int_op: fn(i64, i64) -> Option<i64>, float_op: fn(f64, f64) -> f64) -> Option<Value> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => int_op(x, y).map(Value::Int),
+        _ => Some(Value::Float(float_op(a.as_f64(), b.as_f64()))).filter(|v| v.as_f64().is_finite()),
+    }
+}
+
+// La divisiòn entre enteros sigue siendo entera solo si es exacta: 6/3 = 2, 7/2 = 3.5
+fn divide(a: Value, b: Value) -> Result<Value, Message> {
+    if b.as_f64() == 0.0 {
+        return Err(Message::key("calc_division_by_zero"));
+    }
+    let overflow = || Message::key("calc_div_overflow");
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) if x.checked_rem(y) == Some(0) => x.checked_div(y).map(Value::Int).ok_or_else(overflow),
+        _ => Some(a.as_f64() / b.as_f64()).filter(|v| v.is_finite()).map(Value::Float).ok_or_else(overflow),
+    }
+}
+
+fn remainder(a: Value, b: Value) -> Result<Value, Message> {
+    if b.as_f64() == 0.0 {
+        return Err(Message::key("calc_modulo_by_zero"));
+    }
+    let overflow = || Message::key("calc_rem_overflow");
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => x.checked_rem_euclid(y).map(Value::Int).ok_or_else(overflow),
+        _ => Some(a.as_f64().rem_euclid(b.as_f64())).filter(|v| v.is_finite()).map(Value::Float).ok_or_else(overflow),
+    }
+}
+
+fn pow(base: Value, exponent: Value) -> Result<Value, Message> {
+    match (base, exponent) {
+        (Value::Int(b), Value::Int(e)) if e >= 0 => {
+            if e > MAX_INT_EXPONENT && b.abs() > 1 {
+                return Err(Message::key("calc_exponent_too_large").arg("max", MAX_INT_EXPONENT));
+            }
+            b.checked_pow(e.min(u32::MAX as i64) as u32).map(Value::Int).ok_or_else(|| Message::key("calc_pow_overflow"))
+        }
+        _ => {
+            let result = base.as_f64().powf(exponent.as_f64());
+            if result.is_nan() {
+                return Err(Message::key("calc_pow_not_real"));
+            }
+            if !result.is_finite() {
+                return Err(Message::key("calc_pow_overflow"));
+            }
+            Ok(Value::Float(result))
+        }
+    }
+}
+
+pub const FUNCTION_NAMES: &str = "abs, sqrt, cbrt, exp, ln, log, log(x, base), log2, sin, cos, tan, asin, acos, atan, atan2, floor, ceil, round, min, max, pow, hypot";
+
+fn call(name: &str, args: &[Value]) -> Result<Value, Message> {
+    let arity = |n: usize| -> Result<(), Message> {
+        if args.len() == n { Ok(()) } else { Err(Message::key("calc_arity").arg("name", name).arg("count", n)) }
+    };
+    let x = || args[0].as_f64();
+    let float = |v: f64| -> Result<Value, Message> {
+        if v.is_finite() { Ok(Value::Float(v)) } else { Err(Message::key("calc_not_real").arg("name", name)) }
+    };
+    // floor/ceil/round devuelven entero si cabe en i64
+    let integral = |v: f64| -> Result<Value, Message> {
+        if v.is_finite() && v.abs() < 9.2e18 { Ok(Value::Int(v as i64)) } else { float(v) }
+    };
+
+    match name {
+        "abs" => {
+            arity(1)?;
+            match args[0] {
+                Value::Int(i) => i.checked_abs().map(Value::Int).ok_or_else(|| Message::key("calc_abs_overflow")),
+                Value::Float(f) => Ok(Value::Float(f.abs())),
+            }
+        }
+        "sqrt" => arity(1).and_then(|_| float(x().sqrt())),
+        "cbrt" => arity(1).and_then(|_| float(x().cbrt())),
+        "exp" => arity(1).and_then(|_| float(x().exp())),
+        "ln" => arity(1).and_then(|_| float(x().ln())),
+        "log" if args.len() == 2 => float(x().log(args[1].as_f64())),
+        "log" | "log10" => arity(1).and_then(|_| float(x().log10())),
+        "log2" => arity(1).and_then(|_| float(x().log2())),
+        "sin" => arity(1).and_then(|_| float(x().sin())),
+        "cos" => arity(1).and_then(|_| float(x().cos())),
+        "tan" => arity(1).and_then(|_| float(x().tan())),
+        "asin" => arity(1).and_then(|_| float(x().asin())),
+        "acos" => arity(1).and_then(|_| float(x().acos())),
+        "atan" => arity(1).and_then(|_| float(x().atan())),
+        "atan2" => arity(2).and_then(|_| float(x().atan2(args[1].as_f64()))),
+        "floor" => arity(1).and_then(|_| integral(x().floor())),
+        "ceil" => arity(1).and_then(|_| integral(x().ceil())),
+        "round" => arity(1).and_then(|_| integral(x().round())),
+        "hypot" => arity(2).and_then(|_| float(x().hypot(args[1].as_f64()))),
+        "pow" => arity(2).and_then(|_| pow(args[0], args[1])),
+        "min" | "max" => {
+            let first = *args.first().ok_or_else(|| Message::key("calc_no_arguments").arg("name", name))?;
+            Ok(args[1..].iter().fold(first, |best, v| {
+                let better = if name == "min" { v.as_f64() < best.as_f64() } else { v.as_f64() > best.as_f64() };
+                if better { *v } else { best }
+            }))
+        }
+        _ => Err(Message::key("calc_unknown_function").arg("name", name).arg("available", FUNCTION_NAMES)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{evaluate, parse_variables, Value, MAX_DEPTH};
+
+    fn eval(expr: &str) -> Result<Value, String> {
+        evaluate(expr, &HashMap::new()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_precedence_and_types() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(Value::Int(7)));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(Value::Int(9)));
+        assert_eq!(eval("2 ^ 3 ^ 2"), Ok(Value::Int(512)));
+        assert_eq!(eval("-2 ** 2"), Ok(Value::Int(-4)));
+        assert_eq!(eval("2 ** -1"), Ok(Value::Float(0.5)));
+        assert_eq!(eval("7 / 2"), Ok(Value::Float(3.5)));
+        assert_eq!(eval("6 / 3"), Ok(Value::Int(2)));
+        assert_eq!(eval("-7 % 3"), Ok(Value::Int(2)));
+        assert_eq!(eval("1.5e2 + .5"), Ok(Value::Float(150.5)));
+    }
+
+    #[test]
+    fn test_functions_constants_and_variables() {
+        assert_eq!(eval("sqrt(16) + abs(-3)"), Ok(Value::Float(7.0)));
+        assert_eq!(eval("max(1, 5.5, 3) + min(4, 2)"), Ok(Value::Float(7.5)));
+        assert_eq!(eval("floor(2.7) + round(pi)"), Ok(Value::Int(5)));
+        let Ok(Value::Float(log)) = eval("log(8, 2)") else { panic!("log(8, 2) debe ser float") };
+        assert!((log - 3.0).abs() < 1e-12);
+
+        let mut params = HashMap::new();
+        params.insert("expr".to_string(), "ignorado".to_string());
+        params.insert("x".to_string(), "3".to_string());
+        params.insert("tasa_1".to_string(), "0.5".to_string());
+        let variables = parse_variables(&params).unwrap();
+        assert_eq!(evaluate("x * 2 + tasa_1", &variables).unwrap(), Value::Float(6.5));
+
+        params.insert("pi".to_string(), "3".to_string());
+        assert!(parse_variables(&params).is_err());
+    }
+
+    #[test]
+    fn test_errors_report_position() {
+        assert_eq!(eval("1 + * 2").unwrap_err(), "Error en la posiciòn 4: carácter inesperado '*'");
+        assert_eq!(eval("(1 + 2").unwrap_err(), "Error en la posiciòn 6: se esperaba ')'");
+        assert!(eval("1 / 0").unwrap_err().contains("posiciòn 2: divisiòn entre cero"));
+        assert!(eval("2 + y").unwrap_err().contains("posiciòn 4: variable desconocida 'y'"));
+        assert!(eval("foo(1)").unwrap_err().contains("funciòn desconocida"));
+        assert!(eval("sqrt(-1)").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("").is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let deep = format!("{}1{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert!(eval(&deep).unwrap_err().contains("anidamiento"));
+        assert!(eval(&"-".repeat(MAX_DEPTH + 1)).unwrap_err().contains("anidamiento"));
+        let ok = format!("{}1{}", "(".repeat(10), ")".repeat(10));
+        assert_eq!(eval(&ok), Ok(Value::Int(1)));
+
+        assert!(eval("2 ^ 100000").unwrap_err().contains("exponente"));
+        assert!(eval("2 ^ 64").unwrap_err().contains("desbordamiento"));
+        assert!(eval("10.0 ^ 400").unwrap_err().contains("desbordamiento"));
+        assert_eq!(eval("1 ^ 100000000"), Ok(Value::Int(1)));
+        assert!(eval("9223372036854775807 + 1").unwrap_err().contains("desbordamiento"));
+        assert!(eval("1e308 / 1e-10").unwrap_err().contains("desbordamiento en la divisiòn"));
+        assert!(eval("99999999999999999999").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use data_encoding::{DecodeError, DecodeKind, Encoding as DataEncoding, BASE32, BASE64, BASE64URL, HEXLOWER, HEXLOWER_PERMISSIVE};
+
+use crate::endpoints::sha256_reader;
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::storage::{replace_file, resolve_name, InputSource};
+
+// Codificaciòn y decodificaciòn para /encode, /decode y /hash?decode=...
+// Los errores indican la posiciòn del primer byte invàlido en lugar de devolver basura
+
+pub const MAX_CODEC_BYTES: u64 = 32 * 1024 * 1024;
+// Largo màximo de una lìnea de quoted-printable sin contar el salto (RFC 2045)
+const QP_LINE: usize = 76;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Base64,
+    Base64Url,
+    Base32,
+    Hex,
+    Url,
+    QuotedPrintable,
+}
+
+pub const ENCODING_NAMES: &str = "base64, base64url, base32, hex, url, qp";
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Encoding> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "base64" | "b64" => Some(Encoding::Base64),
+            "base64url" => Some(Encoding::Base64Url),
+            "base32" | "b32" => Some(Encoding::Base32),
+            "hex" | "base16" => Some(Encoding::Hex),
+            "url" | "percent" => Some(Encoding::Url),
+            "qp" | "quoted-printable" | "quotedprintable" => Some(Encoding::QuotedPrintable),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Encoding::Base64 => BASE64.encode(data),
+            Encoding::Base64Url => BASE64URL.encode(data),
+            Encoding::Base32 => BASE32.encode(data),
+            Encoding::Hex => HEXLOWER.encode(data),
+            Encoding::Url => url_encode(data),
+            Encoding::QuotedPrintable => qp_encode(data),
+        }
+    }
+
+    pub fn decode(&self, input: &[u8]) -> Result<Vec<u8>, Message> {
+        match self {
+            Encoding::Base64 => strict_decode(&BASE64, "base64", input),
+            Encoding::Base64Url => strict_decode(&BASE64URL, "base64url", input),
+            Encoding::Base32 => strict_decode(&BASE32, "base32", input),
+            Encoding::Hex => strict_decode(&HEXLOWER_PERMISSIVE, "hex", input),
+            Encoding::Url => url_decode(input),
+            Encoding::QuotedPrintable => qp_decode(input),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodecStep {
+    Encode(Encoding),
+    Decode(Encoding),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecOutput {
+    Text,
+    Sha256,
+    // Guarda el resultado en archivos/ con ese nombre
+    Save(String),
+}
+
+// Pasos separados por comas: `to=hex,base64` codifica en hex y el resultado en base64
+pub fn parse_steps(names: &str, decode: bool) -> Result<Vec<CodecStep>, Message> {
+    names.split(',').map(|name| match Encoding::parse(name) {
+        Some(encoding) if decode => Ok(CodecStep::Decode(encoding)),
+        Some(encoding) => Ok(CodecStep::Encode(encoding)),
+        None => Err(Message::key("codec_unknown").arg("name", name.trim()).arg("available", ENCODING_NAMES)),
+    }).collect()
+}
+
+// `hash=true` devuelve el SHA-256 del resultado y `save=nombre` lo guarda en un archivo
+pub fn parse_output(params: &HashMap<String, String>) -> Result<CodecOutput, ApiError> {
+    match (params.get("hash").map(|h| h.as_str()), params.get("save")) {
+        (Some("true"), Some(_)) => Err(ApiError::invalid("save", Message::key("codec_hash_and_save"))),
+        (Some("true"), None) => Ok(CodecOutput::Sha256),
+        (Some("false") | None, Some(name)) => Ok(CodecOutput::Save(name.clone())),
+        (Some("false") | None, None) => Ok(CodecOutput::Text),
+        (Some(_), _) => Err(ApiError::invalid("hash", Message::key("boolean_expected").arg("field", "hash"))),
+    }
+}
+
+pub fn run_codec(input: &InputSource, steps: &[CodecStep], output: &CodecOutput) -> Result<Message, ApiError> {
+    // Hash de un archivo o cuerpo sin transformar: se lee por bloques
+    if steps.is_empty() && *output == CodecOutput::Sha256 {
+        return input.open().and_then(|reader| sha256_reader(reader).map(Message::from).map_err(|_| ApiError::internal(Message::key("input_read_failed"))));
+    }
+
+    let mut data = input.read_all(MAX_CODEC_BYTES)?;
+    for step in steps {
+        data = match step {
+            CodecStep::Encode(encoding) => encoding.encode(&data).into_bytes(),
+            CodecStep::Decode(encoding) => encoding.decode(&data)?,
+        };
+    }
+
+    match output {
+        CodecOutput::Text => String::from_utf8(data).map(Message::from)
+            .map_err(|_| ApiError::invalid_input(Message::key("codec_not_utf8"))),
+        CodecOutput::Sha256 => sha256_reader(&data[..]).map(Message::from).map_err(|_| ApiError::internal(Message::key("input_read_failed"))),
+        CodecOutput::Save(name) => save_output(name, &data),
+    }
+}
+
+fn save_output(name: &str, data: &[u8]) -> Result<Message, ApiError> {
+    let (key, path) = resolve_name(name).ok_or(ApiError::invalid_name("save"))?;
+    replace_file(&key, &path, data).map_err(|_| ApiError::internal(Message::key("file_write_failed")))?;
+    Ok(Message::key("codec_saved").arg("path", path.display()).arg("bytes", data.len()))
+}
+
+fn strict_decode(encoding: &DataEncoding, name: &str, input: &[u8]) -> Result<Vec<u8>, Message> {
+    encoding.decode(input.trim_ascii()).map_err(|e: DecodeError| {
+        let reason = match e.kind {
+            DecodeKind::Length => "codec_bad_length",
+            DecodeKind::Symbol => "codec_bad_symbol",
+            DecodeKind::Trailing => "codec_trailing_bits",
+            DecodeKind::Padding => "codec_bad_padding",
+        };
+        invalid_input(name, Message::key(reason), e.position)
+    })
+}
+
+fn invalid_input(encoding: &str, reason: Message, pos: usize) -> Message {
+    Message::key("codec_invalid_input").arg("encoding", encoding).arg_message("reason", reason).arg("pos", pos)
+}
+
+// Percent-encoding: solo los caracteres no reservados (RFC 3986) quedan sin codificar
+fn url_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len());
+    for b in data {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            output.push(*b as char);
+        } else {
+            output.push_str(&format!("%{:02X}", b));
+        }
+    }
+    output
+}
+
+fn url_decode(input: &[u8]) -> Result<Vec<u8>, Message> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'%' => {
+                let byte = input.get(i + 1..i + 3).and_then(hex_byte)
+                    .ok_or_else(|| invalid_input("url", Message::key("codec_incomplete_percent"), i))?;
+                output.push(byte);
+                i += 3;
+            }
+            b'+' => {
+                output.push(b' ');
+                i += 1;
+            }
+            b => {
+                output.push(b);
+                i += 1;
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn hex_byte(pair: &[u8]) -> Option<u8> {
+    if pair.len() != 2 || !pair.iter().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()
+}
+
+// Quoted-printable (RFC 2045). Los saltos de lìnea del texto se conservan tal cual
+// y las lìneas largas se parten con saltos suaves "=\r\n"
+fn qp_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len());
+    let mut line = 0;
+    let mut i = 0;
+
+    while i < data.len() {
+        let b = data[i];
+        let line_break = match (b, data.get(i + 1)) {
+            (b'\r', Some(b'\n')) => Some("\r\n"),
+            (b'\n', _) => Some("\n"),
+            _ => None,
+        };
+        if let Some(line_break) = line_break {
+            output.push_str(line_break);
+            line = 0;
+            i += line_break.len();
+            continue;
+        }
+
+        // Espacios al final de una lìnea se codifican para que el transporte no los borre
+        let at_line_end = matches!(data.get(i + 1), None | Some(b'\r') | Some(b'\n'));
+        let literal = ((b'!'..=b'~').contains(&b) && b != b'=') || (matches!(b, b' ' | b'\t') && !at_line_end);
+        let token = if literal { (b as char).to_string() } else { format!("={:02X}", b) };
+
+        if line + token.len() > QP_LINE - 1 {
+            output.push_str("=\r\n");
+            line = 0;
+        }
+        line += token.len();
+        output.push_str(&token);
+        i += 1;
+    }
+    output
+}
+
+fn qp_decode(input: &[u8]) -> Result<Vec<u8>, Message> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        let b = input[i];
+        match b {
+            b'=' => match input.get(i + 1) {
+                // Salto suave: "=\r\n" o "=\n"
+                Some(b'\r') if input.get(i + 2) == Some(&b'\n') => i += 3,
+                Some(b'\n') => i += 2,
+                _ => {
+                    let byte = input.get(i + 1..i + 3).and_then(hex_byte)
+                        .ok_or_else(|| invalid_input("qp", Message::key("codec_incomplete_equals"), i))?;
+                    output.push(byte);
+                    i += 3;
+                }
+            },
+            b'\r' | b'\n' => {
+                // Los espacios agregados antes de un salto de lìnea no forman parte del texto
+                while output.last().is_some_and(|c| matches!(c, b' ' | b'\t')) {
+                    output.pop();
+                }
+                output.push(b);
+                i += 1;
+            }
+            b' ' | b'\t' | b'!'..=b'~' => {
+                output.push(b);
+                i += 1;
+            }
+            _ => return Err(invalid_input("qp", Message::key("codec_byte_not_allowed").arg("byte", format!("0x{:02X}", b)), i)),
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::storage::{write_atomic, InputSource};
+
+    use crate::error_responses::ApiError;
+
+    use super::{parse_output, parse_steps, run_codec, CodecOutput, CodecStep, Encoding};
+
+    fn run(input: &InputSource, steps: &[CodecStep], output: &CodecOutput) -> Result<String, ApiError> {
+        run_codec(input, steps, output).map(|message| message.to_string())
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let data = "¡Hola, mundo! a+b=c ~ 100%".as_bytes();
+        for encoding in [Encoding::Base64, Encoding::Base64Url, Encoding::Base32, Encoding::Hex, Encoding::Url, Encoding::QuotedPrintable] {
+            let encoded = encoding.encode(data);
+            assert_eq!(encoding.decode(encoded.as_bytes()).unwrap(), data, "{:?}", encoding);
+        }
+
+        assert_eq!(Encoding::Base64.encode(b"hola"), "aG9sYQ==");
+        assert_eq!(Encoding::Base32.encode(b"hola"), "NBXWYYI=");
+        assert_eq!(Encoding::Hex.encode(b"hola"), "686f6c61");
+        assert_eq!(Encoding::Url.encode("a b/ñ".as_bytes()), "a%20b%2F%C3%B1");
+        assert_eq!(Encoding::QuotedPrintable.encode("café = ok \n".as_bytes()), "caf=C3=A9 =3D ok=20\n");
+    }
+
+    #[test]
+    fn test_strict_errors_with_position() {
+        assert!(Encoding::Base64.decode(b"aG9s*Q==").unwrap_err().to_string().contains("posiciòn 4"));
+        assert!(Encoding::Base64.decode(b"aG9sYQ=").unwrap_err().to_string().contains("longitud"));
+        assert!(Encoding::Hex.decode(b"686").is_err());
+        assert!(Encoding::Url.decode(b"abc%2").unwrap_err().to_string().contains("posiciòn 3"));
+        assert!(Encoding::QuotedPrintable.decode(b"hola=ZZ").is_err());
+        assert!(Encoding::QuotedPrintable.decode("ñ".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_qp_soft_breaks() {
+        let long = "x".repeat(200);
+        let encoded = Encoding::QuotedPrintable.encode(long.as_bytes());
+        assert!(encoded.split("\r\n").all(|line| line.len() <= 76));
+        assert_eq!(Encoding::QuotedPrintable.decode(encoded.as_bytes()).unwrap(), long.as_bytes());
+        assert_eq!(Encoding::QuotedPrintable.decode(b"hola   \r\nmundo").unwrap(), b"hola\r\nmundo");
+    }
+
+    #[test]
+    fn test_pipeline_outputs_and_files() {
+        let steps = parse_steps("hex,base64", false).unwrap();
+        let encoded = run(&InputSource::Inline("hi".into()), &steps, &CodecOutput::Text).unwrap();
+        assert_eq!(encoded, "Njg2OQ==");
+        assert_eq!(run(&InputSource::Inline(encoded), &[CodecStep::Decode(Encoding::Base64), CodecStep::Decode(Encoding::Hex)], &CodecOutput::Text).unwrap(), "hi");
+
+        // Bytes que no son UTF-8 solo se pueden hashear o guardar
+        let binary = InputSource::Inline("/w==".into());
+        assert!(run(&binary, &[CodecStep::Decode(Encoding::Base64)], &CodecOutput::Text).unwrap_err().message().contains("UTF-8"));
+        assert_eq!(run(&binary, &[CodecStep::Decode(Encoding::Base64)], &CodecOutput::Sha256).unwrap().len(), 64);
+
+        write_atomic(std::path::Path::new("archivos/codec_test.txt"), b"hola", true).unwrap();
+        let stored = InputSource::Stored("codec_test".into());
+        assert_eq!(run(&stored, &[CodecStep::Encode(Encoding::Base64)], &CodecOutput::Text).unwrap(), "aG9sYQ==");
+        assert_eq!(run(&stored, &[], &CodecOutput::Sha256).unwrap(), "b221d9dbb083a7f33428d7c2a3c3198ae925614d70210e28716ccaa7cd4ddb79");
+        assert!(run(&stored, &[CodecStep::Encode(Encoding::Hex)], &CodecOutput::Save("codec_salida".into())).unwrap().contains("8 bytes"));
+        assert_eq!(std::fs::read_to_string("archivos/codec_salida.txt").unwrap(), "686f6c61");
+        let _ = std::fs::remove_file("archivos/codec_test.txt");
+        let _ = std::fs::remove_file("archivos/codec_salida.txt");
+
+        let mut params = HashMap::new();
+        params.insert("hash".to_string(), "true".to_string());
+        assert_eq!(parse_output(&params).unwrap(), CodecOutput::Sha256);
+        params.insert("save".to_string(), "x".to_string());
+        assert_eq!(parse_output(&params).unwrap_err().field(), Some("save"));
+        assert!(parse_steps("base64,rot13", true).unwrap_err().to_string().contains("rot13"));
+    }
+}
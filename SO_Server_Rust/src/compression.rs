@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::time::Instant;
+
+use flate2::read::{DeflateDecoder, DeflateEncoder, GzDecoder, GzEncoder};
+use flate2::Compression;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::file_transfer::MAX_UPLOAD_BYTES;
+use crate::storage::{commit_staged, read_lock, resolve_name, save_version, stage, versioning_enabled, write_lock};
+
+// Compresiòn y descompresiòn de archivos guardados (/compress, /decompress).
+// Todo se hace por bloques: el archivo de entrada se lee mientras se escribe el temporal de salida
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gzip,
+    Deflate,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "gzip" | "gz" => Some(Format::Gzip),
+            "deflate" => Some(Format::Deflate),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Gzip => "gzip",
+            Format::Deflate => "deflate",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Gzip => "gz",
+            Format::Deflate => "deflate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressSpec {
+    pub name: String,
+    pub format: Format,
+    pub level: u32,
+    pub decompress: bool,
+    // Archivo de salida. Con None el resultado se descarta (ùtil para /loadtest)
+    pub output: Option<String>,
+    pub overwrite: bool,
+}
+
+// /compress?name=archivo&format=gzip&level=6&output=salida | /decompress?name=archivo.gz
+pub fn parse_compress_params(params: &HashMap<String, String>, decompress: bool, save: bool) -> Result<CompressSpec, ApiError> {
+    let name = params.get("name").ok_or(ApiError::missing("name"))?.clone();
+
+    let format = match params.get("format") {
+        Some(f) => Format::parse(f).ok_or(ApiError::invalid_choice("format", "'gzip', 'deflate'"))?,
+        // Al descomprimir se deduce de la extensiòn
+        None if decompress && name.ends_with(".deflate") => Format::Deflate,
+        None => Format::Gzip,
+    };
+
+    let level = match params.get("level") {
+        Some(l) => l.parse::<u32>().ok().filter(|l| *l <= 9).ok_or(ApiError::not_in_range("level", 0, 9))?,
+        None => 6,
+    };
+
+    // Por defecto se cambia solo la ùltima extensiòn: al descomprimir se quita la de compresiòn
+    // (datos.gz -> datos) y al comprimir se reemplaza la del archivo (notas.txt -> notas.gz)
+    let stem = |name: &str| name.rsplit_once('.').map_or(name, |(stem, _)| stem).to_string();
+    let output = match params.get("output") {
+        _ if !save => None,
+        Some(output) => Some(output.clone()),
+        None if decompress => Some(match name.strip_suffix(".gz").or_else(|| name.strip_suffix(".deflate")) {
+            Some(original) => original.to_string(),
+            None => stem(&name),
+        }),
+        None => Some(format!("{}.{}", stem(&name), format.extension())),
+    };
+
+    let overwrite = match params.get("overwrite").map(|o| o.as_str()) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(_) => return Err(ApiError::invalid("overwrite", Message::key("boolean_expected").arg("field", "overwrite"))),
+    };
+
+    Ok(CompressSpec { name, format, level, decompress, output, overwrite })
+}
+
+// Cuenta los bytes leìdos del archivo original
+struct Counting<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+pub fn run_compression(spec: &CompressSpec) -> Result<Message, ApiError> {
+    let (_, path) = resolve_name(&spec.name).ok_or(ApiError::invalid_name("name"))?;
+    let output = match &spec.output {
+        Some(output) => Some(resolve_name(output).ok_or(ApiError::invalid("output", Message::key("output_name_invalid")))?),
+        None => None,
+    };
+    if output.as_ref().is_some_and(|(_, out)| *out == path) {
+        return Err(ApiError::invalid("output", Message::key("output_same_as_input")));
+    }
+
+    let file = {
+        let _guard = read_lock(&path);
+        File::open(&path).map_err(|_| ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", &spec.name)))?
+    };
+
+    let start = Instant::now();
+    let mut input = Counting { inner: file, count: 0 };
+    let reader: Box<dyn Read + '_> = match (spec.decompress, spec.format) {
+        (false, Format::Gzip) => Box::new(GzEncoder::new(&mut input, Compression::new(spec.level))),
+        (false, Format::Deflate) => Box::new(DeflateEncoder::new(&mut input, Compression::new(spec.level))),
+        (true, Format::Gzip) => Box::new(GzDecoder::new(&mut input)),
+        (true, Format::Deflate) => Box::new(DeflateDecoder::new(&mut input)),
+    };
+    // Lìmite para que un archivo pequeño no se expanda sin control al descomprimir
+    let mut reader = reader.take(MAX_UPLOAD_BYTES + 1);
+
+    let read_error = |e: io::Error| match e.kind() {
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof if spec.decompress => {
+            ApiError::invalid_input(Message::key("compressed_data_invalid").arg("name", &spec.name).arg("format", spec.format.name()))
+        }
+        _ => ApiError::internal(Message::key("compression_failed").arg("name", &spec.name)),
+    };
+
+    let (written, saved) = match &output {
+        Some((key, out_path)) => {
+            let (tmp, written) = stage(out_path, &mut reader).map_err(read_error)?;
+            if written > MAX_UPLOAD_BYTES {
+                let _ = fs::remove_file(&tmp);
+                return Err(ApiError::PayloadTooLarge { message: Message::key("output_too_large").arg("max", MAX_UPLOAD_BYTES) });
+            }
+
+            let _guard = write_lock(out_path);
+            let exists = out_path.exists();
+            let committed = if exists && !spec.overwrite {
+                Err(ApiError::conflict(Some("output"), Message::key("output_exists").arg("path", out_path.display())))
+            } else if exists && versioning_enabled() && save_version(key, out_path).is_err() {
+                Err(ApiError::internal(Message::key("version_save_failed")))
+            } else {
+                commit_staged(&tmp, out_path, true).map_err(|_| ApiError::internal(Message::key("file_write_failed")))
+            };
+            if let Err(error) = committed {
+                let _ = fs::remove_file(&tmp);
+                return Err(error);
+            }
+            (written, Some(out_path.display().to_string()))
+        }
+        None => (io::copy(&mut reader, &mut io::sink()).map_err(read_error)?, None),
+    };
+    drop(reader);
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let original = input.count;
+    let (raw, packed) = if spec.decompress { (written, original) } else { (original, written) };
+    let ratio = if raw == 0 { 0.0 } else { packed as f64 / raw as f64 };
+    let throughput = if elapsed > 0.0 { raw as f64 / 1_048_576.0 / elapsed } else { 0.0 };
+
+    let report = if spec.decompress { Message::key("decompress_report") } else { Message::key("compress_report").arg("level", spec.level) };
+    let destination = saved.map(|s| format!(" -> '{}'", s)).unwrap_or_default();
+    Ok(report
+        .arg("path", path.display())
+        .arg("format", spec.format.name())
+        .arg("destination", destination)
+        .arg("original", original)
+        .arg("written", written)
+        .arg("ratio", format!("{:.3}", ratio))
+        .arg("saving", format!("{:.1}", (1.0 - ratio) * 100.0))
+        .arg("ms", format!("{:.2}", elapsed * 1000.0))
+        .arg("speed", format!("{:.2}", throughput)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use crate::storage::write_atomic;
+
+    use super::{parse_compress_params, run_compression, CompressSpec, Format};
+
+    fn spec(name: &str, format: Format, decompress: bool, output: Option<&str>) -> CompressSpec {
+        CompressSpec { name: name.to_string(), format, level: 6, decompress, output: output.map(|o| o.to_string()), overwrite: true }
+    }
+
+    #[test]
+    fn test_round_trip_gzip_and_deflate() {
+        let content = "una lìnea que se repite bastante\n".repeat(2000);
+        write_atomic(Path::new("archivos/comp_original.txt"), content.as_bytes(), true).unwrap();
+
+        for (format, packed) in [(Format::Gzip, "comp_original.gz"), (Format::Deflate, "comp_original.deflate")] {
+            let report = run_compression(&spec("comp_original", format, false, Some(packed))).unwrap().to_string();
+            assert!(report.contains("ratio 0.0"), "{}", report);
+            assert!(std::fs::metadata(Path::new("archivos").join(packed)).unwrap().len() < content.len() as u64 / 10);
+
+            run_compression(&spec(packed, format, true, Some("comp_copia"))).unwrap();
+            assert_eq!(std::fs::read_to_string("archivos/comp_copia.txt").unwrap(), content);
+            let _ = std::fs::remove_file(Path::new("archivos").join(packed));
+        }
+
+        // Sin salida solo se mide
+        assert!(run_compression(&spec("comp_original", Format::Gzip, false, None)).unwrap().to_string().contains(&format!("{} ->", content.len())));
+        let _ = std::fs::remove_file("archivos/comp_original.txt");
+        let _ = std::fs::remove_file("archivos/comp_copia.txt");
+    }
+
+    #[test]
+    fn test_invalid_data_and_existing_output() {
+        write_atomic(Path::new("archivos/comp_basura.gz"), b"esto no es gzip", true).unwrap();
+        assert!(run_compression(&spec("comp_basura.gz", Format::Gzip, true, Some("comp_basura_out"))).unwrap_err().message().contains("gzip"));
+        assert!(!Path::new("archivos/comp_basura_out.txt").exists());
+
+        let keep = CompressSpec { overwrite: false, ..spec("comp_basura.gz", Format::Gzip, false, Some("comp_basura.gz")) };
+        assert!(run_compression(&keep).unwrap_err().message().contains("distinto"));
+        let _ = std::fs::remove_file("archivos/comp_basura.gz");
+    }
+
+    #[test]
+    fn test_parse_compress_params() {
+        let mut params = HashMap::new();
+        params.insert("name".to_string(), "datos.deflate".to_string());
+        let parsed = parse_compress_params(&params, true, true).unwrap();
+        assert_eq!((parsed.format, parsed.output), (Format::Deflate, Some("datos".to_string())));
+
+        params.insert("name".to_string(), "datos".to_string());
+        assert_eq!(parse_compress_params(&params, false, true).unwrap().output, Some("datos.gz".to_string()));
+        params.insert("name".to_string(), "datos.csv".to_string());
+        assert_eq!(parse_compress_params(&params, false, true).unwrap().output, Some("datos.gz".to_string()));
+        params.insert("name".to_string(), "datos.png.gz".to_string());
+        assert_eq!(parse_compress_params(&params, true, true).unwrap().output, Some("datos.png".to_string()));
+        assert_eq!(parse_compress_params(&params, false, false).unwrap().output, None);
+        params.insert("level".to_string(), "12".to_string());
+        assert!(parse_compress_params(&params, false, true).is_err());
+    }
+}
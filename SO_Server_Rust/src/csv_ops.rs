@@ -0,0 +1,747 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::storage::{create_temp, replace_staged, resolve_name, InputSource, STORAGE_DIR};
+
+// Operaciones por columnas sobre datos CSV (/csv): filtrar, ordenar y agrupar, con salida CSV o JSON.
+// La entrada se lee fila por fila; para ordenar entradas grandes se escriben bloques ordenados en disco
+// y luego se mezclan (external merge sort), asì la memoria usada no depende del tamaño del archivo
+
+// Bytes de filas que se ordenan en memoria antes de pasar el bloque a disco
+pub const SORT_RUN_BYTES: usize = 8 * 1024 * 1024;
+// La respuesta directa tiene lìmite; para resultados màs grandes se usa save=
+pub const MAX_INLINE_OUTPUT: usize = 1024 * 1024;
+pub const MAX_GROUPS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub column: String,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+impl Filter {
+    // Si ambos lados son nùmeros se comparan como nùmeros, si no como texto
+    fn matches(&self, field: &str) -> bool {
+        if self.comparison == Comparison::Contains {
+            return field.contains(&self.value);
+        }
+        // Una celda que no es nùmero no cumple una comparaciòn numèrica (p. ej. una celda vacìa con precio>10)
+        let numeric = |text: &str| text.trim().parse::<f64>().is_ok();
+        if self.comparison != Comparison::Eq && self.comparison != Comparison::Ne && numeric(&self.value) && !numeric(field) {
+            return false;
+        }
+        let ordering = compare_fields(field, &self.value);
+        match self.comparison {
+            Comparison::Eq => ordering == Ordering::Equal,
+            Comparison::Ne => ordering != Ordering::Equal,
+            Comparison::Lt => ordering == Ordering::Less,
+            Comparison::Le => ordering != Ordering::Greater,
+            Comparison::Gt => ordering == Ordering::Greater,
+            Comparison::Ge => ordering != Ordering::Less,
+            Comparison::Contains => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFn,
+    // `count` sin columna cuenta filas
+    pub column: Option<String>,
+}
+
+impl Aggregate {
+    fn header(&self) -> String {
+        let name = match self.function {
+            AggregateFn::Count => "count",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Avg => "avg",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+        };
+        match &self.column {
+            Some(column) => format!("{}({})", name, column),
+            None => name.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvSpec {
+    pub filters: Vec<Filter>,
+    pub sort: Option<SortKey>,
+    pub group_by: Option<String>,
+    pub aggregates: Vec<Aggregate>,
+    pub limit: Option<usize>,
+    pub delimiter: u8,
+    pub format: CsvFormat,
+    pub save: Option<String>,
+    pub run_bytes: usize,
+}
+
+// /csv?sort=-precio&where=ciudad=Cartago,precio>=100&group=ciudad&agg=count,sum:precio&format=json&limit=10&save=archivo
+pub fn parse_csv_params(params: &HashMap<String, String>) -> Result<CsvSpec, ApiError> {
+    let filters = match params.get("where") {
+        Some(predicates) => predicates.split(',').map(parse_filter).collect::<Result<Vec<_>, _>>().map_err(|e| ApiError::invalid("where", e))?,
+        None => Vec::new(),
+    };
+
+    let sort = params.get("sort").map(|column| match column.strip_prefix('-') {
+        Some(column) => SortKey { column: column.to_string(), descending: true },
+        None => SortKey { column: column.to_string(), descending: false },
+    });
+    if sort.as_ref().is_some_and(|s| s.column.is_empty()) {
+        return Err(ApiError::invalid("sort", Message::key("csv_sort_column")));
+    }
+
+    let group_by = params.get("group").cloned();
+    let mut aggregates = match params.get("agg") {
+        Some(list) => list.split(',').map(parse_aggregate).collect::<Result<Vec<_>, _>>().map_err(|e| ApiError::invalid("agg", e))?,
+        None => Vec::new(),
+    };
+    if group_by.is_some() && aggregates.is_empty() {
+        aggregates.push(Aggregate { function: AggregateFn::Count, column: None });
+    }
+
+    let limit = match params.get("limit") {
+        Some(l) => Some(l.parse::<usize>().map_err(|_| ApiError::not_positive_integer("limit"))?),
+        None => None,
+    };
+
+    let delimiter = match params.get("delimiter").map(|d| d.as_bytes()) {
+        None => b',',
+        Some(b"tab") => b'\t',
+        Some([d]) if d.is_ascii() && !matches!(d, b'"' | b'\n' | b'\r') => *d,
+        Some(_) => return Err(ApiError::invalid("delimiter", Message::key("csv_invalid_delimiter"))),
+    };
+
+    let format = match params.get("format").map(|f| f.as_str()) {
+        None | Some("csv") => CsvFormat::Csv,
+        Some("json") => CsvFormat::Json,
+        Some(_) => return Err(ApiError::invalid_choice("format", "'csv', 'json'")),
+    };
+
+    Ok(CsvSpec { filters, sort, group_by, aggregates, limit, delimiter, format, save: params.get("save").cloned(), run_bytes: SORT_RUN_BYTES })
+}
+
+fn parse_filter(predicate: &str) -> Result<Filter, Message> {
+    let invalid = || Message::key("csv_invalid_filter").arg("filter", predicate);
+    let start = predicate.find(['=', '!', '<', '>', '~']).ok_or_else(invalid)?;
+    let rest = &predicate[start..];
+    let (comparison, len) = if rest.starts_with("!=") {
+        (Comparison::Ne, 2)
+    } else if rest.starts_with("<=") {
+        (Comparison::Le, 2)
+    } else if rest.starts_with(">=") {
+        (Comparison::Ge, 2)
+    } else {
+        match rest.as_bytes()[0] {
+            b'=' => (Comparison::Eq, 1),
+            b'<' => (Comparison::Lt, 1),
+            b'>' => (Comparison::Gt, 1),
+            b'~' => (Comparison::Contains, 1),
+            _ => return Err(invalid()),
+        }
+    };
+
+    let column = predicate[..start].trim();
+    if column.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Filter { column: column.to_string(), comparison, value: rest[len..].to_string() })
+}
+
+fn parse_aggregate(text: &str) -> Result<Aggregate, Message> {
+    let (name, column) = match text.split_once(':') {
+        Some((name, column)) => (name, Some(column.to_string())),
+        None => (text, None),
+    };
+    let function = match name {
+        "count" => AggregateFn::Count,
+        "sum" => AggregateFn::Sum,
+        "avg" => AggregateFn::Avg,
+        "min" => AggregateFn::Min,
+        "max" => AggregateFn::Max,
+        _ => return Err(Message::key("csv_unknown_aggregate").arg("name", name)),
+    };
+    if function != AggregateFn::Count && column.is_none() {
+        return Err(Message::key("csv_aggregate_column").arg("name", name));
+    }
+    Ok(Aggregate { function, column })
+}
+
+// Nùmeros antes que texto; entre nùmeros orden numèrico y entre textos orden lexicogràfico
+pub fn compare_fields(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+// Lee un registro CSV (RFC 4180): campos entre comillas pueden tener separadores, "" y saltos de lìnea.
+// Devuelve None al final de la entrada
+pub fn read_record<R: BufRead>(reader: &mut R, delimiter: u8) -> io::Result<Option<Vec<String>>> {
+    let mut fields = Vec::new();
+    let mut field = Vec::new();
+    let mut line = Vec::new();
+    let mut in_quotes = false;
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            if in_quotes {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "comillas sin cerrar"));
+            }
+            if fields.is_empty() && field.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+
+        let mut i = 0;
+        while i < line.len() {
+            let b = line[i];
+            if in_quotes {
+                if b != b'"' {
+                    field.push(b);
+                } else if line.get(i + 1) == Some(&b'"') {
+                    field.push(b'"');
+                    i += 1;
+                } else {
+                    in_quotes = false;
+                }
+            } else if b == b'"' && field.is_empty() {
+                in_quotes = true;
+            } else if b == delimiter {
+                fields.push(to_string(std::mem::take(&mut field))?);
+            } else if b == b'\n' || (b == b'\r' && matches!(line.get(i + 1), Some(b'\n') | None)) {
+                // Fin de lìnea
+            } else {
+                field.push(b);
+            }
+            i += 1;
+        }
+        if !in_quotes {
+            break;
+        }
+    }
+
+    fields.push(to_string(field)?);
+    Ok(Some(fields))
+}
+
+fn to_string(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "el texto no es utf-8 vàlido"))
+}
+
+// Los errores de `read_record`: comillas sin cerrar (UnexpectedEof), texto que no es UTF-8 (InvalidData) o de lectura
+fn record_error(e: &io::Error) -> Message {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => Message::key("csv_unclosed_quote"),
+        io::ErrorKind::InvalidData => Message::key("csv_invalid_utf8"),
+        _ => Message::key("input_read_failed"),
+    }
+}
+
+pub fn write_record<W: Write>(out: &mut W, record: &[String], delimiter: u8) -> io::Result<()> {
+    for (i, field) in record.iter().enumerate() {
+        if i > 0 {
+            out.write_all(&[delimiter])?;
+        }
+        if field.bytes().any(|b| b == delimiter || matches!(b, b'"' | b'\n' | b'\r')) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+// Filas de datos ya validadas (mismo nùmero de campos que el encabezado), sin lìneas vacìas
+struct Rows<R> {
+    reader: R,
+    delimiter: u8,
+    columns: usize,
+    line: usize,
+}
+
+impl<R: BufRead> Iterator for Rows<R> {
+    type Item = Result<Vec<String>, Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line += 1;
+            let record = match read_record(&mut self.reader, self.delimiter) {
+                Ok(Some(record)) => record,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(Message::key("csv_invalid_row").arg("row", self.line).arg_message("error", record_error(&e)))),
+            };
+            if record.len() == 1 && record[0].is_empty() {
+                continue;
+            }
+            if record.len() != self.columns {
+                return Some(Err(Message::key("csv_field_count").arg("row", self.line).arg("found", record.len()).arg("expected", self.columns)));
+            }
+            return Some(Ok(record));
+        }
+    }
+}
+
+// Destino de las filas de salida: en memoria (con lìmite) o un temporal que luego reemplaza al archivo de save=
+struct Output {
+    out: Box<dyn Write>,
+    inline: Option<SharedBuffer>,
+    format: CsvFormat,
+    delimiter: u8,
+    header: Vec<String>,
+    // Columnas que se escriben como nùmero en JSON (resultados de agregados)
+    numeric: Vec<bool>,
+    rows: usize,
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self.0.borrow_mut();
+        if data.len() + buf.len() > MAX_INLINE_OUTPUT {
+            return Err(io::Error::other("salida demasiado grande"));
+        }
+        data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn write_error(&self, e: io::Error) -> Message {
+        if self.inline.is_some() && e.kind() == io::ErrorKind::Other {
+            Message::key("csv_output_too_large").arg("max", MAX_INLINE_OUTPUT)
+        } else {
+            Message::key("csv_write_failed")
+        }
+    }
+
+    fn start(&mut self) -> Result<(), Message> {
+        let result = match self.format {
+            CsvFormat::Csv => write_record(&mut self.out, &self.header, self.delimiter),
+            CsvFormat::Json => self.out.write_all(b"["),
+        };
+        result.map_err(|e| self.write_error(e))
+    }
+
+    fn row(&mut self, row: &[String]) -> Result<(), Message> {
+        let result = match self.format {
+            CsvFormat::Csv => write_record(&mut self.out, row, self.delimiter),
+            CsvFormat::Json => {
+                let fields: Vec<String> = self.header.iter().zip(row).zip(&self.numeric).map(|((name, value), numeric)| {
+                    let value = if *numeric { value.clone() } else { serde_json::to_string(value).unwrap() };
+                    format!("{}:{}", serde_json::to_string(name).unwrap(), value)
+                }).collect();
+                let separator = if self.rows == 0 { "" } else { "," };
+                write!(self.out, "{}{{{}}}", separator, fields.join(","))
+            }
+        };
+        self.rows += 1;
+        result.map_err(|e| self.write_error(e))
+    }
+
+    fn finish(mut self) -> Result<(Option<String>, usize), Message> {
+        if self.format == CsvFormat::Json {
+            self.out.write_all(b"]").map_err(|e| self.write_error(e))?;
+        }
+        self.out.flush().map_err(|e| self.write_error(e))?;
+        drop(self.out);
+        let inline = self.inline.map(|buffer| String::from_utf8_lossy(&buffer.0.borrow()).into_owned());
+        Ok((inline, self.rows))
+    }
+}
+
+// Borra los bloques ordenados y el temporal de salida si la tarea termina antes de tiempo
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// El resultado es la tabla (texto tal cual) o, con save=, el aviso de dònde se guardò
+pub fn run_csv(input: &InputSource, spec: &CsvSpec) -> Result<Message, ApiError> {
+    let mut reader = BufReader::new(input.open()?);
+    let header = match read_record(&mut reader, spec.delimiter) {
+        Ok(Some(header)) if header.iter().any(|h| !h.is_empty()) => header,
+        Ok(_) => return Err(ApiError::invalid_input(Message::key("csv_empty"))),
+        Err(e) => return Err(ApiError::invalid_input(Message::key("csv_invalid_header").arg_message("error", record_error(&e)))),
+    };
+    let unknown = |name: &str, columns: &[String]| Message::key("csv_unknown_column").arg("name", name).arg("columns", columns.join(", "));
+    let column = |name: &str| header.iter().position(|h| h == name).ok_or_else(|| unknown(name, &header));
+
+    let filters = spec.filters.iter().map(|f| column(&f.column).map(|i| (i, f))).collect::<Result<Vec<_>, _>>()?;
+    let group = spec.group_by.as_deref().map(column).transpose()?;
+    let aggregates = spec.aggregates.iter().map(|a| a.column.as_deref().map(column).transpose().map(|i| (i, a.function)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let grouped = group.is_some() || !aggregates.is_empty();
+
+    // Con agregados el orden se aplica sobre las columnas del resultado
+    let out_header: Vec<String> = if grouped {
+        group.map(|g| header[g].clone()).into_iter().chain(spec.aggregates.iter().map(Aggregate::header)).collect()
+    } else {
+        header.clone()
+    };
+    let sort = match &spec.sort {
+        Some(key) => Some((out_header.iter().position(|h| *h == key.column)
+            .ok_or_else(|| unknown(&key.column, &out_header))?, key.descending)),
+        None => None,
+    };
+
+    let mut temps = TempFiles(Vec::new());
+    let (out, inline): (Box<dyn Write>, _) = match &spec.save {
+        Some(name) => {
+            let (_, path) = resolve_name(name).ok_or(ApiError::invalid_name("save"))?;
+            let (tmp, file) = create_temp(&path).map_err(|_| ApiError::internal(Message::key("file_write_failed")))?;
+            temps.0.push(tmp);
+            (Box::new(BufWriter::new(file)), None)
+        }
+        None => {
+            let buffer = SharedBuffer::default();
+            (Box::new(buffer.clone()), Some(buffer))
+        }
+    };
+    let numeric = out_header.iter().enumerate().map(|(i, _)| grouped && (group.is_none() || i > 0)).collect();
+    let mut output = Output { out, inline, format: spec.format, delimiter: spec.delimiter, header: out_header, numeric, rows: 0 };
+    output.start()?;
+
+    let rows = Rows { reader, delimiter: spec.delimiter, columns: header.len(), line: 1 }
+        .filter(|row| row.as_ref().map_or(true, |row| filters.iter().all(|(i, f)| f.matches(&row[*i]))));
+    let limit = spec.limit.unwrap_or(usize::MAX);
+    let mut runs = 0;
+
+    if grouped {
+        let mut table = aggregate(rows, group, &aggregates, &header)?;
+        if let Some((column, descending)) = sort {
+            table.sort_by(|a, b| ordered(compare_fields(&a[column], &b[column]), descending));
+        }
+        for row in table.iter().take(limit) {
+            output.row(row)?;
+        }
+    } else if let Some((column, descending)) = sort {
+        runs = external_sort(rows, column, descending, spec.run_bytes, &mut temps, |row| {
+            if output.rows >= limit {
+                return Ok(false);
+            }
+            output.row(&row).map(|_| true)
+        })?;
+    } else {
+        for row in rows {
+            if output.rows >= limit {
+                break;
+            }
+            output.row(&row?)?;
+        }
+    }
+
+    let (inline, count) = output.finish()?;
+    match (inline, &spec.save) {
+        (Some(text), _) => Ok(text.into()),
+        (None, Some(name)) => {
+            let (key, path) = resolve_name(name).ok_or(ApiError::invalid_name("save"))?;
+            let tmp = temps.0.remove(0);
+            File::open(&tmp).and_then(|f| f.sync_all()).and_then(|_| replace_staged(&key, &tmp, &path))
+                .map_err(|_| ApiError::internal(Message::key("file_write_failed")))?;
+            let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let saved = Message::key(if runs > 0 { "csv_saved_sorted" } else { "csv_saved" });
+            Ok(saved.arg("path", path.display()).arg("rows", count).arg("bytes", bytes).arg("runs", runs))
+        }
+        (None, None) => unreachable!(),
+    }
+}
+
+fn ordered(ordering: Ordering, descending: bool) -> Ordering {
+    if descending { ordering.reverse() } else { ordering }
+}
+
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+fn aggregate<I>(rows: I, group: Option<usize>, aggregates: &[(Option<usize>, AggregateFn)], header: &[String]) -> Result<Vec<Vec<String>>, Message>
+where
+    I: Iterator<Item = Result<Vec<String>, Message>>,
+{
+    let mut groups: BTreeMap<String, Vec<Accumulator>> = BTreeMap::new();
+    if group.is_none() {
+        groups.insert(String::new(), aggregates.iter().map(|_| Accumulator::default()).collect());
+    }
+
+    for row in rows {
+        let row = row?;
+        let key = group.map(|g| row[g].clone()).unwrap_or_default();
+        if !groups.contains_key(&key) && groups.len() >= MAX_GROUPS {
+            return Err(Message::key("csv_too_many_groups").arg("max", MAX_GROUPS));
+        }
+        let accumulators = groups.entry(key).or_insert_with(|| aggregates.iter().map(|_| Accumulator::default()).collect());
+
+        for ((column, function), acc) in aggregates.iter().zip(accumulators.iter_mut()) {
+            let Some(column) = column else {
+                acc.count += 1;
+                continue;
+            };
+            // Las celdas vacìas no cuentan
+            let field = row[*column].trim();
+            if field.is_empty() {
+                continue;
+            }
+            if *function == AggregateFn::Count {
+                acc.count += 1;
+                continue;
+            }
+            let value = field.parse::<f64>().ok().filter(|v| v.is_finite())
+                .ok_or_else(|| Message::key("csv_not_numeric").arg("value", field).arg("column", &header[*column]))?;
+            acc.count += 1;
+            acc.sum += value;
+            acc.min = Some(acc.min.map_or(value, |m| m.min(value)));
+            acc.max = Some(acc.max.map_or(value, |m| m.max(value)));
+        }
+    }
+
+    Ok(groups.into_iter().map(|(key, accumulators)| {
+        let values = aggregates.iter().zip(accumulators).map(|((_, function), acc)| match function {
+            AggregateFn::Count => acc.count.to_string(),
+            AggregateFn::Sum => acc.sum.to_string(),
+            AggregateFn::Avg if acc.count > 0 => (acc.sum / acc.count as f64).to_string(),
+            AggregateFn::Min if acc.min.is_some() => acc.min.unwrap().to_string(),
+            AggregateFn::Max if acc.max.is_some() => acc.max.unwrap().to_string(),
+            _ => "null".to_string(),
+        });
+        group.map(|_| key).into_iter().chain(values).collect()
+    }).collect())
+}
+
+// Ordena en bloques de hasta `run_bytes` en memoria; si la entrada no cabe en un bloque, cada bloque
+// se escribe ordenado en disco y luego se mezclan con un heap. El orden es estable.
+// `emit` devuelve false para cortar (limit). Devuelve cuàntos bloques se escribieron en disco
+fn external_sort<I, F>(rows: I, column: usize, descending: bool, run_bytes: usize, temps: &mut TempFiles, mut emit: F) -> Result<usize, Message>
+where
+    I: Iterator<Item = Result<Vec<String>, Message>>,
+    F: FnMut(Vec<String>) -> Result<bool, Message>,
+{
+    let compare = |a: &Vec<String>, b: &Vec<String>| ordered(compare_fields(&a[column], &b[column]), descending);
+    let mut buffer: Vec<Vec<String>> = Vec::new();
+    let mut buffered = 0;
+    let mut runs: Vec<PathBuf> = Vec::new();
+
+    let spill = |buffer: &mut Vec<Vec<String>>, temps: &mut TempFiles| -> Result<PathBuf, Message> {
+        buffer.sort_by(compare);
+        let (path, file) = create_temp(&Path::new(STORAGE_DIR).join("csv_bloque")).map_err(|_| Message::key("csv_run_write_failed"))?;
+        temps.0.push(path.clone());
+        let mut out = BufWriter::new(file);
+        for row in buffer.drain(..) {
+            write_record(&mut out, &row, b',').map_err(|_| Message::key("csv_run_write_failed"))?;
+        }
+        out.flush().map_err(|_| Message::key("csv_run_write_failed"))?;
+        Ok(path)
+    };
+
+    for row in rows {
+        let row = row?;
+        buffered += row.iter().map(|f| f.len() + 24).sum::<usize>() + 24;
+        buffer.push(row);
+        if buffered >= run_bytes {
+            runs.push(spill(&mut buffer, temps)?);
+            buffered = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        buffer.sort_by(compare);
+        for row in buffer {
+            if !emit(row)? {
+                break;
+            }
+        }
+        return Ok(0);
+    }
+    if !buffer.is_empty() {
+        runs.push(spill(&mut buffer, temps)?);
+    }
+
+    let mut readers = Vec::new();
+    for path in &runs {
+        readers.push(BufReader::new(File::open(path).map_err(|_| Message::key("csv_run_read_failed"))?));
+    }
+    let next = |run: usize, readers: &mut Vec<BufReader<File>>| -> Result<Option<Vec<String>>, Message> {
+        read_record(&mut readers[run], b',').map_err(|_| Message::key("csv_run_read_failed"))
+    };
+
+    let mut heap = BinaryHeap::new();
+    for run in 0..readers.len() {
+        if let Some(row) = next(run, &mut readers)? {
+            heap.push(Head { row, run, column, descending });
+        }
+    }
+    while let Some(Head { row, run, .. }) = heap.pop() {
+        if !emit(row)? {
+            break;
+        }
+        if let Some(row) = next(run, &mut readers)? {
+            heap.push(Head { row, run, column, descending });
+        }
+    }
+    Ok(runs.len())
+}
+
+// Primera fila pendiente de un bloque. BinaryHeap saca el mayor, asì que el orden està invertido;
+// a igual clave sale primero el bloque anterior para mantener la estabilidad
+struct Head {
+    row: Vec<String>,
+    run: usize,
+    column: usize,
+    descending: bool,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        ordered(compare_fields(&self.row[self.column], &other.row[self.column]), self.descending)
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use crate::error_responses::ApiError;
+    use crate::storage::InputSource;
+
+    use super::{parse_csv_params, read_record, run_csv, write_record, Comparison};
+
+    const DATA: &str = "nombre,ciudad,precio\nana,Cartago,100\nluis,\"San José, centro\",25.5\n\nmarta,Cartago,300\npedro,Heredia,\n";
+
+    fn run(query: &[(&str, &str)]) -> Result<String, ApiError> {
+        let params: HashMap<String, String> = query.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        run_csv(&InputSource::Inline(DATA.to_string()), &parse_csv_params(&params)?).map(|m| m.to_string())
+    }
+
+    #[test]
+    fn test_read_and_write_records() {
+        let mut input = Cursor::new("a,\"b \"\"c\"\"\",\"d\r\ne\"\r\nx,y,z");
+        assert_eq!(read_record(&mut input, b',').unwrap(), Some(vec!["a".into(), "b \"c\"".into(), "d\r\ne".into()]));
+        assert_eq!(read_record(&mut input, b',').unwrap(), Some(vec!["x".into(), "y".into(), "z".into()]));
+        assert_eq!(read_record(&mut input, b',').unwrap(), None);
+        assert!(read_record(&mut Cursor::new("\"sin cerrar"), b',').is_err());
+
+        let mut out = Vec::new();
+        write_record(&mut out, &["a,b".into(), "c\"d".into(), "e".into()], b',').unwrap();
+        assert_eq!(out, b"\"a,b\",\"c\"\"d\",e\n");
+    }
+
+    #[test]
+    fn test_filter_sort_and_limit() {
+        assert_eq!(run(&[("sort", "-precio")]).unwrap(), "nombre,ciudad,precio\npedro,Heredia,\nmarta,Cartago,300\nana,Cartago,100\nluis,\"San José, centro\",25.5\n");
+        assert_eq!(run(&[("where", "precio>=50,ciudad!=Heredia"), ("sort", "nombre")]).unwrap(), "nombre,ciudad,precio\nana,Cartago,100\nmarta,Cartago,300\n");
+        assert_eq!(run(&[("where", "ciudad~José"), ("format", "json")]).unwrap(), "[{\"nombre\":\"luis\",\"ciudad\":\"San José, centro\",\"precio\":\"25.5\"}]");
+        assert_eq!(run(&[("limit", "1")]).unwrap(), "nombre,ciudad,precio\nana,Cartago,100\n");
+        assert!(run(&[("sort", "edad")]).unwrap_err().message().contains("Columna desconocida: 'edad'"));
+        assert_eq!(parse_csv_params(&HashMap::from([("where".to_string(), "a<=3".to_string())])).unwrap().filters[0].comparison, Comparison::Le);
+        assert_eq!(parse_csv_params(&HashMap::from([("where".to_string(), "precio".to_string())])).unwrap_err().field(), Some("where"));
+    }
+
+    #[test]
+    fn test_group_and_aggregate() {
+        assert_eq!(run(&[("group", "ciudad"), ("agg", "count,sum:precio,avg:precio"), ("sort", "-count")]).unwrap(),
+            "ciudad,count,sum(precio),avg(precio)\nCartago,2,400,200\nHeredia,1,0,null\n\"San José, centro\",1,25.5,25.5\n");
+        assert_eq!(run(&[("agg", "min:precio,max:precio,count:precio"), ("format", "json")]).unwrap(),
+            "[{\"min(precio)\":25.5,\"max(precio)\":300,\"count(precio)\":3}]");
+        assert!(run(&[("group", "ciudad"), ("agg", "sum:nombre")]).unwrap_err().message().contains("no es numèrico"));
+        assert!(run(&[("agg", "median:precio")]).is_err());
+    }
+
+    #[test]
+    fn test_external_sort_on_disk() {
+        let mut data = String::from("id,valor\n");
+        for i in 0..2000u64 {
+            data.push_str(&format!("{},{}\n", i, (i * 7919) % 1000));
+        }
+        let params = HashMap::from([("sort".to_string(), "valor".to_string()), ("save".to_string(), "csv_ordenado".to_string())]);
+        let spec = super::CsvSpec { run_bytes: 4096, ..parse_csv_params(&params).unwrap() };
+        let report = run_csv(&InputSource::Inline(data), &spec).unwrap().to_string();
+        assert!(report.contains("2000 filas") && report.contains("bloques ordenados en disco"), "{}", report);
+
+        let sorted = std::fs::read_to_string("archivos/csv_ordenado.txt").unwrap();
+        let values: Vec<u64> = sorted.lines().skip(1).map(|l| l.split(',').nth(1).unwrap().parse().unwrap()).collect();
+        assert_eq!(values.len(), 2000);
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        // Estable: a igual valor se conserva el orden de los ids
+        let ids: Vec<u64> = sorted.lines().skip(1).filter(|l| l.ends_with(",0")).map(|l| l.split(',').next().unwrap().parse().unwrap()).collect();
+        assert_eq!(ids, vec![0, 1000]);
+        let _ = std::fs::remove_file("archivos/csv_ordenado.txt");
+        assert!(!std::fs::read_dir("archivos").unwrap().any(|e| e.unwrap().file_name().to_string_lossy().contains("csv_bloque")));
+    }
+}
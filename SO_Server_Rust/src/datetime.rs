@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Fechas con zona horaria, precisiòn y formato para /timestamp, /dateparse, /dateconvert, /dateadd y /datesub
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Utc,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    Secs,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Rfc3339,
+    Rfc2822,
+    Unix,
+    // Patròn strftime, por ejemplo "%Y-%m-%d %H:%M"
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeFormat {
+    pub zone: Zone,
+    pub precision: Precision,
+    pub format: OutputFormat,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        TimeFormat { zone: Zone::Utc, precision: Precision::Secs, format: OutputFormat::Rfc3339 }
+    }
+}
+
+// Duraciòn con partes de calendario (años, meses, dìas) y partes exactas (horas hacia abajo)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DurationSpec {
+    pub negative: bool,
+    pub months: u32,
+    pub days: u64,
+    pub exact: TimeDelta,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateOp {
+    Now,
+    // Interpreta `input` y lo devuelve con el formato de salida (sirve tambièn para convertir de zona)
+    Parse { input: String, input_format: Option<String> },
+    // Suma (o resta si la duraciòn es negativa) a `input` o a la hora actual
+    Add { input: Option<String>, input_format: Option<String>, duration: DurationSpec },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateRequest {
+    pub op: DateOp,
+    // Zona con la que se interpretan entradas sin desfase, por ejemplo "2025-01-01 10:00"
+    pub from: Zone,
+    pub output: TimeFormat,
+}
+
+pub fn parse_zone(value: &str) -> Result<Zone, Message> {
+    // El '+' de la query llega como espacio si no se codificò como %2B
+    let value = match value.strip_prefix(' ') {
+        Some(rest) => format!("+{}", rest),
+        None => value.to_string(),
+    };
+
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Ok(Zone::Utc);
+    }
+
+    if let Some(sign) = value.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let digits: String = value[1..].chars().filter(|c| *c != ':').collect();
+        let invalid = || Message::key("invalid_offset").arg("value", &value);
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<i32>().ok(), Some(0)),
+            4 => (digits[..2].parse::<i32>().ok(), digits[2..].parse::<i32>().ok()),
+            _ => (None, None),
+        };
+        let seconds = match (hours, minutes) {
+            (Some(h), Some(m)) if h <= 23 && m <= 59 => (h * 3600 + m * 60) * if sign == '-' { -1 } else { 1 },
+            _ => return Err(invalid()),
+        };
+        return FixedOffset::east_opt(seconds).map(Zone::Fixed).ok_or_else(invalid);
+    }
+
+    value.parse::<Tz>().map(Zone::Named).map_err(|_| Message::key("unknown_zone").arg("value", &value))
+}
+
+pub fn parse_precision(value: &str) -> Result<Precision, Message> {
+    match value {
+        "s" => Ok(Precision::Secs),
+        "ms" => Ok(Precision::Millis),
+        "us" => Ok(Precision::Micros),
+        "ns" => Ok(Precision::Nanos),
+        _ => Err(Message::key("invalid_choice").arg("field", "precision").arg("options", "'s', 'ms', 'us', 'ns'")),
+    }
+}
+
+pub fn parse_output_format(value: &str) -> Result<OutputFormat, Message> {
+    match value {
+        "rfc3339" | "iso" => Ok(OutputFormat::Rfc3339),
+        "rfc2822" => Ok(OutputFormat::Rfc2822),
+        "unix" | "epoch" => Ok(OutputFormat::Unix),
+        pattern if pattern.contains('%') => {
+            check_strftime(pattern)?;
+            Ok(OutputFormat::Custom(pattern.to_string()))
+        }
+        _ => Err(Message::key("invalid_date_format")),
+    }
+}
+
+fn check_strftime(pattern: &str) -> Result<(), Message> {
+    if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        return Err(Message::key("invalid_strftime").arg("pattern", pattern));
+    }
+    Ok(())
+}
+
+// Duraciones como "90m", "1d12h", "-2w", "1y6mo" o "250ms". Unidades: y, mo, w, d, h, m, s, ms, us, ns
+pub fn parse_duration(value: &str) -> Result<DurationSpec, Message> {
+    let invalid = || Message::key("invalid_duration").arg("value", value);
+    let (negative, mut rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().trim_start_matches('+')),
+    };
+
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut spec = DurationSpec { negative, ..DurationSpec::default() };
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let amount = rest[..digits].parse::<i64>().map_err(|_| invalid())?;
+        let unit_len = rest[digits..].find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len() - digits);
+        let unit = &rest[digits..digits + unit_len];
+
+        let added = match unit {
+            "y" => { spec.months = amount.checked_mul(12).and_then(|m| u32::try_from(m).ok()).and_then(|m| spec.months.checked_add(m)).ok_or_else(invalid)?; true }
+            "mo" => { spec.months = u32::try_from(amount).ok().and_then(|m| spec.months.checked_add(m)).ok_or_else(invalid)?; true }
+            "w" => { spec.days = (amount as u64).checked_mul(7).and_then(|d| spec.days.checked_add(d)).ok_or_else(invalid)?; true }
+            "d" => { spec.days = spec.days.checked_add(amount as u64).ok_or_else(invalid)?; true }
+            "h" => add_exact(&mut spec, TimeDelta::try_hours(amount)),
+            "m" => add_exact(&mut spec, TimeDelta::try_minutes(amount)),
+            "s" => add_exact(&mut spec, TimeDelta::try_seconds(amount)),
+            "ms" => add_exact(&mut spec, TimeDelta::try_milliseconds(amount)),
+            "us" => add_exact(&mut spec, Some(TimeDelta::microseconds(amount))),
+            "ns" => add_exact(&mut spec, Some(TimeDelta::nanoseconds(amount))),
+            _ => false,
+        };
+
+        if !added {
+            return Err(invalid());
+        }
+        rest = &rest[digits + unit_len..];
+    }
+
+    Ok(spec)
+}
+
+fn add_exact(spec: &mut DurationSpec, delta: Option<TimeDelta>) -> bool {
+    match delta.and_then(|d| spec.exact.checked_add(&d)) {
+        Some(total) => {
+            spec.exact = total;
+            true
+        }
+        None => false,
+    }
+}
+
+// Paràmetros comunes de salida: tz, precision y format. El error indica cuàl de ellos es invàlido
+pub fn parse_time_format(params: &HashMap<String, String>, zone_key: &str) -> Result<TimeFormat, ApiError> {
+    let mut output = TimeFormat::default();
+    if let Some(zone) = params.get(zone_key) {
+        output.zone = parse_zone(zone).map_err(|e| ApiError::invalid(zone_key, e))?;
+    }
+    if let Some(precision) = params.get("precision") {
+        output.precision = parse_precision(precision).map_err(|e| ApiError::invalid("precision", e))?;
+    }
+    if let Some(format) = params.get("format") {
+        output.format = parse_output_format(format).map_err(|e| ApiError::invalid("format", e))?;
+    }
+    Ok(output)
+}
+
+// Interpreta una fecha: RFC 3339, RFC 2822, segundos Unix, el patròn indicado o formatos locales comunes
+pub fn parse_input(input: &str, input_format: Option<&str>, from: Zone) -> Result<DateTime<Utc>, Message> {
+    let input = input.trim();
+
+    if let Some(pattern) = input_format {
+        check_strftime(pattern)?;
+        if let Ok(dt) = DateTime::parse_from_str(input, pattern) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+        let naive = NaiveDateTime::parse_from_str(input, pattern)
+            .or_else(|_| NaiveDate::parse_from_str(input, pattern).map(|d| d.and_hms_opt(0, 0, 0).unwrap()))
+            .map_err(|e| Message::key("date_pattern_mismatch").arg("input", input).arg("pattern", pattern).arg("error", e))?;
+        return localize(naive, from);
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(input) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(secs) = input.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0).ok_or_else(|| Message::key("timestamp_out_of_range").arg("value", secs));
+    }
+
+    for pattern in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, pattern) {
+            return localize(naive, from);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return localize(date.and_hms_opt(0, 0, 0).unwrap(), from);
+    }
+
+    Err(Message::key("unknown_date_format").arg("input", input))
+}
+
+// Una hora local sin desfase se ubica en la zona `from`. En un cambio de horario se toma la primera
+fn localize(naive: NaiveDateTime, from: Zone) -> Result<DateTime<Utc>, Message> {
+    let local = match from {
+        Zone::Utc => Some(Utc.from_utc_datetime(&naive)),
+        Zone::Fixed(offset) => offset.from_local_datetime(&naive).earliest().map(|d| d.with_timezone(&Utc)),
+        Zone::Named(tz) => tz.from_local_datetime(&naive).earliest().map(|d| d.with_timezone(&Utc)),
+    };
+    local.ok_or_else(|| Message::key("nonexistent_local_time").arg("time", naive))
+}
+
+fn apply_duration<Z: TimeZone>(dt: DateTime<Z>, duration: &DurationSpec) -> Option<DateTime<Z>> {
+    if duration.negative {
+        dt.checked_sub_months(Months::new(duration.months))?
+            .checked_sub_days(Days::new(duration.days))?
+            .checked_sub_signed(duration.exact)
+    } else {
+        dt.checked_add_months(Months::new(duration.months))?
+            .checked_add_days(Days::new(duration.days))?
+            .checked_add_signed(duration.exact)
+    }
+}
+
+// None si el instante no cabe en la precisiòn pedida (nanosegundos Unix fuera de ~1677-2262)
+fn format_in<Z: TimeZone>(dt: DateTime<Z>, output: &TimeFormat) -> Option<String>
+where
+    Z::Offset: std::fmt::Display,
+{
+    let use_z = output.zone == Zone::Utc;
+    let text = match &output.format {
+        OutputFormat::Rfc3339 => {
+            let seconds = match output.precision {
+                Precision::Secs => SecondsFormat::Secs,
+                Precision::Millis => SecondsFormat::Millis,
+                Precision::Micros => SecondsFormat::Micros,
+                Precision::Nanos => SecondsFormat::Nanos,
+            };
+            dt.to_rfc3339_opts(seconds, use_z)
+        }
+        OutputFormat::Rfc2822 => dt.to_rfc2822(),
+        OutputFormat::Unix => match output.precision {
+            Precision::Secs => dt.timestamp().to_string(),
+            Precision::Millis => dt.timestamp_millis().to_string(),
+            Precision::Micros => dt.timestamp_micros().to_string(),
+            Precision::Nanos => dt.timestamp_nanos_opt()?.to_string(),
+        },
+        OutputFormat::Custom(pattern) => dt.format(pattern).to_string(),
+    };
+    Some(text)
+}
+
+// Pasa el instante a la zona de salida, aplica la duraciòn (los dìas y meses siguen el calendario local) y lo formatea
+fn render(instant: DateTime<Utc>, duration: Option<&DurationSpec>, output: &TimeFormat) -> Result<String, Message> {
+    let out_of_range = || Message::key("date_out_of_range");
+    match output.zone {
+        Zone::Utc => {
+            let dt = match duration { Some(d) => apply_duration(instant, d).ok_or_else(out_of_range)?, None => instant };
+            format_in(dt, output).ok_or_else(out_of_range)
+        }
+        Zone::Fixed(offset) => {
+            let dt = instant.with_timezone(&offset);
+            let dt = match duration { Some(d) => apply_duration(dt, d).ok_or_else(out_of_range)?, None => dt };
+            format_in(dt, output).ok_or_else(out_of_range)
+        }
+        Zone::Named(tz) => {
+            let dt = instant.with_timezone(&tz);
+            let dt = match duration { Some(d) => apply_duration(dt, d).ok_or_else(out_of_range)?, None => dt };
+            format_in(dt, output).ok_or_else(out_of_range)
+        }
+    }
+}
+
+pub fn run_date_request(request: &DateRequest) -> Result<String, Message> {
+    match &request.op {
+        DateOp::Now => render(Utc::now(), None, &request.output),
+        DateOp::Parse { input, input_format } => {
+            let instant = parse_input(input, input_format.as_deref(), request.from)?;
+            render(instant, None, &request.output)
+        }
+        DateOp::Add { input, input_format, duration } => {
+            let instant = match input {
+                Some(input) => parse_input(input, input_format.as_deref(), request.from)?,
+                None => Utc::now(),
+            };
+            render(instant, Some(duration), &request.output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeDelta;
+
+    use super::{parse_duration, parse_output_format, parse_zone, run_date_request, DateOp, DateRequest, DurationSpec, OutputFormat, Precision, TimeFormat, Zone};
+
+    fn request(op: DateOp, from: &str, output: TimeFormat) -> DateRequest {
+        DateRequest { op, from: parse_zone(from).unwrap(), output }
+    }
+
+    fn parse(input: &str) -> DateOp {
+        DateOp::Parse { input: input.to_string(), input_format: None }
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        assert_eq!(parse_zone("UTC").unwrap(), Zone::Utc);
+        assert!(matches!(parse_zone("America/Costa_Rica").unwrap(), Zone::Named(_)));
+        assert_eq!(parse_zone("-06:00").unwrap(), parse_zone("-0600").unwrap());
+        assert_eq!(parse_zone(" 05:30").unwrap(), parse_zone("+05:30").unwrap());
+        assert!(parse_zone("Marte/Olympus").is_err());
+        assert!(parse_zone("+25:00").is_err());
+        assert!(parse_zone("+ñ12").is_err());
+    }
+
+    #[test]
+    fn test_now_with_precision_and_formats() {
+        let ms = TimeFormat { precision: Precision::Millis, ..TimeFormat::default() };
+        let now = run_date_request(&request(DateOp::Now, "UTC", ms)).unwrap();
+        assert_eq!(now.len(), "2025-01-01T00:00:00.000Z".len());
+
+        let unix = TimeFormat { format: OutputFormat::Unix, ..TimeFormat::default() };
+        assert!(run_date_request(&request(DateOp::Now, "UTC", unix)).unwrap().parse::<i64>().is_ok());
+        assert!(parse_output_format("%Q%").is_err());
+    }
+
+    #[test]
+    fn test_parse_and_convert_between_zones() {
+        let to_cr = TimeFormat { zone: parse_zone("America/Costa_Rica").unwrap(), ..TimeFormat::default() };
+        let result = run_date_request(&request(parse("2025-03-01T12:00:00Z"), "UTC", to_cr.clone())).unwrap();
+        assert_eq!(result, "2025-03-01T06:00:00-06:00");
+
+        // Entrada sin desfase interpretada en Madrid (UTC+1 en invierno)
+        let utc = TimeFormat::default();
+        assert_eq!(run_date_request(&request(parse("2025-01-15 10:30"), "Europe/Madrid", utc.clone())).unwrap(), "2025-01-15T09:30:00Z");
+
+        let custom = TimeFormat { format: OutputFormat::Custom("%d/%m/%Y".to_string()), ..TimeFormat::default() };
+        let op = DateOp::Parse { input: "31-12-2024".to_string(), input_format: Some("%d-%m-%Y".to_string()) };
+        assert_eq!(run_date_request(&request(op, "UTC", custom)).unwrap(), "31/12/2024");
+
+        let rfc2822 = TimeFormat { format: OutputFormat::Rfc2822, ..TimeFormat::default() };
+        assert_eq!(run_date_request(&request(parse("0"), "UTC", rfc2822)).unwrap(), "Thu, 1 Jan 1970 00:00:00 +0000");
+        assert!(run_date_request(&request(parse("ayer"), "UTC", utc)).is_err());
+    }
+
+    #[test]
+    fn test_duration_arithmetic() {
+        assert_eq!(parse_duration("1d2h30m").unwrap(), DurationSpec { negative: false, months: 0, days: 1, exact: TimeDelta::minutes(150) });
+        assert!(parse_duration("-1mo").unwrap().negative);
+        assert!(parse_duration("3x").is_err() && parse_duration("h").is_err() && parse_duration("").is_err());
+
+        let add = |input: &str, duration: &str| DateOp::Add { input: Some(input.to_string()), input_format: None, duration: parse_duration(duration).unwrap() };
+        assert_eq!(run_date_request(&request(add("2024-01-31T00:00:00Z", "1mo"), "UTC", TimeFormat::default())).unwrap(), "2024-02-29T00:00:00Z");
+        assert_eq!(run_date_request(&request(add("2025-01-01T00:00:00Z", "-90s"), "UTC", TimeFormat::default())).unwrap(), "2024-12-31T23:58:30Z");
+
+        // Un dìa en Madrid cruzando el cambio de horario conserva la hora local
+        let madrid = TimeFormat { zone: parse_zone("Europe/Madrid").unwrap(), ..TimeFormat::default() };
+        assert_eq!(run_date_request(&request(add("2025-03-29T12:00:00+01:00", "1d"), "UTC", madrid)).unwrap(), "2025-03-30T12:00:00+02:00");
+    }
+}
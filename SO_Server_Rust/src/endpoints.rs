@@ -1,9 +1,13 @@
 use rand::Rng;
 use sha2::{Sha256, Digest};
 use chrono::{self, DateTime, Utc};
-use std::fs::{File, create_dir_all, remove_file};
-use std::io::Write;
-use std::path::Path;
+use std::fs::{self, create_dir_all, remove_file};
+use std::io::{ErrorKind, Read};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::text_ops::reverse_graphemes;
+use crate::storage::{file_path, list_versions, resolve_name, save_version, valid_name, version_path, write_atomic, write_lock, STORAGE_DIR};
 
 
 // Este archivo va a ser un mòdulo que va a contener la lògica de todos los endpoints
@@ -16,58 +20,184 @@ pub fn fibonacci(n: u64) -> u64{
         _ => fibonacci(n - 1) + fibonacci(n - 2),
     }
 }
-// / createfile?name=filename&content=text&repeat=X
-pub fn create_file (name : &str, content: &str) -> Result<String, String> {
-    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
-        return Err("Nombre del archivo invàlido (Solo se permiten alfanùmericos)".to_string());
+// Modo de escritura de /createfile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteMode {
+    Create,
+    Overwrite,
+    Append,
+}
+
+impl WriteMode {
+    pub fn parse(s: &str) -> Option<WriteMode> {
+        match s {
+            "create" => Some(WriteMode::Create),
+            "overwrite" => Some(WriteMode::Overwrite),
+            "append" => Some(WriteMode::Append),
+            _ => None,
+        }
     }
+}
 
-    let folder = "archivos";
-    if let Err(_) = create_dir_all(folder) {
-        return Err("No se pudo crear el directorio".to_string());
+// / createfile?name=filename&content=text&mode=create|overwrite|append
+// Con `versioning` el contenido anterior se guarda como versiòn antes de reemplazarlo
+pub fn create_file (name : &str, content: &str, mode: WriteMode, versioning: bool) -> Result<Message, ApiError> {
+    if !valid_name(name) {
+        return Err(ApiError::invalid_name("name"));
     }
 
-    let path = format!("{}/{}.txt", folder, name);
-    let path_original = Path::new(&path);
+    if create_dir_all(STORAGE_DIR).is_err() {
+        return Err(ApiError::internal(Message::key("dir_create_failed")));
+    }
+
+    let path_original = file_path(name);
+    let path = path_original.display().to_string();
 
-    if path_original.exists() {
-        return Err(format!("El archivo '{}' ya existe", path));
+    // Mientras tengamos el candado de escritura nadie màs puede leer ni escribir este archivo
+    let _guard = write_lock(&path_original);
+
+    let existed = path_original.exists();
+    if existed && mode != WriteMode::Create && versioning && save_version(name, &path_original).is_err() {
+        return Err(ApiError::internal(Message::key("version_save_failed")));
     }
 
-    match File::create(&path_original) {
-        Ok(mut file) => {
-            if let Err(_) = file.write_all(content.as_bytes()) {
-                return Err("Error escribiendo en el archivo".to_string());
-            }
-            Ok(format!("Archivo '{}' creado exitosamente", path))
+    let result = match mode {
+        WriteMode::Create => write_atomic(&path_original, content.as_bytes(), false),
+        WriteMode::Overwrite => write_atomic(&path_original, content.as_bytes(), true),
+        WriteMode::Append => {
+            let mut data = match fs::read(&path_original) {
+                Ok(data) => data,
+                Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(_) => return Err(ApiError::internal(Message::key("file_read_failed"))),
+            };
+            data.extend_from_slice(content.as_bytes());
+            write_atomic(&path_original, &data, true)
         }
-        Err(_) => return Err("No se pudo crear el archivo".to_string()),
+    };
+
+    match result {
+        Ok(_) => match mode {
+            WriteMode::Append if existed => Ok(Message::key("file_appended").arg("path", path)),
+            WriteMode::Overwrite if existed => Ok(Message::key("file_overwritten").arg("path", path)),
+            _ => Ok(Message::key("file_created").arg("path", path)),
+        },
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(ApiError::conflict(Some("name"), Message::key("file_exists").arg("path", path))),
+        Err(_) => Err(ApiError::internal(Message::key("file_write_failed"))),
     }
 }
 
 // /deletefile?name=filename
-pub fn delete_file (name: &str) -> Result<String, String> {
-    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
-        return Err("Nombre del archivo invàlido (Solo se permiten alfanùmericos)".to_string());
-    }
+pub fn delete_file (name: &str, versioning: bool) -> Result<Message, ApiError> {
+    let (key, path_original) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Err(ApiError::invalid_name("name")),
+    };
+
+    let path = path_original.display().to_string();
 
-    let folder = "archivos";
-    let path = format!("{}/{}.txt", folder, name);
-    let path_original = Path::new(&path);
+    let _guard = write_lock(&path_original);
 
-    if !path_original.exists() {
-        return Err(format!("El archivo '{}' no existe", path));
+    if versioning && save_version(&key, &path_original).is_err() {
+        return Err(ApiError::internal(Message::key("version_save_failed")));
     }
 
     match remove_file(&path_original) {
-        Ok(_) => Ok(format!("Archivo '{}' eliminado exitosamente", path)),
-        Err(_) => Err(format!("No se pudo eliminar el archivo '{}'", path)),
+        Ok(_) => Ok(Message::key("file_deleted").arg("path", path)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", path))),
+        Err(_) => Err(ApiError::internal(Message::key("file_delete_failed").arg("path", path))),
+    }
+}
+
+// /listversions?name=filename
+pub fn list_file_versions(name: &str) -> Result<Message, ApiError> {
+    let (key, _) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Err(ApiError::invalid_name("name")),
+    };
+
+    let versions = list_versions(&key).map_err(|_| ApiError::internal(Message::key("versions_read_failed")))?;
+    if versions.is_empty() {
+        return Ok(Message::key("no_versions").arg("name", key));
+    }
+
+    let list: Vec<String> = versions.iter().map(|v| {
+        let modified: DateTime<Utc> = v.modified.into();
+        format!("v{} ({} bytes, {})", v.number, v.size, modified.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+    }).collect();
+    Ok(Message::key("versions_list").arg("name", key).arg("versions", list.join(", ")))
+}
+
+// /getversion?name=filename&version=n
+pub fn get_file_version(name: &str, version: u64) -> Result<Message, ApiError> {
+    let (key, _) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Err(ApiError::invalid_name("name")),
+    };
+
+    let content = fs::read(version_path(&key, version))
+        .map_err(|_| ApiError::not_found(Some("version"), Message::key("version_not_found").arg("version", version).arg("name", name)))?;
+
+    // Una versiòn binaria (subida por PUT /files/) no se puede devolver como texto sin perder datos
+    match String::from_utf8(content) {
+        Ok(text) => Ok(text.into()),
+        Err(_) => Err(ApiError::invalid("version", Message::key("version_not_text").arg("version", version).arg("name", name))),
+    }
+}
+
+// /restoreversion?name=filename&version=n
+pub fn restore_file_version(name: &str, version: u64, versioning: bool) -> Result<Message, ApiError> {
+    let (key, path_original) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Err(ApiError::invalid_name("name")),
+    };
+
+    let _guard = write_lock(&path_original);
+
+    let content = fs::read(version_path(&key, version)).map_err(|_| ApiError::not_found(Some("version"), Message::key("version_not_found").arg("version", version).arg("name", name)))?;
+
+    // Con el versionado activo el contenido actual tambièn se guarda, asì restaurar nunca pierde datos
+    if versioning && path_original.exists() && save_version(&key, &path_original).is_err() {
+        return Err(ApiError::internal(Message::key("version_save_failed")));
+    }
+
+    match write_atomic(&path_original, &content, true) {
+        Ok(_) => Ok(Message::key("version_restored").arg("path", path_original.display()).arg("version", version)),
+        Err(_) => Err(ApiError::internal(Message::key("file_write_failed"))),
+    }
+}
+
+// /purgeversions?name=filename&keep=n&older_than=segundos
+pub fn purge_file_versions(name: &str, keep: Option<usize>, older_than: Option<u64>) -> Result<Message, ApiError> {
+    let (key, path_original) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Err(ApiError::invalid_name("name")),
+    };
+
+    let _guard = write_lock(&path_original);
+
+    let versions = list_versions(&key).map_err(|_| ApiError::internal(Message::key("versions_read_failed")))?;
+    let now = std::time::SystemTime::now();
+    let newest_kept = versions.len().saturating_sub(keep.unwrap_or(versions.len()));
+
+    let mut purged = 0;
+    for (i, v) in versions.iter().enumerate() {
+        let too_many = i < newest_kept;
+        let too_old = match older_than {
+            Some(secs) => now.duration_since(v.modified).map(|age| age.as_secs() >= secs).unwrap_or(false),
+            None => false,
+        };
+
+        if (too_many || too_old) && remove_file(version_path(&key, v.number)).is_ok() {
+            purged += 1;
+        }
     }
+
+    Ok(Message::key("versions_purged").arg("count", purged).arg("name", key))
 }
 
-// / reverse?text=abc
+// / reverse?text=abc (por grafemas, asì "café" con acento combinado no se rompe)
 pub fn rerverse_text(input: &str) -> String{
-    input.chars().rev().collect()
+    reverse_graphemes(input)
 }
 // /toupper?text=abc
 pub fn to_uppercase(input: &str) -> String {
@@ -88,7 +218,7 @@ pub fn timestamp_iso() -> String {
     //Convertimos en formato ISO
     let datetime: DateTime<Utc> = now.into();
     let iso = datetime.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
-    format!("{}", iso)
+    iso.to_string()
 }
 
 // /hash?text=abc
@@ -99,12 +229,28 @@ pub fn sha256_hash(input: &str) -> String{
     format!("{:x}", result)
 }
 
+// Igual que sha256_hash pero leyendo por bloques, para archivos grandes
+pub fn sha256_reader<R: Read>(mut reader: R) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 
 // Test de las funciones de endpoints:
 
 #[cfg(test)]
 mod test {
-    use crate::endpoints::{create_file, delete_file, fibonacci, rerverse_text, to_uppercase};
+    use crate::endpoints::{create_file, delete_file, fibonacci, get_file_version, list_file_versions, purge_file_versions, rerverse_text, restore_file_version, to_uppercase, WriteMode};
+    use crate::responses::Status;
+    use crate::storage::{version_path, versions_dir, write_atomic};
 
     use super::{generate_random_numbers, sha256_hash, timestamp_iso};
 
@@ -124,6 +270,12 @@ mod test {
         assert_eq!(hash.len(), 64);
     }
 
+    #[test]
+    fn test_sha256_reader_matches_sha256_hash() {
+        let text = "hola mundo ".repeat(2000);
+        assert_eq!(super::sha256_reader(text.as_bytes()).unwrap(), sha256_hash(&text));
+    }
+
     #[test]
     fn test_fiboncci() {
         assert_eq!(fibonacci(0), 0);
@@ -145,12 +297,81 @@ mod test {
 
     #[test]
     fn test_create_and_delete_file() {
-        let _ = delete_file("testfile");
+        let _ = delete_file("testfile", false);
 
-        let result = create_file("testfile", "contenido");
+        let result = create_file("testfile", "contenido", WriteMode::Create, false);
         assert!(result.is_ok());
 
-        let result = delete_file("testfile");
+        let result = delete_file("testfile", false);
         assert!(result.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_create_file_modes() {
+        let _ = delete_file("testfile_modos", false);
+
+        assert!(create_file("testfile_modos", "uno", WriteMode::Create, false).is_ok());
+        let error = create_file("testfile_modos", "otro", WriteMode::Create, false).unwrap_err();
+        assert_eq!((error.code(), error.field()), ("conflict", Some("name")));
+        assert!(error.message().contains("ya existe"));
+
+        assert!(create_file("testfile_modos", "dos", WriteMode::Overwrite, false).is_ok());
+        assert!(create_file("testfile_modos", "-tres", WriteMode::Append, false).is_ok());
+        assert_eq!(std::fs::read_to_string("archivos/testfile_modos.txt").unwrap(), "dos-tres");
+
+        assert!(delete_file("testfile_modos", false).is_ok());
+        let error = delete_file("testfile_modos", false).unwrap_err();
+        assert_eq!(error.status(), Status::NotFound);
+        assert!(error.message().contains("no existe"));
+    }
+
+    #[test]
+    fn test_concurrent_appends_are_not_lost() {
+        let _ = delete_file("testfile_concurrente", false);
+
+        let handles: Vec<_> = (0..8).map(|_| {
+            std::thread::spawn(|| create_file("testfile_concurrente", "x", WriteMode::Append, false).unwrap())
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string("archivos/testfile_concurrente.txt").unwrap(), "xxxxxxxx");
+        let _ = delete_file("testfile_concurrente", false);
+    }
+
+    #[test]
+    fn test_versioning_overwrite_delete_and_restore() {
+        let _ = std::fs::remove_dir_all(versions_dir("testfile_versiones"));
+        let _ = std::fs::remove_file("archivos/testfile_versiones.txt");
+
+        create_file("testfile_versiones", "uno", WriteMode::Create, true).unwrap();
+        create_file("testfile_versiones", "dos", WriteMode::Overwrite, true).unwrap();
+        delete_file("testfile_versiones", true).unwrap();
+
+        let list = list_file_versions("testfile_versiones").unwrap().to_string();
+        assert!(list.contains("v1") && list.contains("v2"));
+        assert_eq!(get_file_version("testfile_versiones", 1).unwrap().to_string(), "uno");
+        assert!(get_file_version("testfile_versiones", 9).is_err());
+
+        // Recuperamos el archivo eliminado: como no existìa no se guarda una versiòn nueva
+        restore_file_version("testfile_versiones", 2, true).unwrap();
+        assert_eq!(std::fs::read_to_string("archivos/testfile_versiones.txt").unwrap(), "dos");
+        assert!(get_file_version("testfile_versiones", 3).is_err());
+
+        let purged = purge_file_versions("testfile_versiones", Some(1), None).unwrap().to_string();
+        assert!(purged.contains("Se eliminaron 1 versiones"));
+        assert!(get_file_version("testfile_versiones", 1).is_err());
+
+        // Sin versionado restaurar no guarda el contenido actual
+        restore_file_version("testfile_versiones", 2, false).unwrap();
+        assert!(get_file_version("testfile_versiones", 3).is_err());
+
+        // Una versiòn binaria se rechaza en vez de devolverse corrupta
+        write_atomic(&version_path("testfile_versiones", 5), &[0xff, 0xfe, 0x00], false).unwrap();
+        assert_eq!(get_file_version("testfile_versiones", 5).unwrap_err().field(), Some("version"));
+
+        let _ = std::fs::remove_file("archivos/testfile_versiones.txt");
+        let _ = std::fs::remove_dir_all(versions_dir("testfile_versiones"));
+    }
+}
@@ -1,68 +1,254 @@
-// Lògica de respuesta de errores
+// Lògica de respuesta de errores.
+// Todo error que llega al cliente es un `ApiError`: un còdigo estable (para que el cliente no dependa del texto),
+// el estado HTTP, el paràmetro que lo causò si aplica y un mensaje para humanos:
+// {"status":400,"code":"missing_parameter","field":"text","error":"Falta el paràmetro 'text'"}
+// El mensaje se traduce al idioma de la conexiòn (i18n.rs); el còdigo y el campo nunca cambian
 
+use std::fmt;
 
-pub fn http_resonse_404(msg: &str) -> String {
-    let json = format!("{{\"status\" : 404, \"error\" : \"{}\"}}", msg);
-    format!(
-        "HTTP/1.0 404 Not Found\r\nContent-Length: {}\r\nContent-Type: text/plain\r\n\r\n{}",
-        json.len(),
-        json
-    )
+use serde::Serialize;
+
+use crate::i18n::{current_lang, Lang, Message};
+use crate::responses::{Response, Status};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    MissingParameter { field: String, message: Message },
+    InvalidParameter { field: Option<String>, message: Message },
+    // Los datos a procesar (texto, archivo o cuerpo) no son vàlidos: base64 mal formado, CSV roto...
+    InvalidInput { message: Message },
+    NotFound { field: Option<String>, message: Message },
+    // El recurso ya existe o està en un estado que no permite la operaciòn
+    Conflict { field: Option<String>, message: Message },
+    RouteNotFound { path: String },
+    MethodNotAllowed { message: Message },
+    // El cliente pidiò (Accept o format=) un formato de respuesta que no se soporta
+    NotAcceptable { message: Message },
+    // Falta el token o no es vàlido (middleware de autenticaciòn)
+    Unauthorized { message: Message },
+    // El cliente agotò su cuota de solicitudes (middleware de lìmite de tasa)
+    RateLimited { message: Message },
+    LengthRequired,
+    PayloadTooLarge { message: Message },
+    Cancelled { message: Message },
+    QueueFull,
+    Timeout { message: Message },
+    Internal { message: Message },
 }
 
-pub fn http_resonse_400(msg: &str) -> String {
-    let json = format!("{{\"status\" : 400, \"error\" : \"{}\"}}", msg);
-    format!(
-        "HTTP/1.0 404 Bad Request\r\nContent-Length: {}\r\nContent-Type: text/plain\r\n\r\n{}",
-        json.len(),
-        json
-    )
+impl ApiError {
+    pub fn missing(field: &str) -> ApiError {
+        ApiError::MissingParameter { field: field.to_string(), message: Message::key("missing_parameter").arg("field", field) }
+    }
+
+    pub fn invalid(field: &str, message: impl Into<Message>) -> ApiError {
+        ApiError::InvalidParameter { field: Some(field.to_string()), message: message.into() }
+    }
+
+    // Paràmetro que debìa ser un entero positivo
+    pub fn not_positive_integer(field: &str) -> ApiError {
+        ApiError::invalid(field, Message::key("positive_integer").arg("field", field))
+    }
+
+    // Paràmetro con un valor fuera de la lista admitida (`options` ya viene con comillas: "'a', 'b'")
+    pub fn invalid_choice(field: &str, options: &str) -> ApiError {
+        ApiError::invalid(field, Message::key("invalid_choice").arg("field", field).arg("options", options))
+    }
+
+    // Paràmetro que debìa ser un entero dentro de [min, max]
+    pub fn not_in_range(field: &str, min: impl ToString, max: impl ToString) -> ApiError {
+        ApiError::invalid(field, Message::key("integer_between").arg("field", field).arg("min", min).arg("max", max))
+    }
+
+    // Nombre de archivo con caracteres no permitidos en el paràmetro indicado
+    pub fn invalid_name(field: &str) -> ApiError {
+        ApiError::invalid(field, Message::key("invalid_file_name"))
+    }
+
+    pub fn invalid_input(message: impl Into<Message>) -> ApiError {
+        ApiError::InvalidInput { message: message.into() }
+    }
+
+    pub fn not_found(field: Option<&str>, message: impl Into<Message>) -> ApiError {
+        ApiError::NotFound { field: field.map(|f| f.to_string()), message: message.into() }
+    }
+
+    pub fn conflict(field: Option<&str>, message: impl Into<Message>) -> ApiError {
+        ApiError::Conflict { field: field.map(|f| f.to_string()), message: message.into() }
+    }
+
+    pub fn internal(message: impl Into<Message>) -> ApiError {
+        ApiError::Internal { message: message.into() }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::MissingParameter { .. } => "missing_parameter",
+            ApiError::InvalidParameter { .. } => "invalid_parameter",
+            ApiError::InvalidInput { .. } => "invalid_input",
+            ApiError::NotFound { .. } => "not_found",
+            ApiError::Conflict { .. } => "conflict",
+            ApiError::RouteNotFound { .. } => "route_not_found",
+            ApiError::MethodNotAllowed { .. } => "method_not_allowed",
+            ApiError::NotAcceptable { .. } => "not_acceptable",
+            ApiError::Unauthorized { .. } => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::LengthRequired => "length_required",
+            ApiError::PayloadTooLarge { .. } => "payload_too_large",
+            ApiError::Cancelled { .. } => "cancelled",
+            ApiError::QueueFull => "queue_full",
+            ApiError::Timeout { .. } => "timeout",
+            ApiError::Internal { .. } => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::MissingParameter { .. } | ApiError::InvalidParameter { .. } | ApiError::InvalidInput { .. } => Status::BadRequest,
+            ApiError::NotFound { .. } | ApiError::RouteNotFound { .. } => Status::NotFound,
+            ApiError::Conflict { .. } | ApiError::Cancelled { .. } => Status::Conflict,
+            ApiError::MethodNotAllowed { .. } => Status::MethodNotAllowed,
+            ApiError::NotAcceptable { .. } => Status::NotAcceptable,
+            ApiError::Unauthorized { .. } => Status::Unauthorized,
+            ApiError::RateLimited { .. } => Status::TooManyRequests,
+            ApiError::LengthRequired => Status::LengthRequired,
+            ApiError::PayloadTooLarge { .. } => Status::PayloadTooLarge,
+            ApiError::QueueFull => Status::ServiceUnavailable,
+            ApiError::Timeout { .. } => Status::GatewayTimeout,
+            ApiError::Internal { .. } => Status::InternalServerError,
+        }
+    }
+
+    // Un error de cada tipo, para documentar los còdigos y sus estados en /openapi.json
+    pub fn samples() -> Vec<ApiError> {
+        vec![
+            ApiError::missing("text"),
+            ApiError::not_positive_integer("size"),
+            ApiError::invalid_input(Message::key("input_too_large").arg("max", 1_048_576)),
+            ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", "archivos/datos.txt")),
+            ApiError::conflict(Some("name"), Message::key("file_exists").arg("path", "archivos/datos.txt")),
+            ApiError::RouteNotFound { path: "/nada".into() },
+            ApiError::MethodNotAllowed { message: Message::key("method_not_allowed").arg("method", "DELETE").arg("path", "/reverse") },
+            ApiError::NotAcceptable { message: Message::key("not_acceptable").arg("format", "text/html") },
+            ApiError::Unauthorized { message: Message::key("unauthorized") },
+            ApiError::RateLimited { message: Message::key("rate_limited").arg("seconds", 2) },
+            ApiError::LengthRequired,
+            ApiError::PayloadTooLarge { message: Message::key("file_too_large").arg("max", 1_048_576) },
+            ApiError::Cancelled { message: Message::key("job_cancelled").arg("job", "b1") },
+            ApiError::QueueFull,
+            ApiError::Timeout { message: Message::key("search_stopped").arg("bytes", 1024).arg("matches", 3) },
+            ApiError::internal(Message::key("task_reply_failed")),
+        ]
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            ApiError::MissingParameter { field, .. } => Some(field),
+            ApiError::InvalidParameter { field, .. } | ApiError::NotFound { field, .. } | ApiError::Conflict { field, .. } => field.as_deref(),
+            _ => None,
+        }
+    }
+
+    // Mensaje para humanos en el idioma indicado
+    pub fn message_in(&self, lang: Lang) -> String {
+        match self {
+            ApiError::RouteNotFound { path } => Message::key("route_not_found").arg("path", path).render(lang),
+            ApiError::QueueFull => Message::key("queue_full").render(lang),
+            ApiError::LengthRequired => Message::key("length_required").render(lang),
+            ApiError::MissingParameter { message, .. }
+            | ApiError::InvalidParameter { message, .. }
+            | ApiError::InvalidInput { message }
+            | ApiError::NotFound { message, .. }
+            | ApiError::Conflict { message, .. }
+            | ApiError::MethodNotAllowed { message }
+            | ApiError::NotAcceptable { message }
+            | ApiError::Unauthorized { message }
+            | ApiError::RateLimited { message }
+            | ApiError::PayloadTooLarge { message }
+            | ApiError::Cancelled { message }
+            | ApiError::Timeout { message }
+            | ApiError::Internal { message } => message.render(lang),
+        }
+    }
+
+    // Mensaje en el idioma de la conexiòn actual
+    pub fn message(&self) -> String {
+        self.message_in(current_lang())
+    }
 }
 
-pub fn http_response_500_json(msg: &str) -> String {
-    let json = format!("{{\"status\":500,\"message\":\"{}\"}}", msg);
-    format!(
-        "HTTP/1.0 500 Internal Server Error\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
-        json.len(),
-        json
-    )
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::error_responses::{http_resonse_400, http_resonse_404, http_response_500_json};
+// Las tareas que validan datos devuelven texto; con `?` ese texto queda como entrada invàlida
+impl From<String> for ApiError {
+    fn from(message: String) -> ApiError {
+        ApiError::InvalidInput { message: message.into() }
+    }
+}
 
+impl From<Message> for ApiError {
+    fn from(message: Message) -> ApiError {
+        ApiError::InvalidInput { message }
+    }
+}
 
-    #[test]
-    fn test_http_response_404() {
-        let msg = "Página no encontrada";
-        let response = http_resonse_404(msg);
+#[derive(Serialize)]
+struct ErrorEnvelope<'a> {
+    status: u16,
+    code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    error: String,
+}
+
+impl From<ApiError> for Response {
+    fn from(error: ApiError) -> Response {
+        let status = error.status();
+        Response::new(status).json(&ErrorEnvelope { status: status.code(), code: error.code(), field: error.field(), error: error.message() })
+    }
+}
 
-        assert!(response.contains("404 Not Found"));
-        assert!(response.contains("Content-Type: text/plain"));
-        assert!(response.contains("\"status\" : 404"));
-        assert!(response.contains(msg));
+impl From<&ApiError> for Response {
+    fn from(error: &ApiError) -> Response {
+        error.clone().into()
     }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::i18n::Lang;
+    use crate::responses::{Response, Status};
+
+    use super::ApiError;
 
     #[test]
-    fn test_http_response_400() {
-        let msg = "Parámetro inválido";
-        let response = http_resonse_400(msg);
+    fn test_missing_parameter_response() {
+        let response = Response::from(ApiError::missing("text"));
+        let bytes = String::from_utf8(response.to_bytes()).unwrap();
 
-        // Nota: tu función tiene mal el código: dice "404 Bad Request", debería ser "400"
-        assert!(response.contains("404 Bad Request")); // <- ¿esto es un error en tu código?
-        assert!(response.contains("\"status\" : 400"));
-        assert!(response.contains(msg));
+        assert!(bytes.starts_with("HTTP/1.0 400 Bad Request\r\n"));
+        assert!(bytes.contains("Content-Type: application/json"));
+        assert_eq!(response.body_json().unwrap(), json!({"status": 400, "code": "missing_parameter", "field": "text", "error": "Falta el paràmetro 'text'"}));
     }
 
     #[test]
-    fn test_http_response_500_json() {
-        let msg = "Error interno del servidor";
-        let response = http_response_500_json(msg);
+    fn test_status_per_variant() {
+        let conflict = Response::from(ApiError::conflict(Some("name"), "El archivo 'archivos/a.txt' ya existe"));
+        assert!(String::from_utf8(conflict.to_bytes()).unwrap().starts_with("HTTP/1.0 409 Conflict\r\n"));
+        assert_eq!(conflict.body_json().unwrap()["code"], "conflict");
+
+        let not_found = Response::from(ApiError::RouteNotFound { path: "/nada".into() });
+        assert_eq!(not_found.status, Status::NotFound);
+        assert_eq!(not_found.body_json().unwrap(), json!({"status": 404, "code": "route_not_found", "error": "Ruta no encontrada: '/nada'"}));
 
-        assert!(response.contains("500 Internal Server Error"));
-        assert!(response.contains("application/json"));
-        assert!(response.contains("\"status\":500"));
-        assert!(response.contains(msg));
+        assert_eq!(ApiError::QueueFull.status(), Status::ServiceUnavailable);
+        assert_eq!(ApiError::not_positive_integer("keep").message_in(Lang::En), "Parameter 'keep' must be a positive integer");
+        assert_eq!(Response::from(ApiError::internal("fallo \"raro\"")).body_json().unwrap()["error"], "fallo \"raro\"");
     }
-}
\ No newline at end of file
+}
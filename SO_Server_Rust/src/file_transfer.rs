@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::SystemTime;
+
+use crate::endpoints::sha256_reader;
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::handle_connection::HttpRequest;
+use crate::responses::{write_head, Response, Status};
+use crate::storage::{commit_staged, read_lock, resolve_name, save_version, stage, versioning_enabled, write_lock};
+
+// Descarga y subida de archivos por HTTP (/files/{name}).
+// A diferencia del resto de endpoints aquì se escribe directo al socket,
+// asì los archivos grandes nunca se cargan completos en memoria
+
+pub const MAX_UPLOAD_BYTES: u64 = 512 * 1024 * 1024;
+const MAX_CACHED_ETAGS: usize = 1024;
+
+// SHA-256 de cada archivo servido, junto al tamaño y la fecha de modificaciòn con los que se calculò
+type EtagCache = HashMap<PathBuf, (u64, SystemTime, String)>;
+static ETAGS: OnceLock<Mutex<EtagCache>> = OnceLock::new();
+
+// `name` es el segmento `{name}` de la ruta, ya separado por el router
+pub fn handle_file_request<S: Read + Write>(stream: &mut S, request: &HttpRequest, name: &str) {
+    let result = match request.method.as_str() {
+        "GET" | "HEAD" => serve_file(stream, request, name),
+        "PUT" => store_file(stream, request, name),
+        _ => Response::from(ApiError::MethodNotAllowed { message: Message::key("method_not_allowed").arg("method", &request.method).arg("path", &request.path) }).header("Allow", "GET, HEAD, PUT").write_to(stream),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error en /files/{}: {}", name, e);
+    }
+}
+
+// GET /files/{name} con soporte de Range e If-None-Match
+fn serve_file<S: Write>(stream: &mut S, request: &HttpRequest, name: &str) -> io::Result<()> {
+    let path = match resolve_name(name) {
+        Some((_, path)) => path,
+        None => return Response::from(ApiError::invalid_name("name")).write_to(stream),
+    };
+
+    // Las escrituras son atòmicas (rename), asì que basta con abrir el archivo bajo el candado:
+    // el descriptor abierto siempre apunta a una versiòn completa
+    let file = {
+        let _guard = read_lock(&path);
+        File::open(&path)
+    };
+
+    let mut file = match file {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Response::from(ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", name))).write_to(stream);
+        }
+        Err(_) => return Response::from(ApiError::internal(Message::key("file_read_failed"))).write_to(stream),
+    };
+
+    let metadata = file.metadata()?;
+    let len = metadata.len();
+    let etag = match cached_etag(&path, &metadata) {
+        Some(etag) => etag,
+        None => {
+            let digest = sha256_reader(&file)?;
+            file.seek(SeekFrom::Start(0))?;
+            remember_etag(&path, &metadata, digest)
+        }
+    };
+
+    if request.headers.get("if-none-match").is_some_and(|inm| etag_matches(inm, &etag)) {
+        return write_head(stream, Status::NotModified, &[("ETag", etag)]);
+    }
+
+    let send_body = request.method != "HEAD";
+    let content_type = content_type_for(&path).to_string();
+
+    match request.headers.get("range").and_then(|r| parse_range(r, len)) {
+        Some(Ok((start, end))) => {
+            let length = end - start + 1;
+            write_head(stream, Status::PartialContent, &[
+                ("Content-Type", content_type),
+                ("Content-Length", length.to_string()),
+                ("Content-Range", format!("bytes {}-{}/{}", start, end, len)),
+                ("Accept-Ranges", "bytes".to_string()),
+                ("ETag", etag),
+            ])?;
+            if send_body {
+                file.seek(SeekFrom::Start(start))?;
+                io::copy(&mut file.take(length), stream)?;
+            }
+        }
+        Some(Err(())) => {
+            write_head(stream, Status::RangeNotSatisfiable, &[
+                ("Content-Range", format!("bytes */{}", len)),
+                ("Content-Length", "0".to_string()),
+            ])?;
+        }
+        None => {
+            write_head(stream, Status::Ok, &[
+                ("Content-Type", content_type),
+                ("Content-Length", len.to_string()),
+                ("Accept-Ranges", "bytes".to_string()),
+                ("ETag", etag),
+            ])?;
+            if send_body {
+                io::copy(&mut file.take(len), stream)?;
+            }
+        }
+    }
+
+    stream.flush()
+}
+
+// PUT /files/{name}: el cuerpo se copia por bloques a un temporal y luego se renombra
+fn store_file<S: Read + Write>(stream: &mut S, request: &HttpRequest, name: &str) -> io::Result<()> {
+    let (key, path) = match resolve_name(name) {
+        Some(resolved) => resolved,
+        None => return Response::from(ApiError::invalid_name("name")).write_to(stream),
+    };
+
+    let length = match request.headers.get("content-length").map(|v| v.trim().parse::<u64>()) {
+        Some(Ok(length)) => length,
+        Some(Err(_)) => return Response::from(ApiError::invalid("Content-Length", Message::key("invalid_content_length"))).write_to(stream),
+        None => return Response::from(ApiError::LengthRequired).write_to(stream),
+    };
+
+    if length > MAX_UPLOAD_BYTES {
+        let message = Message::key("file_too_large").arg("max", MAX_UPLOAD_BYTES);
+        return Response::from(ApiError::PayloadTooLarge { message }).write_to(stream);
+    }
+
+    if request.headers.get("expect").is_some_and(|e| e.eq_ignore_ascii_case("100-continue")) {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        stream.flush()?;
+    }
+
+    let body = (&request.body_start[..]).chain(&mut *stream).take(length);
+    let (tmp_path, written) = match stage(&path, body) {
+        Ok(staged) => staged,
+        Err(_) => return Response::from(ApiError::internal(Message::key("file_write_failed"))).write_to(stream),
+    };
+
+    if written < length {
+        let _ = fs::remove_file(&tmp_path);
+        return Response::from(ApiError::invalid_input(Message::key("body_too_short"))).write_to(stream);
+    }
+
+    let digest = match File::open(&tmp_path).and_then(sha256_reader) {
+        Ok(digest) => digest,
+        Err(_) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Response::from(ApiError::internal(Message::key("file_write_failed"))).write_to(stream);
+        }
+    };
+
+    let (existed, etag) = {
+        let _guard = write_lock(&path);
+
+        let existed = path.exists();
+        let saved = if existed && versioning_enabled() { save_version(&key, &path).map(|_| ()) } else { Ok(()) };
+        // El digest se guarda con la fecha del archivo ya publicado, asì el GET siguiente no vuelve a leerlo
+        match saved.and_then(|_| commit_staged(&tmp_path, &path, true)).and_then(|_| fs::metadata(&path)) {
+            Ok(metadata) => (existed, remember_etag(&path, &metadata, digest)),
+            Err(_) => {
+                let _ = fs::remove_file(&tmp_path);
+                return Response::from(ApiError::internal(Message::key("file_write_failed"))).write_to(stream);
+            }
+        }
+    };
+
+    let status = if existed { Status::Ok } else { Status::Created };
+    let message = Message::key("file_saved").arg("path", path.display()).arg("bytes", written);
+    Response::success(status, message.to_string().into()).header("ETag", etag).write_to(stream)
+}
+
+// ETag (SHA-256 del contenido) ya calculado, si el archivo no cambiò de tamaño ni de fecha desde entonces
+fn cached_etag(path: &Path, metadata: &fs::Metadata) -> Option<String> {
+    let modified = metadata.modified().ok()?;
+    match etags().get(path) {
+        Some((len, time, etag)) if *len == metadata.len() && *time == modified => Some(etag.clone()),
+        _ => None,
+    }
+}
+
+fn remember_etag(path: &Path, metadata: &fs::Metadata, digest: String) -> String {
+    let etag = format!("\"{}\"", digest);
+    if let Ok(modified) = metadata.modified() {
+        let mut etags = etags();
+        if etags.len() >= MAX_CACHED_ETAGS && !etags.contains_key(path) {
+            etags.clear();
+        }
+        etags.insert(path.to_path_buf(), (metadata.len(), modified, etag.clone()));
+    }
+    etag
+}
+
+fn etags() -> MutexGuard<'static, EtagCache> {
+    ETAGS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// If-None-Match puede traer varias etiquetas separadas por coma, con prefijo W/ o '*'
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(|t| t.trim().trim_start_matches("W/")).any(|t| t == "*" || t == etag)
+}
+
+// Interpreta un solo rango `bytes=a-b`, `bytes=a-` o `bytes=-n`.
+// None: se ignora el encabezado (se responde el archivo completo), Some(Err): rango no satisfacible
+pub fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        let suffix = end.parse::<u64>().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() { len.saturating_sub(1) } else { end.parse::<u64>().ok()?.min(len.saturating_sub(1)) };
+        if start >= len || start > end {
+            return Some(Err(()));
+        }
+        (start, end)
+    };
+
+    Some(Ok(range))
+}
+
+pub fn content_type_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("csv") => "text/csv",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ppm") => "image/x-portable-pixmap",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::{self, Cursor, Read, Write};
+    use std::path::Path;
+
+    use crate::endpoints::sha256_hash;
+    use crate::handle_connection::HttpRequest;
+
+    use super::{content_type_for, handle_file_request, parse_range};
+
+    // Socket falso: lee de `input` y acumula lo escrito en `output`
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)], body_start: &[u8]) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+            body_start: body_start.to_vec(),
+        }
+    }
+
+    fn send(req: &HttpRequest, rest_of_body: &[u8]) -> String {
+        let mut stream = MockStream { input: Cursor::new(rest_of_body.to_vec()), output: Vec::new() };
+        let name = req.path.rsplit('/').next().unwrap();
+        handle_file_request(&mut stream, req, name);
+        String::from_utf8_lossy(&stream.output).to_string()
+    }
+
+    fn header(response: &str, name: &str) -> String {
+        let prefix = format!("{}: ", name);
+        response.lines().find_map(|l| l.strip_prefix(&prefix)).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-4", 10), Some(Ok((0, 4))));
+        assert_eq!(parse_range("bytes=5-", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=8-50", 10), Some(Ok((8, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+    }
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for(Path::new("archivos/a.txt")), "text/plain; charset=utf-8");
+        assert_eq!(content_type_for(Path::new("archivos/a.PNG")), "image/png");
+        assert_eq!(content_type_for(Path::new("archivos/a.bin")), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_put_then_get_with_range_and_etag() {
+        let _ = std::fs::remove_file("archivos/transfer_test.txt");
+
+        // Parte del cuerpo llega junto con los encabezados y el resto por el socket
+        let put = request("PUT", "/files/transfer_test", &[("content-length", "10")], b"0123");
+        let response = send(&put, b"456789");
+        assert!(response.starts_with("HTTP/1.0 201 Created"));
+
+        let etag = header(&response, "ETag");
+        assert_eq!(etag, format!("\"{}\"", sha256_hash("0123456789")));
+
+        let get = request("GET", "/files/transfer_test", &[], b"");
+        let response = send(&get, b"");
+        assert_eq!(header(&response, "ETag"), etag);
+        assert!(response.contains("200 OK") && response.contains("Content-Length: 10"));
+        assert!(response.contains("text/plain") && response.ends_with("0123456789"));
+
+        let ranged = request("GET", "/files/transfer_test", &[("range", "bytes=2-5")], b"");
+        let response = send(&ranged, b"");
+        assert!(response.contains("206 Partial Content") && response.contains("Content-Range: bytes 2-5/10"));
+        assert!(response.ends_with("\r\n\r\n2345"));
+
+        let cached = request("GET", "/files/transfer_test", &[("if-none-match", &etag)], b"");
+        assert!(send(&cached, b"").starts_with("HTTP/1.0 304 Not Modified"));
+
+        let again = request("PUT", "/files/transfer_test.txt", &[("content-length", "2")], b"ok");
+        let response = send(&again, b"");
+        assert!(response.starts_with("HTTP/1.0 200 OK") && header(&response, "ETag") != etag);
+        let stale = request("GET", "/files/transfer_test", &[("if-none-match", &etag)], b"");
+        assert!(send(&stale, b"").ends_with("ok"));
+
+        let _ = std::fs::remove_file("archivos/transfer_test.txt");
+    }
+
+    #[test]
+    fn test_file_request_errors() {
+        assert!(send(&request("GET", "/files/no_existe_nunca", &[], b""), b"").contains("404 Not Found"));
+        assert!(send(&request("PUT", "/files/sin_largo", &[], b""), b"").contains("411 Length Required"));
+        assert!(send(&request("DELETE", "/files/x", &[], b""), b"").contains("Allow: GET, HEAD, PUT"));
+
+        let short = request("PUT", "/files/cuerpo_corto", &[("content-length", "50")], b"abc");
+        assert!(send(&short, b"").contains("400"));
+        assert!(!Path::new("archivos/cuerpo_corto.txt").exists());
+    }
+}
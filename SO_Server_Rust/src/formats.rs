@@ -0,0 +1,280 @@
+use serde_json::{Map, Value};
+
+use crate::csv_ops::write_record;
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::responses::Response;
+
+// Formato de salida de las respuestas. Todas las rutas arman el mismo sobre JSON
+// ({"status","message"} o {"status","code","field","error"}) y aquì se convierte al formato que pidiò el cliente,
+// ya sea con el encabezado `Accept` o con el paràmetro `format=`
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Text,
+    Csv,
+    MessagePack,
+    Cbor,
+}
+
+// En orden de preferencia para los comodines de Accept (*/*, text/*, application/*)
+pub const FORMATS: [Format; 5] = [Format::Json, Format::Text, Format::Csv, Format::MessagePack, Format::Cbor];
+
+impl Format {
+    // Nombre corto para `format=`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "text" | "txt" | "plain" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "msgpack" | "messagepack" => Some(Format::MessagePack),
+            "cbor" => Some(Format::Cbor),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Text => "text/plain; charset=utf-8",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::MessagePack => "application/msgpack",
+            Format::Cbor => "application/cbor",
+        }
+    }
+
+    fn media_types(self) -> &'static [&'static str] {
+        match self {
+            Format::Json => &["application/json"],
+            Format::Text => &["text/plain"],
+            Format::Csv => &["text/csv"],
+            Format::MessagePack => &["application/msgpack", "application/x-msgpack", "application/vnd.msgpack"],
+            Format::Cbor => &["application/cbor"],
+        }
+    }
+
+    // `format=` tiene prioridad sobre Accept. Sin ninguno de los dos se responde JSON
+    pub fn negotiate(param: Option<&str>, accept: Option<&str>) -> Result<Format, ApiError> {
+        if let Some(name) = param {
+            return Format::from_name(name).ok_or_else(|| not_acceptable(name));
+        }
+        let accept = match accept.map(str::trim) {
+            Some(accept) if !accept.is_empty() => accept,
+            _ => return Ok(Format::Json),
+        };
+
+        // (tipo, q) en el orden en que vienen; q=0 significa "este no"
+        let ranges: Vec<(String, f32)> = accept.split(',').filter_map(|item| {
+            let mut parts = item.split(';');
+            let media = parts.next()?.trim().to_ascii_lowercase();
+            let q = parts.find_map(|p| p.trim().strip_prefix("q=")).map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            (!media.is_empty()).then_some((media, q))
+        }).collect();
+
+        // Peso de un formato: el del rango màs especìfico que lo incluye
+        let weight = |format: Format| {
+            let mut best: Option<(u8, f32)> = None;
+            for (media, q) in &ranges {
+                let specificity = if format.media_types().contains(&media.as_str()) {
+                    2
+                } else if media.strip_suffix("/*").is_some_and(|kind| format.media_types()[0].starts_with(&format!("{}/", kind))) {
+                    1
+                } else if media == "*/*" {
+                    0
+                } else {
+                    continue;
+                };
+                if best.is_none_or(|(s, _)| specificity > s) {
+                    best = Some((specificity, *q));
+                }
+            }
+            best.map_or(0.0, |(_, q)| q)
+        };
+
+        // Gana el mayor peso; en empate, el orden de FORMATS
+        FORMATS.iter().map(|f| (*f, weight(*f))).filter(|(_, q)| *q > 0.0)
+            .fold(None, |best: Option<(Format, f32)>, (f, q)| if best.is_none_or(|(_, b)| q > b) { Some((f, q)) } else { best })
+            .map(|(format, _)| format)
+            .ok_or_else(|| not_acceptable(accept))
+    }
+
+    // Convierte una respuesta con sobre JSON al formato pedido. Las que no son JSON (archivos, imàgenes) quedan igual
+    pub fn render(self, response: Response) -> Response {
+        if self == Format::Json || !response.get_header("Content-Type").is_some_and(|t| t.starts_with("application/json")) {
+            return response;
+        }
+        let envelope = match response.body_json() {
+            Some(envelope) => envelope,
+            None => return response,
+        };
+
+        let body = match self {
+            Format::Json => unreachable!(),
+            Format::Text => to_text(&envelope).into_bytes(),
+            Format::Csv => to_csv(&envelope),
+            Format::MessagePack => rmp_serde::to_vec_named(&envelope).expect("un valor JSON siempre se puede serializar"),
+            Format::Cbor => {
+                let mut body = Vec::new();
+                ciborium::into_writer(&envelope, &mut body).expect("un valor JSON siempre se puede serializar");
+                body
+            }
+        };
+        response.body(self.content_type(), body)
+    }
+}
+
+// Para las rutas cuyo resultado ya es un documento CSV (/csv): el texto del mensaje se entrega tal cual
+// en lugar de convertir el sobre. Los errores se dejan para `render`
+pub fn csv_passthrough(response: Response) -> Response {
+    if !response.status.is_success() {
+        return response;
+    }
+    match response.body_json().and_then(|mut envelope| envelope.get_mut("message").map(Value::take)) {
+        Some(Value::String(text)) => response.body(Format::Csv.content_type(), text),
+        _ => response,
+    }
+}
+
+fn not_acceptable(requested: &str) -> ApiError {
+    ApiError::NotAcceptable { message: Message::key("not_acceptable").arg("format", requested) }
+}
+
+// Valores anidados en texto y CSV: los textos tal cual, lo demàs como JSON compacto
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// Texto plano: el mensaje solo (un elemento por lìnea si es arreglo, `clave: valor` si es objeto)
+// y los errores como `còdigo: mensaje`
+fn to_text(envelope: &Value) -> String {
+    let mut text = match (envelope.get("message"), envelope.get("error")) {
+        (_, Some(error)) => format!("{}: {}", plain(&envelope["code"]), plain(error)),
+        (Some(Value::Array(items)), _) => items.iter().map(plain).collect::<Vec<_>>().join("\n"),
+        (Some(Value::Object(fields)), _) => fields.iter().map(|(key, value)| format!("{}: {}", key, plain(value))).collect::<Vec<_>>().join("\n"),
+        (Some(message), _) => plain(message),
+        (None, None) => String::new(),
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+// CSV: un arreglo de objetos es una tabla, un arreglo de valores o un texto una columna `value`,
+// un objeto una sola fila con sus claves como encabezado. La salida de /csv, que ya es CSV, no pasa por aquì (ver `csv_passthrough`)
+fn to_csv(envelope: &Value) -> Vec<u8> {
+    let message = match envelope.get("message") {
+        Some(message) => message,
+        None => {
+            let mut error = Map::new();
+            for key in ["status", "code", "field", "error"] {
+                error.insert(key.to_string(), envelope.get(key).cloned().unwrap_or(Value::Null));
+            }
+            return table(&[Value::Object(error)]);
+        }
+    };
+    match message {
+        Value::Array(items) => table(items),
+        other => table(std::slice::from_ref(other)),
+    }
+}
+
+fn table(rows: &[Value]) -> Vec<u8> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        if let Value::Object(fields) = row {
+            for key in fields.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    // Escribir a un Vec no falla
+    if columns.is_empty() {
+        let _ = write_record(&mut out, &["value".to_string()], b',');
+        for row in rows {
+            let _ = write_record(&mut out, &[plain(row)], b',');
+        }
+    } else {
+        let _ = write_record(&mut out, &columns, b',');
+        for row in rows {
+            let record: Vec<String> = columns.iter().map(|c| row.get(c).map(plain).unwrap_or_default()).collect();
+            let _ = write_record(&mut out, &record, b',');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use crate::error_responses::ApiError;
+    use crate::responses::{Response, Status};
+
+    use super::{csv_passthrough, Format};
+
+    #[test]
+    fn test_negotiate_format() {
+        assert_eq!(Format::negotiate(None, None).unwrap(), Format::Json);
+        assert_eq!(Format::negotiate(None, Some("text/csv")).unwrap(), Format::Csv);
+        assert_eq!(Format::negotiate(None, Some("text/*;q=0.5, application/cbor")).unwrap(), Format::Cbor);
+        assert_eq!(Format::negotiate(None, Some("text/html, */*;q=0.1")).unwrap(), Format::Json);
+        assert_eq!(Format::negotiate(None, Some("application/json;q=0, */*")).unwrap(), Format::Text);
+        assert_eq!(Format::negotiate(Some("msgpack"), Some("text/csv")).unwrap(), Format::MessagePack);
+
+        let error = Format::negotiate(None, Some("text/html, image/png")).unwrap_err();
+        assert_eq!((error.code(), error.status()), ("not_acceptable", Status::NotAcceptable));
+        assert!(Format::negotiate(Some("xml"), None).is_err());
+    }
+
+    #[test]
+    fn test_render_text_and_csv() {
+        let text = Format::Text.render(Response::ok("cba"));
+        assert_eq!(text.get_header("Content-Type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(text.body, b"cba\n");
+
+        let error = Format::Text.render(ApiError::missing("text").into());
+        assert_eq!(error.status, Status::BadRequest);
+        assert_eq!(String::from_utf8(error.body).unwrap(), "missing_parameter: Falta el paràmetro 'text'\n");
+
+        let numbers = Format::Csv.render(Response::ok_json(json!([1, 2.5])));
+        assert_eq!(numbers.body, b"value\n1\n2.5\n");
+
+        let rows = Format::Csv.render(Response::ok_json(json!([{"a": 1, "b": "x, y"}, {"a": 2, "c": true}])));
+        assert_eq!(String::from_utf8(rows.body).unwrap(), "a,b,c\n1,\"x, y\",\n2,,true\n");
+
+        // Un texto cualquiera es una celda con encabezado y comillas, no CSV crudo
+        let text = Format::Csv.render(Response::ok("b,a"));
+        assert_eq!(String::from_utf8(text.body).unwrap(), "value\n\"b,a\"\n");
+        let csv = csv_passthrough(Response::ok("a,b\n1,2\n"));
+        assert_eq!((csv.get_header("Content-Type"), csv.body.as_slice()), (Some("text/csv; charset=utf-8"), &b"a,b\n1,2\n"[..]));
+        assert_eq!(Format::Csv.render(csv.clone()), csv);
+
+        let error = Format::Csv.render(ApiError::missing("text").into());
+        assert_eq!(String::from_utf8(error.body).unwrap(), "status,code,field,error\n400,missing_parameter,text,Falta el paràmetro 'text'\n");
+    }
+
+    #[test]
+    fn test_render_binary_formats() {
+        let envelope = json!({"status": 200, "message": [1, "dos"]});
+
+        let msgpack = Format::MessagePack.render(Response::ok_json(json!([1, "dos"])));
+        assert_eq!(msgpack.get_header("Content-Type"), Some("application/msgpack"));
+        assert_eq!(rmp_serde::from_slice::<Value>(&msgpack.body).unwrap(), envelope);
+
+        let cbor = Format::Cbor.render(Response::ok_json(json!([1, "dos"])));
+        assert_eq!(ciborium::from_reader::<Value, _>(cbor.body.as_slice()).unwrap(), envelope);
+
+        let raw = Response::new(Status::Ok).body("image/png", vec![1, 2, 3]);
+        assert_eq!(Format::Cbor.render(raw.clone()), raw);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::storage::{read_lock, resolve_name, STORAGE_DIR};
+use crate::task_queue::CancelToken;
+
+// Bùsqueda de contenido en el directorio de archivos (/grep)
+
+pub const MAX_CONTEXT: usize = 10;
+pub const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    pub regex: bool,
+    pub ignore_case: bool,
+    pub context: usize,
+    pub max_matches: usize,
+    pub max_bytes: u64,
+    pub deadline: Option<Instant>,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        GrepOptions { regex: false, ignore_case: false, context: 0, max_matches: 100, max_bytes: 10 * 1024 * 1024, deadline: None }
+    }
+}
+
+// Estado que se comparte entre archivos: lìmites y salida acumulada
+struct Search {
+    lines: Vec<String>,
+    matches: usize,
+    files_with_matches: usize,
+    bytes_scanned: u64,
+    truncated: bool,
+}
+
+// /grep?pattern=texto&name=archivo&regex=true&context=2
+pub fn grep_files(pattern: &str, name: Option<&str>, options: &GrepOptions, cancel: &CancelToken) -> Result<Message, ApiError> {
+    if pattern.is_empty() {
+        return Err(ApiError::invalid("pattern", Message::key("grep_empty_pattern")));
+    }
+
+    let source = if options.regex { pattern.to_string() } else { regex::escape(pattern) };
+    let re = RegexBuilder::new(&source)
+        .case_insensitive(options.ignore_case)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| ApiError::invalid("pattern", Message::key("grep_invalid_regex").arg("error", e)))?;
+
+    let files = match name {
+        Some(name) => match resolve_name(name) {
+            Some((_, path)) if path.is_file() => vec![(path.file_name().unwrap_or_default().to_string_lossy().to_string(), path)],
+            Some(_) => return Err(ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", name))),
+            None => return Err(ApiError::invalid_name("name")),
+        },
+        None => stored_files().map_err(|_| ApiError::internal(Message::key("storage_read_failed")))?,
+    };
+
+    // Cancelada con /cancel o sin tiempo por 'timeout_ms'
+    let stopped = |search: &Search| {
+        let message = Message::key("search_stopped").arg("bytes", search.bytes_scanned).arg("matches", search.matches);
+        if cancel.is_cancelled() {
+            Some(ApiError::Cancelled { message })
+        } else if options.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(ApiError::Timeout { message })
+        } else {
+            None
+        }
+    };
+
+    let mut search = Search { lines: Vec::new(), matches: 0, files_with_matches: 0, bytes_scanned: 0, truncated: false };
+
+    for (file_name, path) in &files {
+        if search.truncated {
+            break;
+        }
+        if let Some(error) = stopped(&search) {
+            return Err(error);
+        }
+        if grep_file(file_name, path, &re, options, cancel, &mut search).is_err() {
+            return Err(ApiError::internal(Message::key("grep_file_read_failed").arg("name", file_name)));
+        }
+    }
+
+    if let Some(error) = stopped(&search) {
+        return Err(error);
+    }
+
+    let partial = if search.truncated { Message::key("grep_partial") } else { Message::from(String::new()) };
+    let lines: String = search.lines.iter().map(|line| format!("\n{}", line)).collect();
+    Ok(Message::key("grep_summary")
+        .arg("matches", search.matches)
+        .arg("files_with_matches", search.files_with_matches)
+        .arg("files", files.len())
+        .arg("bytes", search.bytes_scanned)
+        .arg_message("partial", partial)
+        .arg("lines", lines))
+}
+
+// Archivos guardados, sin temporales ni el directorio de versiones (empiezan con '.')
+fn stored_files() -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(STORAGE_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with('.') && entry.file_type()?.is_file() {
+            files.push((file_name, entry.path()));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// Recorre un archivo lìnea por lìnea con formato de grep:
+// `archivo:n:texto` para coincidencias, `archivo-n-texto` para contexto y `--` entre grupos
+fn grep_file(file_name: &str, path: &Path, re: &Regex, options: &GrepOptions, cancel: &CancelToken, search: &mut Search) -> std::io::Result<()> {
+    let file = {
+        let _guard = read_lock(path);
+        File::open(path)?
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut raw = Vec::new();
+    let mut before: VecDeque<(usize, String)> = VecDeque::with_capacity(options.context);
+    let mut after_remaining = 0;
+    let mut last_printed: Option<usize> = None;
+    let mut file_matches = 0;
+    let mut line_number = 0;
+
+    loop {
+        if search.bytes_scanned >= options.max_bytes {
+            search.truncated = true;
+            break;
+        }
+        if line_number % 1024 == 0 && (cancel.is_cancelled() || options.deadline.is_some_and(|d| Instant::now() >= d)) {
+            break;
+        }
+
+        // Se lee a travès de `take` para que una lìnea enorme (o un archivo sin saltos) no pase del lìmite de bytes
+        raw.clear();
+        let remaining = options.max_bytes - search.bytes_scanned;
+        let read = reader.by_ref().take(remaining).read_until(b'\n', &mut raw)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        search.bytes_scanned += read as u64;
+
+        let line = String::from_utf8_lossy(&raw);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if re.is_match(line) {
+            if search.matches >= options.max_matches {
+                search.truncated = true;
+                break;
+            }
+
+            if options.context > 0 && last_printed.is_some_and(|last| before.front().map(|(n, _)| *n).unwrap_or(line_number) > last + 1) {
+                search.lines.push("--".to_string());
+            }
+            for (n, text) in before.drain(..) {
+                search.lines.push(format!("{}-{}-{}", file_name, n, text));
+            }
+
+            search.lines.push(format!("{}:{}:{}", file_name, line_number, line));
+            search.matches += 1;
+            file_matches += 1;
+            last_printed = Some(line_number);
+            after_remaining = options.context;
+        } else if after_remaining > 0 {
+            search.lines.push(format!("{}-{}-{}", file_name, line_number, line));
+            last_printed = Some(line_number);
+            after_remaining -= 1;
+        } else if options.context > 0 {
+            if before.len() == options.context {
+                before.pop_front();
+            }
+            before.push_back((line_number, line.to_string()));
+        }
+    }
+
+    if file_matches > 0 {
+        search.files_with_matches += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::storage::write_atomic;
+    use crate::task_queue::CancelToken;
+
+    use super::{grep_files, GrepOptions};
+
+    fn setup(name: &str, content: &str) {
+        write_atomic(&std::path::Path::new("archivos").join(format!("{}.txt", name)), content.as_bytes(), true).unwrap();
+    }
+
+    #[test]
+    fn test_grep_literal_with_context() {
+        setup("grep_contexto", "uno\ndos\ntres\ncuatro\ncinco\nseis\nsiete\n");
+        let options = GrepOptions { context: 1, ..GrepOptions::default() };
+
+        let result = grep_files("tres", Some("grep_contexto"), &options, &CancelToken::new()).unwrap().to_string();
+        let lines: Vec<&str> = result.lines().collect();
+        assert!(lines[0].starts_with("1 coincidencias en 1 archivos"));
+        assert_eq!(&lines[1..], ["grep_contexto.txt-2-dos", "grep_contexto.txt:3:tres", "grep_contexto.txt-4-cuatro"]);
+
+        // El punto es literal si no se pide regex
+        assert!(grep_files("s.is", Some("grep_contexto"), &GrepOptions::default(), &CancelToken::new()).unwrap().to_string().starts_with("0 coincidencias"));
+        let _ = std::fs::remove_file("archivos/grep_contexto.txt");
+    }
+
+    #[test]
+    fn test_grep_regex_and_limits() {
+        setup("grep_regex", "a1\nb2\na3\na4\n");
+        let options = GrepOptions { regex: true, max_matches: 2, ..GrepOptions::default() };
+
+        let result = grep_files("^a[0-9]$", Some("grep_regex"), &options, &CancelToken::new()).unwrap().to_string();
+        assert!(result.contains("grep_regex.txt:1:a1") && result.contains("grep_regex.txt:3:a3"));
+        assert!(!result.contains("a4") && result.contains("resultados parciales"));
+
+        assert!(grep_files("(", Some("grep_regex"), &options, &CancelToken::new()).unwrap_err().message().contains("invàlida"));
+        assert!(grep_files("a", Some("no_existe_grep"), &options, &CancelToken::new()).is_err());
+        let _ = std::fs::remove_file("archivos/grep_regex.txt");
+    }
+
+    #[test]
+    fn test_grep_long_line_respects_byte_limit() {
+        // Una sola lìnea de 1 MB no se lee completa con un lìmite de 100 bytes
+        setup("grep_linea_larga", &"x".repeat(1024 * 1024));
+        let options = GrepOptions { max_bytes: 100, ..GrepOptions::default() };
+        let result = grep_files("y", Some("grep_linea_larga"), &options, &CancelToken::new()).unwrap().to_string();
+        assert!(result.contains("100 bytes") && result.contains("resultados parciales"), "{}", result);
+        let _ = std::fs::remove_file("archivos/grep_linea_larga.txt");
+    }
+
+    #[test]
+    fn test_grep_cancelled() {
+        setup("grep_cancelado", "hola\n");
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert_eq!(grep_files("hola", Some("grep_cancelado"), &GrepOptions::default(), &cancel).unwrap_err().code(), "cancelled");
+
+        let expired = GrepOptions { deadline: Some(Instant::now()), ..GrepOptions::default() };
+        assert_eq!(grep_files("hola", Some("grep_cancelado"), &expired, &CancelToken::new()).unwrap_err().code(), "timeout");
+        let _ = std::fs::remove_file("archivos/grep_cancelado.txt");
+    }
+}
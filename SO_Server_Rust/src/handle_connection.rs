@@ -1,363 +1,370 @@
 use std::io::Write;
-use std::os::unix::process;
-use std::sync::atomic::Ordering;
-use std::sync::mpsc::{self, Sender};
+use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::{io::Read, net::TcpStream};
 use std::collections::HashMap;
-use std::time::Instant;
-
-use crate::error_responses::{http_resonse_400, http_resonse_404, http_response_500_json};
-use crate::responses::{http_response_200};
-use crate::task_queue::{Task, TaskType, WorkerStatus};
-use crate::CONNECTION_COUNT;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::responses::{write_head, Response, Status};
+use crate::error_responses::ApiError;
+use crate::i18n::{current_lang, set_lang, Lang, Message};
+use crate::formats::Format;
+use crate::file_transfer::MAX_UPLOAD_BYTES;
+use crate::metrics::METRICS;
+use crate::stats::STATS;
+use crate::middleware::{Exchange, Outcome, Pipeline};
+use crate::router::{head_response, Connection, Context, HeadOnly, Method, Reply, Resolution, Router};
+use crate::storage::{stage, StagedBody, STORAGE_DIR};
+use crate::task_queue::{reply_channel, submit, Task, TaskType, WorkerStatus};
 // Archivo para la lògica de manejo de las conexiones
 
-pub fn handle_connection(mut stream: TcpStream, task_sender: Sender<Task>, start_time : Instant, worker_states : Arc<Mutex<Vec<WorkerStatus>>>) {
-    let mut buffer = [0u8; 1024];
-    let read_bytes = stream.read(&mut buffer).unwrap_or(0);
-
-    if read_bytes == 0 {
-        return;
-    }
-
-    let request = String::from_utf8_lossy(&buffer[..]);
-    let (method, path) = parse_request(&request);
+// Solicitud HTTP ya parseada. Los encabezados se guardan en minùsculas y `body_start`
+// contiene los bytes del cuerpo que llegaron en la misma lectura que los encabezados
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body_start: Vec<u8>,
+}
 
-    println!("Solicitud: {} {}", method, path);
-    let response = route_request(&path, &task_sender, start_time, worker_states);
+const MAX_HEAD_BYTES: usize = 8192;
 
-    println!("{}", response);
-    stream.write_all(response.as_bytes()).unwrap();
-    stream.flush().unwrap();
+// Lo que comparten todas las conexiones: la cola de tareas y el estado de los workers
+pub struct ServerState {
+    pub sender: Sender<Task>,
+    pub start_time: Instant,
+    pub worker_states: Arc<Mutex<Vec<WorkerStatus>>>,
+    // Conexiones atendidas desde el arranque y conexiones abiertas ahora
+    pub connections: AtomicUsize,
+    pub active: AtomicUsize,
 }
 
-fn parse_request(request: &str) -> (&str, String) {
-    let mut lines = request.lines();
-    if let Some(request_line) = lines.next() {
-        let parts: Vec<&str> = request_line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            return (parts[0], parts[1].to_string());
-        }
+impl ServerState {
+    pub fn new(sender: Sender<Task>, worker_states: Arc<Mutex<Vec<WorkerStatus>>>) -> ServerState {
+        ServerState { sender, start_time: Instant::now(), worker_states, connections: AtomicUsize::new(0), active: AtomicUsize::new(0) }
     }
-    ("", "".to_string())
 }
 
-pub fn route_request(path: &str, sender: &Sender<Task>, start_time : Instant, worker_states : Arc<Mutex<Vec<WorkerStatus>>>) -> String {
-    let (route, params) = parse_query(path);
-
-    match route.as_str() {
-        "/fibonacci" => {
-            if let Some(n_str) = params.get("num") {
-                match n_str.parse::<u64>() {
-                    Ok(n) => {
-                        return enqueue_and_reply(sender, TaskType::Fibonacci(n), &format!("Fibonacci para {}", n))
-                    }
-                    Err(e) => return http_resonse_400(&e.to_string()),
-                }
-            } else {
-                return http_resonse_400("Falta el paràmetro 'num'");
-            }
-        }
+// Una conexiòn: se lee la solicitud, pasa por la cadena de middlewares hasta el router y se escribe la respuesta.
+// El registro, los ids, la autenticaciòn y demàs viven en las capas (middleware.rs)
+pub fn handle_connection(mut stream: TcpStream, router: &Router, pipeline: &Pipeline, state: &ServerState) {
+    state.connections.fetch_add(1, Ordering::Relaxed);
+    let _active = ActiveConnection::new(&state.active);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
 
-        "/reverse" => {
-            if let Some(text) = params.get("text") {
-               return  enqueue_and_reply(sender, TaskType::Reverse(text.clone()), &format!("Reverse de {}", text));
-            } else {
-                return http_resonse_400("Falta el paràmetro 'text'");
-            }
-        }
+    let mut request = match read_request(&mut stream) {
+        Some(request) => request,
+        None => return,
+    };
 
-        "/toupper" => {
-            if let Some(text) = params.get("text") {
-                return enqueue_and_reply(sender, TaskType::Toupper(text.clone()), &format!("Touper de {}", text));
-            } else {
-                return http_resonse_400("Falta el paràmetro 'text'");
-            }
-        }
+    // Las respuestas de las capas (401, 429) tambièn salen en el idioma del cliente
+    set_lang(request_lang(&request));
+    let mut exchange = Exchange::new(stream.peer_addr().ok().map(|addr| addr.ip()));
+    let outcome = pipeline.run(&mut request, &mut exchange, &mut stream, |request, stream| serve(router, state, request, stream));
+    record_request(router, &request, &outcome, exchange.started.elapsed());
 
-        "/hash" => {
-            if let Some(text) = params.get("text") {
-                return enqueue_and_reply(sender,TaskType::Sha256(text.clone()), &format!("Sha256_hash de {}", text));
-            } else {
-                return http_resonse_400("Falta el paràmetro 'text'");
-            }
-        }
-
-        "/sleep" => {
-            if let Some(sec) = params.get("seconds") {
-                match sec.parse::<u64>() {
-                    Ok(seconds) => {
-                        return enqueue_and_reply(sender, TaskType::Sleep(seconds), &format!("Simulaciòn por {} segundos", sec));
-                    }
-                    Err(_) => {
-                        return http_resonse_400("El parametro 'seconds' debe de ser un nùmero positivo");
-                    }
-                }
-            }
-            return http_resonse_400("Falta el paràmetro 'seconds'");
-        }
-
-        "/timestamp" => {
-            return enqueue_and_reply(sender, TaskType::TimeStamp, &format!("TimeStamp actual en formato Iso"));
+    if let Some(mut response) = outcome.into_response() {
+        if response.get_header("Content-Language").is_none() {
+            response.set_header("Content-Language", current_lang().code());
         }
+        let _ = response.write_to(&mut stream);
+    }
+}
 
-        "/random" => {
-            let count_str = params.get("count");
-            let min_str = params.get("min");
-            let max_str = params.get("max");
+// Cuenta una conexiòn abierta mientras vive
+struct ActiveConnection<'a>(&'a AtomicUsize);
 
-            if count_str.is_none() || min_str.is_none() || max_str.is_none() {
-                return http_resonse_400("Falta alguno o varios de los 3 paràmetros que se necesitas -> 'count', 'min', 'max'")
-            }
+impl<'a> ActiveConnection<'a> {
+    fn new(active: &'a AtomicUsize) -> ActiveConnection<'a> {
+        active.fetch_add(1, Ordering::Relaxed);
+        ActiveConnection(active)
+    }
+}
 
-            let count = match count_str.unwrap().parse::<usize>() {
-                Ok(c) => c,
-                Err(_) => return http_resonse_400("El paràmetro 'count' debe de ser entero positivo"),
-            };
+impl Drop for ActiveConnection<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
 
-            let min = match min_str.unwrap().parse::<i32>() {
-                Ok(mi) => mi,
-                Err(_) => return http_resonse_400("El paràmetro 'min' debe de ser entero positivo"),
-            };
+// Anota la solicitud en /metrics y en las estadìsticas de /status. Los mètodos desconocidos van juntos
+// para no crear una serie por cada uno
+fn record_request(router: &Router, request: &HttpRequest, outcome: &Outcome, elapsed: Duration) {
+    let (path, _) = parse_query(&request.path);
+    let method = Method::parse(&request.method).map_or("OTHER", |m| m.as_str());
+    let (route, status) = (router.label(&request.method, &path), outcome.status().unwrap_or(0));
+    METRICS.record_request(route, method, status, elapsed);
+    STATS.record_route(method, route, status, elapsed);
+}
 
-            let max = match max_str.unwrap().parse::<i32>() {
-                Ok(ma) => ma,
-                Err(_) => return http_resonse_400("El paràmetro 'max' debe de ser entero positivo"),
-            };
+// Idioma de los mensajes: `lang=` o Accept-Language (español por defecto)
+fn request_lang(request: &HttpRequest) -> Lang {
+    let (_, params) = parse_query(&request.path);
+    Lang::negotiate(params.get("lang").map(String::as_str), request.headers.get("accept-language").map(String::as_str))
+}
 
-            if min >= max {
-                return http_resonse_400("El paràmetro 'min' debe ser menor estrico que el paràmetro 'max'")
-            }
+// Atiende una solicitud ya leìda: idioma, ruta, formato de salida y handler.
+// Devuelve la respuesta a escribir, o None si la ruta ya la escribiò en la conexiòn
+pub fn serve(router: &Router, state: &ServerState, request: &HttpRequest, stream: &mut dyn Connection) -> Option<Response> {
+    // Idioma de los mensajes para toda la conexiòn
+    set_lang(request_lang(request));
+    let (path, mut params) = parse_query(&request.path);
+    params.remove("lang");
+    let content_language = current_lang().code();
+
+    let resolution = router.resolve(&request.method, &path);
+    let (route, path_params) = match resolution {
+        Resolution::Found(route, path_params) => (route, path_params),
+        other => return other.response(&request.method, &path).map(|r| r.header("Content-Language", content_language)),
+    };
 
-            return enqueue_and_reply(sender, TaskType::Random { count, min, max }, "Generar números aleatorios");
+    // Formato de la respuesta: `format=` (salvo donde ya es un paràmetro de la ruta) o Accept.
+    // Las rutas que escriben su propio cuerpo (archivos, imàgenes) no se convierten
+    let format = if route.raw {
+        Format::Json
+    } else {
+        let format_param = if route.declares("format") { None } else { params.remove("format") };
+        match Format::negotiate(format_param.as_deref(), request.headers.get("accept").map(String::as_str)) {
+            Ok(format) => format,
+            Err(error) => return Some(Response::from(error).header("Content-Language", content_language)),
         }
+    };
 
-        "/createfile" => {
-            if let (Some(name), Some(content)) = (params.get("name"), params.get("content")) {
-                return enqueue_and_reply(sender, TaskType::CreateFile { name: name.clone(), content: content.clone() }, &format!("Crear archivo '{}'", name));
-            }
-            return http_resonse_400("Los paràmetros 'name' y 'content' son obligatorios")
-        }
+    // HEAD sobre una ruta GET que escribe directo: se dejan pasar solo los encabezados
+    let head = request.method == "HEAD";
+    let mut head_only;
+    let stream: &mut dyn Connection = if head && route.raw && route.method == Method::Get {
+        head_only = HeadOnly::new(stream);
+        &mut head_only
+    } else {
+        stream
+    };
 
-        "/deletefile" => {
-            if let Some(name) = params.get("name") {
-                return enqueue_and_reply(sender, TaskType::DeleteFile(name.clone()), &format!("Eliminar archivo '{}'", name));
-            }
-            return http_resonse_400("El paràmetro 'name' es obligatorio");
+    let method = Method::parse(&request.method).unwrap_or(route.method);
+    let mut ctx = Context { method, path, params, path_params, request, stream, state, router, format };
+    match route.call(&mut ctx) {
+        Reply::Written => None,
+        Reply::Response(response) => {
+            let response = format.render(response).header("Content-Language", content_language);
+            Some(if head { head_response(response) } else { response })
         }
+    }
+}
 
-        "/simulate" => {
-            let delay = match params.get("seconds") {
-                Some(s) => match s.parse::<u64>() {
-                    Ok(val) => val,
-                    Err(_) => return http_resonse_400("EL parametro 'seconds' debe entero positivo")
-                },
-                None => return http_resonse_400("Falta el parametro 'seconds'"),
-            };
-
-            let task = match params.get("task") {
-                Some(t) => t.as_str(),
-                None => return http_resonse_400("Falta el parametro 'task'"),
-            };
-
-            match task {
-                "reverse" => {
-                    if let Some(text) = params.get("text") {
-                        let inner = TaskType::Reverse(text.clone());
-                        return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simular reverse");
-                     } else {
-                        return http_resonse_400("Falta el parametro 'text'")
-                     }
-                }
-
-                "toupper" => {
-                    if let Some(text) = params.get("text") {
-                        let inner = TaskType::Toupper(text.clone());
-                        return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate toupper");
-                    } else {
-                        return http_resonse_400("Falta el parametro 'text'")
-                    }
-                }
-
-                "fibonacci" => {
-                    if let Some(n_str) = params.get("num") {
-                        match n_str.parse::<u64>() {
-                            Ok(n) => {
-                                let inner = TaskType::Fibonacci(n);
-                                return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate fibonacci");
-                            }
-                            Err(_e) => return http_resonse_400("EL parametro 'num' debe de ser entero positivo"),
-                        }
-                    } else {
-                        return http_resonse_400("Falta el parametro 'num'")
-                    }
-                }
-
-                "hash" => {
-                    if let Some(text) = params.get("text") {
-                        let inner = TaskType::Sha256(text.clone());
-                        return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate hash");
-                    } else {
-                        return http_resonse_400("Falta el parametro 'text'")
-                    }
-                }
+// Copia el cuerpo de la solicitud (segùn Content-Length) a un temporal oculto en archivos/
+pub(crate) fn stage_body<S: Read>(stream: &mut S, request: &HttpRequest) -> Result<StagedBody, ApiError> {
+    let length = match request.headers.get("content-length").map(|v| v.trim().parse::<u64>()) {
+        Some(Ok(length)) if length <= MAX_UPLOAD_BYTES => length,
+        Some(Ok(_)) => return Err(ApiError::PayloadTooLarge { message: Message::key("body_too_large").arg("max", MAX_UPLOAD_BYTES) }),
+        Some(Err(_)) => return Err(ApiError::invalid("Content-Length", Message::key("invalid_content_length"))),
+        None => return Err(ApiError::MissingParameter { field: "Content-Length".into(), message: Message::key("length_required") }),
+    };
 
-                "timestamp" => {
-                    let inner = TaskType::TimeStamp;
-                    return enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate timestamp");
-                }
-                
-                "random" => {
-                    let count = params.get("count").and_then(|s| s.parse::<usize>().ok());
-                    let min = params.get("min").and_then(|s| s.parse::<i32>().ok());
-                    let max = params.get("max").and_then(|s| s.parse::<i32>().ok());
-
-                    match (count, min, max) {
-                        (Some(c), Some(a), Some(b)) if a <= b => {
-                            let inner = TaskType::Random { count: c, min: a, max: b };
-                            return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate random");
-                        }
-                        _ => format!("Faltan paramentros")
-                    }
-                }
+    let body = (&request.body_start[..]).chain(&mut *stream).take(length);
+    let (tmp_path, written) = stage(&Path::new(STORAGE_DIR).join("cuerpo"), body).map_err(|_| ApiError::internal(Message::key("body_read_failed")))?;
+    let staged = StagedBody::new(tmp_path);
+    if written < length {
+        return Err(ApiError::invalid_input(Message::key("body_too_short")));
+    }
+    Ok(staged)
+}
 
-                "createfile" => {
-                    if let (Some(name), Some(content)) = (params.get("name"), params.get("content")) {
-                        let inner = TaskType::CreateFile { name: name.clone(), content: content.clone() };
-                        return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate createfile");
-                    }
-                    return http_resonse_400("Falta alguno de los siguiente parametros: 'name' o 'content'")
-                }
+// Lee hasta el final de los encabezados (\r\n\r\n) o hasta MAX_HEAD_BYTES
+fn read_request<R: Read>(stream: &mut R) -> Option<HttpRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
 
-                "deletefile" => {
-                    if let Some(name) = params.get("name") {
-                        let inner = TaskType::DeleteFile(name.clone());
-                        return enqueue_and_reply(sender, TaskType::Simulate { delay: delay, inner: Box::new(inner) }, "Simulate deletefile");
-                    }
-                    return http_resonse_400("Falta el parametro 'name'")
-                }
-                _ => return http_resonse_400("Tarea no soportada por simulate")
-            }
+    while find_head_end(&buffer).is_none() && buffer.len() < MAX_HEAD_BYTES {
+        let read_bytes = stream.read(&mut chunk).unwrap_or(0);
+        if read_bytes == 0 {
+            break;
         }
+        buffer.extend_from_slice(&chunk[..read_bytes]);
+    }
 
-        "/loadtest" => {
-            let count = params.get("count").and_then(|v| v.parse::<usize>().ok()).unwrap_or(10);
-            let task_name = params.get("task").map(|s| s.as_str()).unwrap_or("default");
-            let text = params.get("text").cloned().unwrap_or_else(|| "default".to_string());
-
-            let task_type_template = match task_name {
-                "reverse" => TaskType::Reverse(text),
-                "toupper" => TaskType::Toupper(text),
-                "sha256" => TaskType::Sha256(text),
-                "timestamp" => TaskType::TimeStamp,
-                _ => return http_resonse_400("Tarea no soportada para loadtest"),
-            };
-
-            let start = Instant::now();
-            let mut receivers = Vec::with_capacity(count);
-
-            //Vamos a encolar las tareas
-            for _ in 0..count {
-                let (tx, rx) = mpsc::channel::<String>();
-                let task = Task {
-                    description : format!("Loadtest para {}", task_name),
-                    task_type : task_type_template.clone(),
-                    response_tx : tx,
-                };
-                if let Err(_) = sender.send(task) {
-                    return http_response_500_json("Fallo al encolar tarea");
-                }
-                receivers.push(rx);
-            }
-
-            //Aquì en esta secciòn esperamos todas las respuestas
-            let mut results = Vec::new();
-            for rx in receivers {
-                if let Ok(res) = rx.recv() {
-                    results.push(res);
-                } else {
-                    results.push("ERROR en respuesta".to_string());
-                }
-            }
-
-            let elapsed = start.elapsed();
-            let sample = results.iter().take(5).cloned().collect::<Vec<_>>();
-
-            let json = format!("{{\"task\":\"{}\",\"total_tasks\":\"{}\", \"duration_ms\" : {}, \"results_sample\" : {:?}}}", task_name, count, elapsed.as_millis(), sample);
-
-            return http_response_200(&json);
-        }
-        
-        "/help" => {
-            return enqueue_and_reply(sender, TaskType::Help, &format!("Manual para usar los endpoints"));
-        }
+    if buffer.is_empty() {
+        return None;
+    }
+    parse_request(&buffer)
+}
 
-        "/status" => {
-            let uptime = start_time.elapsed().as_secs();
+fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|w| w == b"\r\n\r\n")
+}
 
-            let worker_states_clone = match worker_states.lock() {
-                Ok(ws) => ws.clone(),
-                Err(_) => return http_response_500_json("No se pudo accerder a los datos de los workers"),
-            };
+fn parse_request(raw: &[u8]) -> Option<HttpRequest> {
+    let (head, body_start) = match find_head_end(raw) {
+        Some(end) => (&raw[..end], raw[end + 4..].to_vec()),
+        None => (raw, Vec::new()),
+    };
 
-            let workers_json : Vec<String> = worker_states_clone.iter().map(|w| {
-                format!("{{\"id\" : {}, \"status\" : \"{}\", \"description\" : \"{}\"}}", w.id, if w.busy {"ocupado"} else {"disponible"}, w.description)
-            }).collect();
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.lines();
 
-            let response = format!("{{\"status\" : 200, \"pid\" : {}, \"uptime_secs\": {}, \"conexiones\": {}, \"workers\" : [{}]}}", process::parent_id(), uptime, CONNECTION_COUNT.load(Ordering::SeqCst), workers_json.join(","));
+    let request_line = lines.next()?;
+    let parts: Vec<&str> = request_line.split_whitespace().collect();
+    if parts.len() < 2 {
+        return None;
+    }
 
-            return http_response_200(&response);
+    let mut headers = HashMap::new();
+    for line in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
         }
-        _ => http_resonse_404("Ruta no encontrada")
     }
+
+    Some(HttpRequest { method: parts[0].to_string(), path: parts[1].to_string(), headers, body_start })
 }
 
-fn parse_query (path: &str) -> (String, HashMap<String, String>) {
+pub(crate) fn parse_query (path: &str) -> (String, HashMap<String, String>) {
     let mut parts = path.split('?');
     let route = parts.next().unwrap_or("").to_string();
     let mut query_map = HashMap::new();
 
     if let Some(query) = parts.next() {
         for param in query.split('&') {
-            let mut kv = param.split('=');
-            let key = kv.next().unwrap_or("").to_string();
-            let value = kv.next().unwrap_or("").to_string();
-            query_map.insert(key, value);
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            query_map.insert(percent_decode(key), percent_decode(value));
         }
     }
 
     (route, query_map)
 }
 
-pub fn enqueue_and_reply(sender: &Sender<Task>, task_type: TaskType, desc: &str) -> String {
-    let (response_tx, response_rx) = mpsc::channel::<String>();
+// Decodifica %XX y '+' de la query string. Las secuencias invàlidas se dejan tal cual
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn enqueue_and_reply(sender: &Sender<Task>, task_type: TaskType, desc: &str) -> Response {
+    let (response_tx, response_rx) = reply_channel();
+    let replies_json = task_type.replies_json();
 
     let task = Task {
         description: desc.to_string(),
         task_type,
         response_tx,
+        enqueued: Instant::now(),
     };
 
-    if let Err(_) = sender.send(task) {
-        return http_response_500_json("No se pudo encolar la tarea");
+    if let Err(error) = submit(sender, task) {
+        return error.into();
     }
 
     // Esperamos la respuesta del worker (bloqueante)
     match response_rx.recv() {
-        Ok(json_result) => http_response_200(&json_result),
-        Err(_) => http_response_500_json("Error al recibir resultado de la tarea"),
+        Ok(Err(error)) => error.into(),
+        // Las tareas que ya responden JSON (ayuda, nùmeros aleatorios) se anidan como valor y no como texto
+        // /random con cantidades grandes llega por partes cuando no se envìa por streaming: se juntan todas
+        Ok(Ok(first)) if replies_json => {
+            let result: String = std::iter::once(first).chain(response_rx.iter().map_while(Result::ok)).map(|chunk| chunk.to_string()).collect();
+            match serde_json::from_str::<Value>(&result) {
+                Ok(value) => Response::ok_json(value),
+                Err(_) => Response::ok(&result),
+            }
+        }
+        Ok(Ok(result)) => Response::ok(&result.to_string()),
+        Err(_) => ApiError::internal(Message::key("task_reply_failed")).into(),
     }
 }
 
+// Igual que enqueue_and_reply, pero cada mensaje del worker se escribe al socket apenas llega, dentro del
+// mismo sobre {"status":200,"message":...} que las demàs respuestas.
+// No se conoce el largo total, asì que se responde sin Content-Length y se cierra la conexiòn al terminar
+pub fn stream_task<W: Write>(stream: &mut W, sender: &Sender<Task>, task_type: TaskType, desc: &str) {
+    let (response_tx, response_rx) = reply_channel();
+
+    let task = Task {
+        description: desc.to_string(),
+        task_type,
+        response_tx,
+        enqueued: Instant::now(),
+    };
+
+    if let Err(error) = submit(sender, task) {
+        let _ = Response::from(error).write_to(stream);
+        return;
+    }
+
+    // Un error antes del primer bloque todavìa se puede responder con su estado
+    let first = match response_rx.recv() {
+        Ok(Ok(chunk)) => chunk,
+        Ok(Err(error)) => {
+            let _ = Response::from(error).write_to(stream);
+            return;
+        }
+        Err(_) => {
+            let _ = Response::from(ApiError::internal(Message::key("task_reply_failed"))).write_to(stream);
+            return;
+        }
+    };
+
+    let prefix = format!("{{\"status\":{},\"message\":", Status::Ok.code());
+    if write_head(stream, Status::Ok, &[("Content-Type", "application/json".to_string())]).is_err()
+        || stream.write_all(prefix.as_bytes()).is_err()
+        || stream.write_all(first.to_string().as_bytes()).is_err()
+    {
+        return;
+    }
+
+    // Si el cliente se desconecta se suelta el receptor y el worker deja de generar
+    // Un error a mitad del envìo ya no se puede reportar: el estado 200 ya saliò, solo se corta la respuesta sin cerrar el sobre
+    for chunk in response_rx.iter() {
+        match chunk {
+            Ok(chunk) if stream.write_all(chunk.to_string().as_bytes()).is_ok() => {}
+            _ => return,
+        }
+    }
+    let _ = stream.write_all(b"}");
+    let _ = stream.flush();
+}
 
 #[cfg(test)]
 mod test {
-    use std::{sync::mpsc::channel, thread};
+    use std::io::Cursor;
+    use std::sync::mpsc::{channel, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
 
-    use crate::task_queue::{Task, TaskType};
+    use serde_json::json;
 
-    use super::enqueue_and_reply;
+    use crate::responses::Status;
+    use crate::error_responses::ApiError;
+    use crate::i18n::{set_lang, Lang, Message};
+    use crate::metrics::METRICS_CONTENT_TYPE;
+    use crate::task_queue::{process_task, Task, TaskType};
+
+    use crate::responses::Response;
+    use crate::routes::default_router;
+
+    use super::{enqueue_and_reply, parse_query, parse_request, serve, stage_body, stream_task, ServerState};
 
 
     #[test]
@@ -367,13 +374,160 @@ mod test {
         //Simula un worker trabajando
         thread::spawn(move || {
             if let Ok(task) = reply_rx.recv() {
-                let _ = task.response_tx.send("resultado_ok".to_string());
+                let _ = task.response_tx.send(Ok("resultado_ok".into()));
             }
         });
 
         let response = enqueue_and_reply(&reply_tx, TaskType::Reverse("abc".into()), "Reverse text");
-        assert!(response.contains("HTTP/1.0 202 Accepted"));
-        assert!(response.contains("resultado_ok"));
+        assert_eq!(response.status, Status::Ok);
+        assert_eq!(response.body_json().unwrap(), json!({"status": 200, "message": "resultado_ok"}));
+    }
+
+    #[test]
+    fn test_enqueue_and_reply_task_error() {
+        let (reply_tx, reply_rx) = channel::<Task>();
+
+        thread::spawn(move || {
+            for (i, task) in reply_rx.iter().enumerate() {
+                let error = if i == 0 { ApiError::invalid_input("base64 invàlido") } else { ApiError::conflict(Some("name"), "El archivo 'archivos/a.txt' ya existe") };
+                let _ = task.response_tx.send(Err(error));
+            }
+        });
+
+        let response = enqueue_and_reply(&reply_tx, TaskType::Reverse("abc".into()), "Decodificar");
+        assert_eq!(response.status, Status::BadRequest);
+        assert_eq!(response.body_json().unwrap(), json!({"status": 400, "code": "invalid_input", "error": "base64 invàlido"}));
+
+        let response = enqueue_and_reply(&reply_tx, TaskType::Reverse("abc".into()), "Crear archivo");
+        assert_eq!(response.status, Status::Conflict);
+        assert_eq!(response.body_json().unwrap()["field"], "name");
+    }
+
+    // Atiende una solicitud cruda con las rutas del servidor
+    fn serve_raw(raw: &str, sender: Sender<Task>) -> Response {
+        let state = ServerState::new(sender, Arc::new(Mutex::new(Vec::new())));
+        let request = parse_request(raw.as_bytes()).unwrap();
+        serve(&default_router(), &state, &request, &mut Cursor::new(Vec::new())).unwrap()
+    }
+
+    #[test]
+    fn test_messages_follow_connection_language() {
+        let (sender, _receiver) = channel::<Task>();
+        let error = serve_raw("GET /reverse HTTP/1.0\r\nAccept-Language: en-US,en;q=0.9\r\n\r\n", sender.clone());
+        assert_eq!(error.get_header("Content-Language"), Some("en"));
+        assert_eq!(error.body_json().unwrap(), json!({"status": 400, "code": "missing_parameter", "field": "text", "error": "Missing parameter 'text'"}));
+
+        let error = serve_raw("GET /reverse?lang=es HTTP/1.0\r\nAccept-Language: en\r\n\r\n", sender);
+        assert_eq!(error.body_json().unwrap()["error"], "Falta el paràmetro 'text'");
+
+        let (reply_tx, reply_rx) = channel::<Task>();
+        thread::spawn(move || {
+            for task in reply_rx.iter() {
+                let _ = task.response_tx.send(Ok(Message::key("job_cancelled").arg("job", "b1")));
+            }
+        });
+        set_lang(Lang::En);
+        let reply = enqueue_and_reply(&reply_tx, TaskType::TimeStamp, "idioma");
+        set_lang(Lang::Es);
+        assert_eq!(reply.body_json().unwrap()["message"], "Job 'b1' cancelled");
+
+        // Los resultados y errores de los workers tambièn se traducen al idioma de la conexiòn
+        let (sender, receiver) = channel::<Task>();
+        thread::spawn(move || receiver.iter().for_each(|task| { process_task(task); }));
+        let reply = serve_raw("GET /primes?size=100&lang=en HTTP/1.0\r\n\r\n", sender.clone());
+        assert!(reply.body_json().unwrap()["message"].as_str().unwrap().starts_with("25 primes less than or equal to 100"));
+        let error = serve_raw("GET /calc?expr=1%2F0&lang=en HTTP/1.0\r\n\r\n", sender);
+        assert_eq!(error.body_json().unwrap()["error"], "Error at position 1: division by zero");
+    }
+
+    #[test]
+    fn test_route_errors_are_structured() {
+        let (sender, _receiver) = channel::<Task>();
+
+        let response = serve_raw("GET /reverse HTTP/1.0\r\n\r\n", sender.clone());
+        assert_eq!(response.body_json().unwrap(), json!({"status": 400, "code": "missing_parameter", "field": "text", "error": "Falta el paràmetro 'text'"}));
+
+        let response = serve_raw("GET /purgeversions?name=a&keep=x HTTP/1.0\r\n\r\n", sender.clone());
+        let body = response.body_json().unwrap();
+        assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("invalid_parameter"), Some("keep")));
+
+        // El campo viene del parser, no del texto del mensaje
+        for (raw, field) in [("/timestamp?tz=Mars/X", "tz"), ("/encode?to=xx&text=a", "to"), ("/dateconvert?input=0&to=UTC&from=Mars/X", "from"), ("/random?count=3", "min")] {
+            let body = serve_raw(&format!("GET {} HTTP/1.0\r\n\r\n", raw), sender.clone()).body_json().unwrap();
+            assert_eq!(body["field"], field, "{}", raw);
+        }
+
+        let response = serve_raw("GET /nada HTTP/1.0\r\n\r\n", sender);
+        assert_eq!(response.status, Status::NotFound);
+        assert_eq!(response.body_json().unwrap()["code"], "route_not_found");
+    }
+
+    #[test]
+    fn test_methods_are_checked() {
+        let (sender, _receiver) = channel::<Task>();
+
+        let response = serve_raw("DELETE /reverse?text=abc HTTP/1.0\r\n\r\n", sender.clone());
+        assert_eq!(response.status, Status::MethodNotAllowed);
+        assert_eq!(response.get_header("Allow"), Some("GET, HEAD, OPTIONS"));
+
+        let response = serve_raw("OPTIONS /csv HTTP/1.0\r\n\r\n", sender.clone());
+        assert_eq!((response.status, response.get_header("Allow")), (Status::NoContent, Some("GET, POST, OPTIONS")));
+
+        // Las rutas que pueden guardar archivos (save=) no se ejecutan con HEAD
+        let response = serve_raw("HEAD /mandelbrot?save=x&width=4096&height=4096 HTTP/1.0\r\n\r\n", sender.clone());
+        assert_eq!(response.status, Status::MethodNotAllowed);
+        assert!(!std::path::Path::new("archivos/x.png").exists());
+
+        let response = serve_raw("HEAD /reverse HTTP/1.0\r\n\r\n", sender);
+        assert_eq!(response.status, Status::BadRequest);
+        assert!(response.body.is_empty());
+        assert_ne!(response.get_header("Content-Length"), Some("0"));
+    }
+
+    #[test]
+    fn test_docs_do_not_need_workers() {
+        // Nadie atiende la cola: si /help pasara por un worker la prueba se quedarìa esperando
+        let (sender, receiver) = channel::<Task>();
+        drop(receiver);
+        let help = serve_raw("GET /help HTTP/1.0\r\n\r\n", sender.clone());
+        assert_eq!(help.status, Status::Ok);
+        assert!(help.body_json().unwrap()["message"]["endpoints"].as_array().unwrap().iter().any(|e| e["path"] == "/status"));
+
+        let spec = serve_raw("GET /openapi.json?format=text HTTP/1.0\r\n\r\n", sender);
+        assert_eq!(spec.get_header("Content-Type"), Some("application/json"));
+        assert_eq!(spec.body_json().unwrap()["openapi"], "3.0.3");
+    }
+
+    #[test]
+    fn test_metrics_exposition() {
+        let (sender, _receiver) = channel::<Task>();
+        let metrics = serve_raw("GET /metrics?format=json HTTP/1.0\r\n\r\n", sender);
+        assert_eq!(metrics.get_header("Content-Type"), Some(METRICS_CONTENT_TYPE));
+        let text = String::from_utf8(metrics.body).unwrap();
+        assert!(text.contains("# TYPE http_requests_total counter\n"));
+        assert!(text.contains("# TYPE task_duration_seconds histogram\n"));
+        assert!(text.contains("\nprocess_open_fds "));
+
+        // Las series van por patròn de ruta y las url desconocidas comparten una sola
+        let router = default_router();
+        assert_eq!(router.label("GET", "/files/a.txt"), "/files/{name}");
+        assert_eq!(router.label("DELETE", "/reverse"), "/reverse");
+        assert_eq!(router.label("GET", "/no/existe"), "unmatched");
+    }
+
+    #[test]
+    fn test_status_reports_this_process() {
+        let (sender, _receiver) = channel::<Task>();
+        let status = serve_raw("GET /status HTTP/1.0\r\n\r\n", sender.clone()).body_json().unwrap();
+        let pid = std::process::id();
+        assert_eq!(status["message"]["pid"], pid);
+        assert_eq!(status["message"]["process"]["pid"], pid);
+        assert!(status["message"]["process"]["threads"].as_u64().unwrap() > 0);
+        assert!(status["message"]["process"]["cpu"]["user_secs"].is_number());
+
+        let system = serve_raw("GET /status/system HTTP/1.0\r\n\r\n", sender).body_json().unwrap();
+        assert!(system["message"]["cpus"].as_u64().unwrap() >= 1);
+        assert!(system["message"]["memory"]["total_bytes"].as_u64().unwrap() > 0);
     }
 
     #[test]
@@ -382,8 +536,9 @@ mod test {
         drop(reply_rx); //Aquì se cierra el receiver
 
         let response = enqueue_and_reply(&reply_tx, TaskType::Reverse("abc".into()), "reverse text");
-        assert!(response.contains("500"));
-        assert!(response.contains("No se pudo encolar la tarea"));
+        assert_eq!(response.status, Status::InternalServerError);
+        let body = response.body_json().unwrap();
+        assert_eq!((body["code"].as_str(), body["error"].as_str()), (Some("internal_error"), Some(Message::key("workers_stopped").to_string().as_str())));
     }
 
     #[test]
@@ -397,7 +552,64 @@ mod test {
         });
 
         let response = enqueue_and_reply(&reply_tx, TaskType::Reverse("abc".into()), "reverse test");
-        assert!(response.contains("500"));
-        assert!(response.contains("Error al recibir resultado"));
+        assert_eq!(response.status, Status::InternalServerError);
+        assert_eq!(response.body_json().unwrap()["error"], "Error al recibir resultado de la tarea");
+    }
+
+    #[test]
+    fn test_parse_request_headers_and_body() {
+        let raw = b"PUT /files/a HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhola!";
+        let request = parse_request(raw).unwrap();
+
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/files/a");
+        assert_eq!(request.headers.get("content-length").unwrap(), "5");
+        assert_eq!(request.body_start, b"hola!");
+        assert!(parse_request(b"\r\n\r\n").is_none());
+    }
+
+    #[test]
+    fn test_parse_query_decodes_values() {
+        let (route, params) = parse_query("/grep?pattern=%5Ehola+mundo%21&data=a%3Db=c&bad=100%");
+        assert_eq!(route, "/grep");
+        assert_eq!(params.get("pattern").unwrap(), "^hola mundo!");
+        assert_eq!(params.get("data").unwrap(), "a=b=c");
+        assert_eq!(params.get("bad").unwrap(), "100%");
+    }
+
+    #[test]
+    fn test_stream_task_writes_every_chunk() {
+        let (task_tx, task_rx) = channel::<Task>();
+
+        thread::spawn(move || {
+            if let Ok(task) = task_rx.recv() {
+                for chunk in ["[1, ", "2, ", "3]"] {
+                    let _ = task.response_tx.send(Ok(chunk.into()));
+                }
+            }
+        });
+
+        let mut output = Vec::new();
+        stream_task(&mut output, &task_tx, TaskType::TimeStamp, "stream test");
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.0 200 OK"));
+        let body = output.split_once("\r\n\r\n").unwrap().1;
+        assert_eq!(serde_json::from_str::<serde_json::Value>(body).unwrap(), json!({"status": 200, "message": [1, 2, 3]}));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stage_body_joins_head_and_rest() {
+        let request = parse_request(b"POST /textstats HTTP/1.0\r\nContent-Length: 11\r\n\r\nhola").unwrap();
+        let staged = stage_body(&mut &b" mundo!extra"[..], &request).unwrap();
+        let path = staged.path().to_path_buf();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hola mundo!");
+        // El temporal se borra al soltar el cuerpo
+        drop(staged);
+        assert!(!path.exists());
+
+        let short = parse_request(b"POST /textstats HTTP/1.0\r\nContent-Length: 50\r\n\r\nhola").unwrap();
+        assert!(stage_body(&mut &b""[..], &short).unwrap_err().message().contains("corto"));
+        let missing = parse_request(b"POST /textstats HTTP/1.0\r\n\r\n").unwrap();
+        assert_eq!(stage_body(&mut &b""[..], &missing).unwrap_err().field(), Some("Content-Length"));
+    }
+}
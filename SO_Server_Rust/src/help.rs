@@ -0,0 +1,277 @@
+use serde_json::{json, Map, Value};
+
+use crate::error_responses::ApiError;
+use crate::formats::FORMATS;
+use crate::handle_connection::parse_query;
+use crate::i18n::Lang;
+use crate::router::{Method, ParamKind, Route, Router};
+
+// Manual de /help y documento OpenAPI de /openapi.json. Los dos se arman con la tabla de rutas:
+// el mètodo, el patròn y el esquema de paràmetros son los mismos que usa el router y la descripciòn,
+// el ejemplo y los errores vienen del `Doc` de cada ruta, asì que no se pueden desfasar del servidor
+
+// Errores que puede dar cualquier ruta, ademàs de los suyos
+const COMMON_ERRORS: [&str; 3] = ["method_not_allowed", "queue_full", "internal_error"];
+
+fn type_name(kind: ParamKind) -> &'static str {
+    match kind {
+        ParamKind::Text => "string",
+        ParamKind::Integer => "integer",
+        ParamKind::Number => "number",
+        ParamKind::Boolean => "boolean",
+    }
+}
+
+fn describe(route: &Route, name: &str, lang: Lang) -> &'static str {
+    route.doc.param(name).map(|text| lang.pick(text)).unwrap_or_default()
+}
+
+// Còdigos de error de una ruta: los que implica su esquema y los que declara su documentaciòn
+pub fn route_errors(route: &Route) -> Vec<&'static str> {
+    let mut codes = Vec::new();
+    if route.params.iter().any(|p| p.required) {
+        codes.push("missing_parameter");
+    }
+    if !route.params.is_empty() {
+        codes.push("invalid_parameter");
+    }
+    for code in route.doc.errors {
+        if !codes.contains(code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+// Todos los paràmetros aceptan ademàs `lang=es|en` y, donde no es un paràmetro propio, `format=`
+pub fn help_json(router: &Router, lang: Lang) -> Value {
+    let endpoints: Vec<Value> = router.routes().iter().map(|route| {
+        let path_params = route.path_params().into_iter()
+            .map(|name| json!({"name": name, "in": "path", "type": "string", "required": true, "description": describe(route, name, lang)}));
+        let query_params = route.params.iter()
+            .map(|p| json!({"name": p.name, "in": "query", "type": type_name(p.kind), "required": p.required, "description": describe(route, p.name, lang)}));
+        json!({
+            "method": route.method.as_str(),
+            "path": route.pattern,
+            "description": lang.pick(route.doc.summary),
+            "params": path_params.chain(query_params).collect::<Vec<_>>(),
+            "example": route.doc.example,
+            "errors": route_errors(route),
+        })
+    }).collect();
+    json!({"endpoints": endpoints})
+}
+
+fn schema(kind: ParamKind) -> Value {
+    match kind {
+        ParamKind::Integer => json!({"type": "integer", "minimum": 0}),
+        kind => json!({"type": type_name(kind)}),
+    }
+}
+
+// `get_files_name` para GET /files/{name}
+fn operation_id(route: &Route) -> String {
+    let mut id = route.method.as_str().to_ascii_lowercase();
+    for word in route.pattern.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+        id.push('_');
+        id.push_str(word);
+    }
+    id
+}
+
+// Sobre de error de ejemplo en el idioma pedido
+fn error_example(error: &ApiError, lang: Lang) -> Value {
+    let mut example = json!({"status": error.status().code(), "code": error.code(), "error": error.message_in(lang)});
+    if let Some(field) = error.field() {
+        example["field"] = json!(field);
+    }
+    example
+}
+
+fn operation(route: &Route, lang: Lang, samples: &[ApiError]) -> Value {
+    let (_, examples) = parse_query(route.doc.example);
+
+    let mut parameters: Vec<Value> = route.path_params().into_iter().map(|name| {
+        json!({"name": name, "in": "path", "required": true, "description": describe(route, name, lang), "schema": {"type": "string"}})
+    }).collect();
+    for param in &route.params {
+        let mut parameter = json!({"name": param.name, "in": "query", "required": param.required, "description": describe(route, param.name, lang), "schema": schema(param.kind)});
+        if let Some(example) = examples.get(param.name) {
+            parameter["example"] = json!(example);
+        }
+        parameters.push(parameter);
+    }
+    parameters.push(json!({"$ref": "#/components/parameters/lang"}));
+    if !route.raw && !route.declares("format") {
+        parameters.push(json!({"$ref": "#/components/parameters/format"}));
+    }
+
+    // Las rutas normales responden el sobre en cualquiera de los formatos; las `raw`, lo que declaran
+    let mut content = Map::new();
+    if route.raw {
+        let produces: &[&str] = if route.doc.produces.is_empty() { &["application/octet-stream"] } else { route.doc.produces };
+        for media in produces {
+            content.insert(media.to_string(), json!({"schema": {"type": "string", "format": "binary"}}));
+        }
+    } else {
+        for format in FORMATS {
+            let schema = match format.content_type() {
+                "application/json" => json!({"$ref": "#/components/schemas/Success"}),
+                _ => json!({"type": "string"}),
+            };
+            content.insert(format.content_type().to_string(), json!({"schema": schema}));
+        }
+    }
+    let mut responses = Map::new();
+    responses.insert("200".into(), json!({"description": "OK", "content": content}));
+
+    // Varios còdigos pueden compartir estado (400): cada uno queda como un ejemplo de esa respuesta
+    let not_acceptable = (!route.raw).then_some("not_acceptable");
+    for code in route_errors(route).into_iter().chain(not_acceptable).chain(COMMON_ERRORS) {
+        let error = match samples.iter().find(|e| e.code() == code) {
+            Some(error) => error,
+            None => continue,
+        };
+        let response = responses.entry(error.status().code().to_string()).or_insert_with(|| json!({
+            "description": error.status().reason(),
+            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Error"}, "examples": {}}},
+        }));
+        response["content"]["application/json"]["examples"][code] = json!({"value": error_example(error, lang)});
+    }
+
+    let mut operation = json!({
+        "operationId": operation_id(route),
+        "summary": lang.pick(route.doc.summary),
+        "parameters": parameters,
+        "responses": responses,
+    });
+    if matches!(route.method, Method::Post | Method::Put | Method::Patch) {
+        operation["requestBody"] = json!({"required": true, "content": {"application/octet-stream": {"schema": {"type": "string", "format": "binary"}}}});
+    }
+    operation
+}
+
+// Documento OpenAPI 3 con todas las rutas del router
+pub fn openapi_json(router: &Router, lang: Lang) -> Value {
+    let samples = ApiError::samples();
+    let mut paths = Map::new();
+    for route in router.routes() {
+        let item = paths.entry(route.pattern.clone()).or_insert_with(|| json!({}));
+        item[route.method.as_str().to_ascii_lowercase()] = operation(route, lang, &samples);
+    }
+
+    let codes: Vec<&str> = samples.iter().map(|e| e.code()).collect();
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": lang.pick(("Servidor HTTP con un pool de workers para tareas de texto, archivos, fechas y càlculo",
+                "HTTP server with a worker pool for text, file, date and computing tasks")),
+        },
+        "paths": paths,
+        "components": {
+            "parameters": {
+                "lang": {"name": "lang", "in": "query", "required": false, "description": lang.pick(("Idioma de los mensajes", "Language of the messages")),
+                    "schema": {"type": "string", "enum": ["es", "en"]}},
+                "format": {"name": "format", "in": "query", "required": false, "description": lang.pick(("Formato de la respuesta (tiene prioridad sobre Accept)", "Response format (takes precedence over Accept)")),
+                    "schema": {"type": "string", "enum": ["json", "text", "csv", "msgpack", "cbor"]}},
+            },
+            "schemas": {
+                "Success": {"type": "object", "required": ["status", "message"], "properties": {"status": {"type": "integer"}, "message": {}}},
+                "Error": {"type": "object", "required": ["status", "code", "error"], "properties": {
+                    "status": {"type": "integer"},
+                    "code": {"type": "string", "enum": codes},
+                    "field": {"type": "string"},
+                    "error": {"type": "string"},
+                }},
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error_responses::ApiError;
+    use crate::handle_connection::parse_query;
+    use crate::i18n::Lang;
+    use crate::router::Resolution;
+    use crate::routes::default_router;
+
+    use super::{help_json, openapi_json, route_errors};
+
+    #[test]
+    fn test_every_route_is_documented() {
+        let router = default_router();
+        let codes: Vec<&str> = ApiError::samples().iter().map(|e| e.code()).collect();
+        for route in router.routes() {
+            let name = format!("{} {}", route.method.as_str(), route.pattern);
+            assert!(!route.doc.summary.0.is_empty() && !route.doc.summary.1.is_empty(), "{} sin descripciòn", name);
+
+            // Cada paràmetro del esquema està descrito y cada descripciòn es de un paràmetro que existe
+            let declared: Vec<&str> = route.path_params().into_iter().chain(route.params.iter().map(|p| p.name)).collect();
+            for param in &declared {
+                assert!(route.doc.param(param).is_some(), "{}: falta describir '{}'", name, param);
+            }
+            for (param, _) in route.doc.params {
+                assert!(declared.contains(param), "{}: '{}' no es un paràmetro de la ruta", name, param);
+            }
+            for code in route_errors(route) {
+                assert!(codes.contains(&code), "{}: còdigo desconocido '{}'", name, code);
+            }
+
+            // Un GET que puede escribir archivos no se atiende con HEAD
+            let writes = route.params.iter().any(|p| p.name == "save" || p.name == "output");
+            assert!(route.method.as_str() != "GET" || !writes || route.mutating, "{}: escribe archivos y no està marcada con mutating()", name);
+
+            // El ejemplo llega a esta misma ruta y pasa la validaciòn del esquema
+            let (path, params) = parse_query(route.doc.example);
+            match router.resolve(route.method.as_str(), &path) {
+                Resolution::Found(found, _) => assert_eq!(found.pattern, route.pattern, "{}: el ejemplo va a otra ruta", name),
+                _ => panic!("{}: el ejemplo '{}' no resuelve", name, route.doc.example),
+            }
+            assert!(route.validate(&params).is_ok(), "{}: el ejemplo no es vàlido", name);
+        }
+    }
+
+    #[test]
+    fn test_help_lists_real_routes() {
+        let router = default_router();
+        let es = help_json(&router, Lang::Es);
+        let en = help_json(&router, Lang::En);
+        let endpoints = es["endpoints"].as_array().unwrap();
+        assert_eq!(endpoints.len(), router.routes().len());
+        assert!(endpoints.iter().any(|e| e["path"] == "/hash"));
+        assert!(endpoints.iter().any(|e| e["path"] == "/status"));
+        assert!(!endpoints.iter().any(|e| e["path"] == "/sha256"));
+
+        assert_eq!(es["endpoints"][0]["description"], "Invierte el texto recibido");
+        assert_eq!(en["endpoints"][0]["description"], "Reverses the given text");
+        assert_eq!(en["endpoints"][0]["params"][0], serde_json::json!({
+            "name": "text", "in": "query", "type": "string", "required": true, "description": "text to reverse",
+        }));
+        assert_eq!(es["endpoints"][0]["example"], en["endpoints"][0]["example"]);
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let spec = openapi_json(&default_router(), Lang::En);
+        assert_eq!(spec["openapi"], "3.0.3");
+
+        let reverse = &spec["paths"]["/reverse"]["get"];
+        assert_eq!(reverse["operationId"], "get_reverse");
+        assert_eq!(reverse["parameters"][0]["schema"]["type"], "string");
+        assert_eq!(reverse["parameters"][0]["example"], "abc");
+        assert_eq!(reverse["responses"]["400"]["content"]["application/json"]["examples"]["missing_parameter"]["value"]["field"], "text");
+        assert!(reverse["responses"]["200"]["content"]["text/csv; charset=utf-8"].is_object());
+
+        let fibonacci = &spec["paths"]["/fibonacci"]["get"]["parameters"][0];
+        assert_eq!((fibonacci["schema"]["type"].as_str(), fibonacci["required"].as_bool()), (Some("integer"), Some(true)));
+
+        let files = &spec["paths"]["/files/{name}"];
+        assert_eq!(files["get"]["parameters"][0]["in"], "path");
+        assert!(files["put"]["requestBody"].is_object());
+        assert!(files["get"]["responses"]["406"].is_null());
+        assert_eq!(spec["paths"]["/jobs/{id}"]["delete"]["responses"]["404"]["description"], "Not Found");
+    }
+}
@@ -0,0 +1,393 @@
+use std::cell::Cell;
+use std::fmt;
+
+// Mensajes para el cliente en español e inglès.
+// Cada mensaje tiene un id estable y una plantilla por idioma con argumentos `{nombre}`.
+// El idioma se elige por conexiòn (paràmetro `lang=` o encabezado Accept-Language) y los mensajes
+// se traducen en el hilo de la conexiòn; los workers solo devuelven el id y sus argumentos
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Es,
+    En,
+}
+
+impl Lang {
+    // Acepta etiquetas como "en", "en-US" o "es_CR"
+    pub fn parse(tag: &str) -> Option<Lang> {
+        let primary = tag.trim().split(['-', '_']).next().unwrap_or("");
+        match primary.to_ascii_lowercase().as_str() {
+            "es" => Some(Lang::Es),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::Es => "es",
+            Lang::En => "en",
+        }
+    }
+
+    // `lang=` tiene prioridad; si no, el idioma soportado con mayor peso q en Accept-Language.
+    // Sin coincidencias se responde en español
+    pub fn negotiate(param: Option<&str>, accept_language: Option<&str>) -> Lang {
+        if let Some(lang) = param.and_then(Lang::parse) {
+            return lang;
+        }
+
+        let mut best: Option<(f32, Lang)> = None;
+        for item in accept_language.unwrap_or("").split(',') {
+            let mut parts = item.split(';');
+            let lang = match Lang::parse(parts.next().unwrap_or("")) {
+                Some(lang) => lang,
+                None => continue,
+            };
+            let q = parts.find_map(|p| p.trim().strip_prefix("q=")).map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+            // En empate gana el primero de la lista
+            if let Some(q) = q && q > 0.0 && best.is_none_or(|(b, _)| q > b) {
+                best = Some((q, lang));
+            }
+        }
+        best.map(|(_, lang)| lang).unwrap_or_default()
+    }
+
+    // Elige el texto de un par (es, en)
+    pub fn pick(self, (es, en): Translated) -> &'static str {
+        match self {
+            Lang::Es => es,
+            Lang::En => en,
+        }
+    }
+}
+
+// Texto fijo en los dos idiomas, (es, en): descripciones de las rutas y del manual
+pub type Translated = (&'static str, &'static str);
+
+thread_local! {
+    static CURRENT: Cell<Lang> = const { Cell::new(Lang::Es) };
+}
+
+// Cada conexiòn tiene su propio hilo, asì que el idioma elegido se guarda por hilo
+pub fn set_lang(lang: Lang) {
+    CURRENT.with(|current| current.set(lang));
+}
+
+pub fn current_lang() -> Lang {
+    CURRENT.with(|current| current.get())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    // Mensaje del catàlogo con sus argumentos
+    Key { id: &'static str, args: Vec<(&'static str, Message)> },
+    // Texto que se entrega tal cual: resultados de tareas y mensajes que no estàn en el catàlogo
+    Text(String),
+}
+
+impl Message {
+    pub fn key(id: &'static str) -> Message {
+        Message::Key { id, args: Vec::new() }
+    }
+
+    pub fn arg(self, name: &'static str, value: impl ToString) -> Message {
+        self.arg_message(name, Message::Text(value.to_string()))
+    }
+
+    // Argumento que es otro mensaje: se traduce junto con el mensaje que lo contiene.
+    // Los workers no conocen el idioma de la conexiòn, asì que no se puede convertir a texto antes
+    pub fn arg_message(mut self, name: &'static str, value: Message) -> Message {
+        if let Message::Key { args, .. } = &mut self {
+            args.push((name, value));
+        }
+        self
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        match self {
+            Message::Text(text) => text.clone(),
+            Message::Key { id, args } => {
+                let mut text = template(id, lang).to_string();
+                for (name, value) in args {
+                    text = text.replace(&format!("{{{}}}", name), &value.render(lang));
+                }
+                text
+            }
+        }
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Message {
+        Message::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Message {
+        Message::Text(text.to_string())
+    }
+}
+
+// Se muestra en el idioma de la conexiòn del hilo actual
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(current_lang()))
+    }
+}
+
+fn template(id: &str, lang: Lang) -> &str {
+    match CATALOG.iter().find(|(key, _, _)| *key == id) {
+        Some((_, es, en)) => match lang {
+            Lang::Es => es,
+            Lang::En => en,
+        },
+        None => id,
+    }
+}
+
+// (id, español, inglès)
+const CATALOG: &[(&str, &str, &str)] = &[
+    // Paràmetros y solicitud
+    ("missing_parameter", "Falta el paràmetro '{field}'", "Missing parameter '{field}'"),
+    ("missing_input", "Falta el paràmetro 'text' o 'name' (o enviar los datos por POST)", "Missing parameter 'text' or 'name' (or send the data with POST)"),
+    ("missing_keep_or_older_than", "Se necesita al menos uno de los paràmetros 'keep' u 'older_than'", "At least one of the parameters 'keep' or 'older_than' is required"),
+    ("positive_integer", "El paràmetro '{field}' debe de ser entero positivo", "Parameter '{field}' must be a positive integer"),
+    ("invalid_mode", "El paràmetro 'mode' debe ser 'create', 'overwrite' o 'append'", "Parameter 'mode' must be 'create', 'overwrite' or 'append'"),
+    ("invalid_file_name", "Nombre del archivo invàlido (Solo se permiten alfanùmericos)", "Invalid file name (only alphanumeric characters are allowed)"),
+    ("unsupported_task", "Tarea no soportada por {route}", "Task not supported by {route}"),
+    ("simulate_random_limit", "Simulate random admite como màximo {max} valores", "Simulate random accepts at most {max} values"),
+    ("not_acceptable", "Formato de respuesta no soportado: '{format}' (se admite json, text, csv, msgpack o cbor)", "Unsupported response format: '{format}' (json, text, csv, msgpack or cbor are supported)"),
+    ("number_expected", "El paràmetro '{field}' debe de ser un nùmero", "Parameter '{field}' must be a number"),
+    ("invalid_choice", "El paràmetro '{field}' debe ser uno de: {options}", "Parameter '{field}' must be one of: {options}"),
+    ("integer_between", "El paràmetro '{field}' debe ser un entero entre {min} y {max}", "Parameter '{field}' must be an integer between {min} and {max}"),
+    ("max_exceeded", "El paràmetro '{field}' no puede ser mayor a {max}", "Parameter '{field}' cannot be greater than {max}"),
+    ("min_below_max", "El paràmetro 'min' debe ser menor estricto que el paràmetro 'max'", "Parameter 'min' must be strictly less than parameter 'max'"),
+    ("boolean_expected", "El paràmetro '{field}' debe ser 'true' o 'false'", "Parameter '{field}' must be 'true' or 'false'"),
+    ("method_not_allowed", "Mètodo {method} no permitido en '{path}'", "Method {method} not allowed on '{path}'"),
+    ("route_not_found", "Ruta no encontrada: '{path}'", "Route not found: '{path}'"),
+    ("unauthorized", "Se necesita un token vàlido (Authorization: Bearer <token>)", "A valid token is required (Authorization: Bearer <token>)"),
+    ("rate_limited", "Demasiadas solicitudes; vuelva a intentar en {seconds} s", "Too many requests; try again in {seconds} s"),
+    ("handler_panicked", "Error interno al atender la solicitud", "Internal error while handling the request"),
+    ("length_required", "Falta el encabezado 'Content-Length'", "Missing 'Content-Length' header"),
+    ("invalid_content_length", "El encabezado 'Content-Length' es invàlido", "Invalid 'Content-Length' header"),
+    ("body_too_large", "El cuerpo no puede ser mayor a {max} bytes", "The body cannot be larger than {max} bytes"),
+    ("body_too_short", "El cuerpo recibido es màs corto que 'Content-Length'", "The received body is shorter than 'Content-Length'"),
+    ("body_read_failed", "No se pudo leer el cuerpo de la solicitud", "Could not read the request body"),
+    ("input_too_large", "La entrada no puede ser mayor a {max} bytes", "The input cannot be larger than {max} bytes"),
+    ("file_too_large", "El archivo no puede ser mayor a {max} bytes", "The file cannot be larger than {max} bytes"),
+    ("input_read_failed", "No se pudieron leer los datos de entrada", "Could not read the input data"),
+    ("output_too_large", "El resultado supera el lìmite de {max} bytes", "The result exceeds the limit of {max} bytes"),
+    // Cola y trabajos
+    ("queue_full", "No se pudo encolar la tarea", "Could not enqueue the task"),
+    ("task_reply_failed", "Error al recibir resultado de la tarea", "Failed to receive the task result"),
+    ("workers_stopped", "Los workers no estàn disponibles para recibir tareas", "The workers are not available to take tasks"),
+    ("workers_unavailable", "No se pudo accerder a los datos de los workers", "Could not access the worker data"),
+    ("worker_busy", "ocupado", "busy"),
+    ("worker_idle", "disponible", "available"),
+    ("reply_error", "ERROR en respuesta", "ERROR in reply"),
+    ("job_exists", "Ya hay un trabajo en curso con el id '{job}'", "A job with id '{job}' is already running"),
+    ("job_not_found", "No hay un trabajo en curso con el id '{job}'", "There is no running job with id '{job}'"),
+    ("job_cancelled", "Trabajo '{job}' cancelado", "Job '{job}' cancelled"),
+    ("stats_reset", "Estadìsticas de /status reiniciadas", "/status statistics reset"),
+    ("search_stopped", "Bùsqueda cancelada tras revisar {bytes} bytes ({matches} coincidencias parciales)", "Search stopped after scanning {bytes} bytes ({matches} partial matches)"),
+    ("slept", "Simulado por {seconds} segundos", "Slept for {seconds} seconds"),
+    // Archivos y versiones
+    ("dir_create_failed", "No se pudo crear el directorio", "Could not create the directory"),
+    ("file_created", "Archivo '{path}' creado exitosamente", "File '{path}' created successfully"),
+    ("file_overwritten", "Archivo '{path}' sobrescrito exitosamente", "File '{path}' overwritten successfully"),
+    ("file_appended", "Contenido agregado al archivo '{path}'", "Content appended to file '{path}'"),
+    ("file_saved", "Archivo '{path}' guardado ({bytes} bytes)", "File '{path}' saved ({bytes} bytes)"),
+    ("file_deleted", "Archivo '{path}' eliminado exitosamente", "File '{path}' deleted successfully"),
+    ("file_exists", "El archivo '{path}' ya existe", "File '{path}' already exists"),
+    ("file_not_found", "El archivo '{path}' no existe", "File '{path}' does not exist"),
+    ("storage_read_failed", "No se pudo leer el directorio de archivos", "Could not read the files directory"),
+    ("file_read_failed", "No se pudo leer el archivo", "Could not read the file"),
+    ("file_write_failed", "Error escribiendo en el archivo", "Error writing the file"),
+    ("file_delete_failed", "No se pudo eliminar el archivo '{path}'", "Could not delete file '{path}'"),
+    ("version_save_failed", "No se pudo guardar la versiòn anterior del archivo", "Could not save the previous version of the file"),
+    ("versions_read_failed", "No se pudieron leer las versiones", "Could not read the versions"),
+    ("no_versions", "El archivo '{name}' no tiene versiones guardadas", "File '{name}' has no saved versions"),
+    ("versions_list", "Versiones de '{name}': {versions}", "Versions of '{name}': {versions}"),
+    ("version_not_found", "La versiòn {version} del archivo '{name}' no existe", "Version {version} of file '{name}' does not exist"),
+    ("version_not_text", "La versiòn {version} del archivo '{name}' no es texto UTF-8; restàurela y descàrguela con /files/", "Version {version} of file '{name}' is not UTF-8 text; restore it and download it with /files/"),
+    ("version_restored", "Archivo '{path}' restaurado a la versiòn {version}", "File '{path}' restored to version {version}"),
+    ("versions_purged", "Se eliminaron {count} versiones del archivo '{name}'", "Deleted {count} versions of file '{name}'"),
+    // /random
+    ("random_range_missing", "Falta el paràmetro '{field}' (se necesitan 'count', 'min' y 'max')", "Missing parameter '{field}' ('count', 'min' and 'max' are required)"),
+    ("random_range_overflow", "El paràmetro 'max' està demasiado lejos de 'min': el rango se desborda", "Parameter 'max' is too far from 'min': the range overflows"),
+    ("random_negative", "El paràmetro '{field}' no puede ser negativo", "Parameter '{field}' cannot be negative"),
+    ("random_not_positive", "El paràmetro '{field}' debe ser mayor que 0", "Parameter '{field}' must be greater than 0"),
+    ("random_sample_too_large", "Sin reemplazo 'count' no puede ser mayor que la cantidad de valores entre 'min' y 'max'", "Without replacement 'count' cannot exceed the number of values between 'min' and 'max'"),
+    ("random_items_missing", "Falta el paràmetro 'items' (valores separados por coma)", "Missing parameter 'items' (comma separated values)"),
+    ("random_invalid_distribution", "Distribuciòn invàlida: {error}", "Invalid distribution: {error}"),
+    // Fechas
+    ("invalid_offset", "Desfase horario invàlido: '{value}'", "Invalid UTC offset: '{value}'"),
+    ("unknown_zone", "Zona horaria desconocida: '{value}'", "Unknown time zone: '{value}'"),
+    ("invalid_date_format", "El paràmetro 'format' debe ser 'rfc3339', 'rfc2822', 'unix' o un patròn strftime (con %)", "Parameter 'format' must be 'rfc3339', 'rfc2822', 'unix' or a strftime pattern (with %)"),
+    ("invalid_strftime", "Patròn strftime invàlido: '{pattern}'", "Invalid strftime pattern: '{pattern}'"),
+    ("invalid_duration", "Duraciòn invàlida: '{value}' (ejemplo: 1d2h30m, -90s, 1mo)", "Invalid duration: '{value}' (example: 1d2h30m, -90s, 1mo)"),
+    ("date_pattern_mismatch", "La fecha '{input}' no coincide con el patròn '{pattern}': {error}", "Date '{input}' does not match pattern '{pattern}': {error}"),
+    ("timestamp_out_of_range", "Marca de tiempo fuera de rango: {value}", "Timestamp out of range: {value}"),
+    ("unknown_date_format", "No se reconoce el formato de la fecha '{input}' (use RFC 3339, RFC 2822, segundos Unix o 'input_format')", "Unrecognized date format '{input}' (use RFC 3339, RFC 2822, Unix seconds or 'input_format')"),
+    ("nonexistent_local_time", "La hora '{time}' no existe en la zona indicada (cambio de horario)", "Time '{time}' does not exist in the given zone (daylight saving change)"),
+    ("date_out_of_range", "El resultado queda fuera del rango de fechas soportado", "The result is outside the supported date range"),
+    // /calc
+    ("invalid_at", "Error en la posiciòn {pos}: {error}", "Error at position {pos}: {error}"),
+    ("calc_invalid_variable", "Nombre de variable invàlido: '{name}'", "Invalid variable name: '{name}'"),
+    ("calc_too_long", "La expresiòn no puede tener màs de {max} caracteres", "The expression cannot be longer than {max} characters"),
+    ("unexpected_char", "carácter inesperado '{char}'", "unexpected character '{char}'"),
+    ("calc_unexpected_end", "la expresiòn terminò antes de tiempo", "the expression ended too early"),
+    ("calc_expected", "se esperaba {expected}", "expected {expected}"),
+    ("calc_max_depth", "se superò el anidamiento màximo de {max}", "the maximum nesting of {max} was exceeded"),
+    ("calc_unknown_variable", "variable desconocida '{name}'", "unknown variable '{name}'"),
+    ("calc_invalid_number", "nùmero invàlido '{number}'", "invalid number '{number}'"),
+    ("calc_integer_too_large", "el entero '{number}' es demasiado grande", "the integer '{number}' is too large"),
+    ("calc_add_overflow", "desbordamiento en la suma", "overflow in addition"),
+    ("calc_sub_overflow", "desbordamiento en la resta", "overflow in subtraction"),
+    ("calc_mul_overflow", "desbordamiento en la multiplicaciòn", "overflow in multiplication"),
+    ("calc_div_overflow", "desbordamiento en la divisiòn", "overflow in division"),
+    ("calc_rem_overflow", "desbordamiento en el mòdulo", "overflow in remainder"),
+    ("calc_pow_overflow", "desbordamiento en la potencia", "overflow in power"),
+    ("calc_neg_overflow", "desbordamiento al negar", "overflow in negation"),
+    ("calc_abs_overflow", "desbordamiento en abs", "overflow in abs"),
+    ("calc_division_by_zero", "divisiòn entre cero", "division by zero"),
+    ("calc_modulo_by_zero", "mòdulo entre cero", "remainder by zero"),
+    ("calc_exponent_too_large", "el exponente no puede ser mayor a {max}", "the exponent cannot be greater than {max}"),
+    ("calc_pow_not_real", "la potencia no tiene resultado real", "the power has no real result"),
+    ("calc_arity", "la funciòn '{name}' recibe {count} argumento(s)", "function '{name}' takes {count} argument(s)"),
+    ("calc_not_real", "'{name}' no tiene resultado real para esos argumentos", "'{name}' has no real result for those arguments"),
+    ("calc_no_arguments", "la funciòn '{name}' necesita al menos un argumento", "function '{name}' needs at least one argument"),
+    ("calc_unknown_function", "funciòn desconocida '{name}' (disponibles: {available})", "unknown function '{name}' (available: {available})"),
+    // /csv
+    ("csv_sort_column", "El paràmetro 'sort' debe indicar una columna", "Parameter 'sort' must name a column"),
+    ("csv_invalid_delimiter", "El paràmetro 'delimiter' debe ser un solo carácter ascii o 'tab'", "Parameter 'delimiter' must be a single ascii character or 'tab'"),
+    ("csv_invalid_filter", "Condiciòn invàlida: '{filter}' (use columna=valor, !=, <, <=, >, >= o ~ para contiene)", "Invalid condition: '{filter}' (use column=value, !=, <, <=, >, >= or ~ for contains)"),
+    ("csv_unknown_aggregate", "Agregado desconocido: '{name}' (disponibles: count, sum, avg, min, max)", "Unknown aggregate: '{name}' (available: count, sum, avg, min, max)"),
+    ("csv_aggregate_column", "El agregado '{name}' necesita una columna ({name}:columna)", "Aggregate '{name}' needs a column ({name}:column)"),
+    ("csv_unclosed_quote", "comillas sin cerrar", "unclosed quotes"),
+    ("csv_invalid_utf8", "el texto no es utf-8 vàlido", "the text is not valid utf-8"),
+    ("csv_invalid_row", "CSV invàlido en la fila {row}: {error}", "Invalid CSV at row {row}: {error}"),
+    ("csv_field_count", "La fila {row} tiene {found} campos, se esperaban {expected}", "Row {row} has {found} fields, expected {expected}"),
+    ("csv_invalid_header", "CSV invàlido en el encabezado: {error}", "Invalid CSV header: {error}"),
+    ("csv_empty", "El CSV està vacìo", "The CSV is empty"),
+    ("csv_unknown_column", "Columna desconocida: '{name}' (columnas: {columns})", "Unknown column: '{name}' (columns: {columns})"),
+    ("csv_output_too_large", "El resultado supera {max} bytes; use limit= o save=archivo", "The result exceeds {max} bytes; use limit= or save=file"),
+    ("csv_write_failed", "Error escribiendo el resultado", "Error writing the result"),
+    ("csv_too_many_groups", "Hay màs de {max} grupos distintos", "There are more than {max} distinct groups"),
+    ("csv_not_numeric", "El valor '{value}' de la columna '{column}' no es numèrico", "Value '{value}' in column '{column}' is not numeric"),
+    ("csv_run_write_failed", "No se pudo escribir un bloque temporal", "Could not write a temporary run"),
+    ("csv_run_read_failed", "No se pudo leer un bloque temporal", "Could not read a temporary run"),
+    ("csv_saved", "Resultado guardado en '{path}' ({rows} filas, {bytes} bytes)", "Result saved to '{path}' ({rows} rows, {bytes} bytes)"),
+    ("csv_saved_sorted", "Resultado guardado en '{path}' ({rows} filas, {bytes} bytes, {runs} bloques ordenados en disco)", "Result saved to '{path}' ({rows} rows, {bytes} bytes, {runs} sorted runs on disk)"),
+    // /encode, /decode y /hash
+    ("codec_unknown", "Codificaciòn desconocida: '{name}' (disponibles: {available})", "Unknown encoding: '{name}' (available: {available})"),
+    ("codec_hash_and_save", "Los paràmetros 'hash' y 'save' no se pueden combinar", "Parameters 'hash' and 'save' cannot be combined"),
+    ("codec_not_utf8", "El resultado no es texto UTF-8; use 'hash=true' o 'save=<nombre>'", "The result is not UTF-8 text; use 'hash=true' or 'save=<name>'"),
+    ("codec_saved", "Resultado guardado en '{path}' ({bytes} bytes)", "Result saved to '{path}' ({bytes} bytes)"),
+    ("codec_invalid_input", "Entrada {encoding} invàlida: {reason} en la posiciòn {pos}", "Invalid {encoding} input: {reason} at position {pos}"),
+    ("codec_bad_length", "longitud invàlida", "invalid length"),
+    ("codec_bad_symbol", "sìmbolo invàlido", "invalid symbol"),
+    ("codec_trailing_bits", "bits sobrantes distintos de cero", "non-zero trailing bits"),
+    ("codec_bad_padding", "relleno invàlido", "invalid padding"),
+    ("codec_incomplete_percent", "secuencia '%' incompleta", "incomplete '%' sequence"),
+    ("codec_incomplete_equals", "'=' sin dos dìgitos hexadecimales", "'=' without two hexadecimal digits"),
+    ("codec_byte_not_allowed", "byte {byte} no permitido", "byte {byte} not allowed"),
+    // /json
+    ("json_with_missing", "Falta el paràmetro 'with' (archivo) o 'with_text' para comparar", "Missing parameter 'with' (file) or 'with_text' to compare against"),
+    ("json_unknown_op", "Operaciòn JSON desconocida: '{op}' (disponibles: pretty, minify, validate, sort, query, diff)", "Unknown JSON operation: '{op}' (available: pretty, minify, validate, sort, query, diff)"),
+    ("json_invalid_path", "Ruta invàlida en la posiciòn {pos}: {error}", "Invalid path at position {pos}: {error}"),
+    ("json_path_start", "debe empezar con '$'", "must start with '$'"),
+    ("json_bracket_after_dot", "no se esperaba '[' después de '.'", "unexpected '[' after '.'"),
+    ("json_missing_field", "falta el nombre del campo", "missing field name"),
+    ("json_missing_bracket", "falta el ']' de cierre", "missing closing ']'"),
+    ("json_invalid_filter", "filtro invàlido, use ?(@.campo op valor)", "invalid filter, use ?(@.field op value)"),
+    ("json_invalid_slice", "rango invàlido, use [inicio:fin]", "invalid slice, use [start:end]"),
+    ("json_invalid_bracket", "se esperaba un ìndice, un nombre entre comillas, '*', un rango o un filtro", "expected an index, a quoted name, '*', a slice or a filter"),
+    ("json_invalid_document", "El documento no es JSON vàlido: {error} (lìnea {line}, columna {column})", "The document is not valid JSON: {error} (line {line}, column {column})"),
+    ("json_invalid_second_document", "El segundo documento no es JSON vàlido: {error} (lìnea {line}, columna {column})", "The second document is not valid JSON: {error} (line {line}, column {column})"),
+    ("json_valid", "JSON vàlido: {summary}", "Valid JSON: {summary}"),
+    ("json_invalid", "JSON invàlido: {error} (lìnea {line}, columna {column})", "Invalid JSON: {error} (line {line}, column {column})"),
+    ("json_summary", "{kind}, {values} valores, profundidad {depth}", "{kind}, {values} values, depth {depth}"),
+    ("json_object", "objeto con {count} claves", "object with {count} keys"),
+    ("json_array", "arreglo con {count} elementos", "array with {count} items"),
+    ("json_string", "texto", "string"),
+    ("json_number", "nùmero", "number"),
+    ("json_boolean", "booleano", "boolean"),
+    // /grep
+    ("grep_empty_pattern", "El patròn de bùsqueda no puede estar vacìo", "The search pattern cannot be empty"),
+    ("grep_invalid_regex", "Expresiòn regular invàlida: {error}", "Invalid regular expression: {error}"),
+    ("grep_file_read_failed", "No se pudo leer el archivo '{name}'", "Could not read file '{name}'"),
+    ("grep_summary", "{matches} coincidencias en {files_with_matches} archivos ({files} archivos y {bytes} bytes revisados){partial}{lines}", "{matches} matches in {files_with_matches} files ({files} files and {bytes} bytes scanned){partial}{lines}"),
+    ("grep_partial", " - se alcanzò el lìmite, resultados parciales", " - limit reached, partial results"),
+    // Tareas de CPU
+    ("bench_primes", "{count} primos menores o iguales a {n} ({ms} ms)", "{count} primes less than or equal to {n} ({ms} ms)"),
+    ("bench_matrix", "Matriz {size}x{size} multiplicada, traza {trace} ({ms} ms, {gflops} GFLOPS)", "{size}x{size} matrix multiplied, trace {trace} ({ms} ms, {gflops} GFLOPS)"),
+    ("bench_pi", "pi ~ {pi} con {samples} muestras, error {error} ({ms} ms)", "pi ~ {pi} with {samples} samples, error {error} ({ms} ms)"),
+    ("bench_sort", "{size} elementos ordenados, mìnimo {min}, mediana {median}, màximo {max} ({ms} ms)", "{size} items sorted, minimum {min}, median {median}, maximum {max} ({ms} ms)"),
+    ("bench_collatz", "Bajo {limit} la secuencia de Collatz màs larga empieza en {number} con {steps} pasos ({ms} ms)", "Below {limit} the longest Collatz sequence starts at {number} with {steps} steps ({ms} ms)"),
+    // /mandelbrot
+    ("mandelbrot_invalid_window", "La ventana es invàlida: se necesita x_min < x_max y y_min < y_max", "Invalid window: x_min < x_max and y_min < y_max are required"),
+    ("mandelbrot_tile", "Filas {start}..{end} renderizadas en {ms} ms", "Rows {start}..{end} rendered in {ms} ms"),
+    ("image_encode_failed", "No se pudo codificar la imagen: {error}", "Could not encode the image: {error}"),
+    ("mandelbrot_saved", "Imagen {width}x{height} guardada en '{path}' ({bytes} bytes): {tiles} franjas, {wall} ms de pared, {compute} ms sumando las franjas, aceleraciòn {speedup}x", "Image {width}x{height} saved to '{path}' ({bytes} bytes): {tiles} tiles, {wall} ms wall time, {compute} ms summing the tiles, speedup {speedup}x"),
+    // /compress y /decompress
+    ("output_name_invalid", "Nombre del archivo de salida invàlido", "Invalid output file name"),
+    ("output_same_as_input", "El archivo de salida debe ser distinto al de entrada", "The output file must be different from the input file"),
+    ("output_exists", "El archivo '{path}' ya existe (use overwrite=true)", "File '{path}' already exists (use overwrite=true)"),
+    ("compressed_data_invalid", "El archivo '{name}' no contiene datos {format} vàlidos", "File '{name}' does not contain valid {format} data"),
+    ("compression_failed", "No se pudo procesar el archivo '{name}'", "Could not process file '{name}'"),
+    ("compress_report", "Comprimido '{path}' ({format}, nivel {level}){destination}: {original} -> {written} bytes, ratio {ratio} ({saving}% de ahorro), {ms} ms, {speed} MB/s", "Compressed '{path}' ({format}, level {level}){destination}: {original} -> {written} bytes, ratio {ratio} ({saving}% saved), {ms} ms, {speed} MB/s"),
+    ("decompress_report", "Descomprimido '{path}' ({format}){destination}: {original} -> {written} bytes, ratio {ratio} ({saving}% de ahorro), {ms} ms, {speed} MB/s", "Decompressed '{path}' ({format}){destination}: {original} -> {written} bytes, ratio {ratio} ({saving}% saved), {ms} ms, {speed} MB/s"),
+    // Texto
+    ("text_unknown_op", "Operaciòn de texto desconocida: '{name}' (disponibles: {available})", "Unknown text operation: '{name}' (available: {available})"),
+    ("text_empty_ops", "El paràmetro 'ops' no puede estar vacìo", "Parameter 'ops' cannot be empty"),
+];
+
+#[cfg(test)]
+mod test {
+    use super::{current_lang, set_lang, Lang, Message, CATALOG};
+
+    #[test]
+    fn test_negotiate_language() {
+        assert_eq!(Lang::negotiate(None, None), Lang::Es);
+        assert_eq!(Lang::negotiate(None, Some("en-US,en;q=0.9")), Lang::En);
+        assert_eq!(Lang::negotiate(None, Some("fr-FR, en;q=0.5, es;q=0.8")), Lang::Es);
+        assert_eq!(Lang::negotiate(None, Some("de, fr;q=0.9")), Lang::Es);
+        assert_eq!(Lang::negotiate(None, Some("en;q=0, es;q=0.1")), Lang::Es);
+        assert_eq!(Lang::negotiate(Some("en"), Some("es")), Lang::En);
+        assert_eq!(Lang::negotiate(Some("xx"), Some("en_GB")), Lang::En);
+    }
+
+    #[test]
+    fn test_render_message() {
+        let message = Message::key("file_exists").arg("path", "archivos/a.txt");
+        assert_eq!(message.render(Lang::Es), "El archivo 'archivos/a.txt' ya existe");
+        assert_eq!(message.render(Lang::En), "File 'archivos/a.txt' already exists");
+        assert_eq!(Message::from("sin traducir").render(Lang::En), "sin traducir");
+
+        let nested = Message::key("output_name_invalid");
+        let message = Message::key("invalid_at").arg("pos", 3).arg_message("error", nested);
+        assert_eq!(message.render(Lang::En), "Error at position 3: Invalid output file name");
+
+        assert_eq!(current_lang(), Lang::Es);
+        set_lang(Lang::En);
+        assert_eq!(Message::key("queue_full").to_string(), "Could not enqueue the task");
+        set_lang(Lang::Es);
+    }
+
+    #[test]
+    fn test_catalog_is_consistent() {
+        let placeholders = |text: &str| {
+            let mut names: Vec<String> = text.split('{').skip(1).filter_map(|rest| rest.split_once('}')).map(|(name, _)| name.to_string()).collect();
+            names.sort();
+            names
+        };
+        for (i, (id, es, en)) in CATALOG.iter().enumerate() {
+            assert!(CATALOG[..i].iter().all(|(other, _, _)| other != id), "id repetido: {}", id);
+            assert_eq!(placeholders(es), placeholders(en), "argumentos distintos en '{}'", id);
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use serde_json::{json, Value};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::storage::InputSource;
+
+// Transformaciones de documentos JSON (/json): consultas tipo JSONPath, formato, validaciòn,
+// orden de claves y diferencias entre dos documentos
+
+pub const MAX_JSON_BYTES: u64 = 16 * 1024 * 1024;
+pub const MAX_INDENT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonOp {
+    Pretty,
+    Minify,
+    Validate,
+    SortKeys,
+    Query(Vec<Segment>),
+    // Diferencias contra un segundo documento
+    Diff(InputSource),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSpec {
+    pub op: JsonOp,
+    // Sangrìa de la salida; con None solo `pretty` sangra (2 espacios)
+    pub indent: Option<usize>,
+}
+
+// /json?op=query&path=$.items[*].name | op=pretty&indent=4 | op=diff&with=otro_archivo
+pub fn parse_json_params(params: &HashMap<String, String>) -> Result<JsonSpec, ApiError> {
+    let op = match params.get("op").map(|o| o.as_str()) {
+        Some("pretty") => JsonOp::Pretty,
+        Some("minify") => JsonOp::Minify,
+        Some("validate") => JsonOp::Validate,
+        Some("sort") => JsonOp::SortKeys,
+        Some("query") => JsonOp::Query(parse_path(params.get("path").ok_or(ApiError::missing("path"))?).map_err(|e| ApiError::invalid("path", e))?),
+        Some("diff") => match (params.get("with"), params.get("with_text")) {
+            (Some(name), _) => JsonOp::Diff(InputSource::Stored(name.clone())),
+            (None, Some(text)) => JsonOp::Diff(InputSource::Inline(text.clone())),
+            (None, None) => return Err(ApiError::MissingParameter { field: "with".into(), message: Message::key("json_with_missing") }),
+        },
+        Some(other) => return Err(ApiError::invalid("op", Message::key("json_unknown_op").arg("op", other))),
+        None => return Err(ApiError::missing("op")),
+    };
+
+    let indent = match params.get("indent") {
+        Some(i) => Some(i.parse::<usize>().ok().filter(|i| *i <= MAX_INDENT)
+            .ok_or(ApiError::not_in_range("indent", 0, MAX_INDENT))?),
+        None => None,
+    };
+    Ok(JsonSpec { op, indent })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Child(String),
+    Index(i64),
+    Wildcard,
+    // [inicio:fin], con negativos contados desde el final
+    Slice(Option<i64>, Option<i64>),
+    // ..segmento: aplica el segmento al nodo y a todos sus descendientes
+    Recursive(Box<Segment>),
+    Filter(Predicate),
+}
+
+// [?(@.campo op valor)] o [?(@.campo)] para "existe"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub field: Vec<String>,
+    pub comparison: Option<(String, Value)>,
+}
+
+impl Predicate {
+    fn matches(&self, node: &Value) -> bool {
+        let mut current = node;
+        for name in &self.field {
+            match current.get(name) {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+        let Some((op, expected)) = &self.comparison else {
+            return true;
+        };
+        let ordering = match (current, expected) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match op.as_str() {
+            "==" => current == expected,
+            "!=" => current != expected,
+            "<" => ordering.is_some_and(|o| o.is_lt()),
+            "<=" => ordering.is_some_and(|o| o.is_le()),
+            ">" => ordering.is_some_and(|o| o.is_gt()),
+            _ => ordering.is_some_and(|o| o.is_ge()),
+        }
+    }
+}
+
+// Ruta tipo JSONPath: $, .nombre, ['nombre'], [n], [*], .*, [a:b], ..nombre y [?(@.campo op valor)]
+pub fn parse_path(path: &str) -> Result<Vec<Segment>, Message> {
+    let chars: Vec<char> = path.chars().collect();
+    let error = |pos: usize, msg: Message| Message::key("json_invalid_path").arg("pos", pos).arg_message("error", msg);
+    if chars.first() != Some(&'$') {
+        return Err(error(0, Message::key("json_path_start")));
+    }
+
+    let mut segments = Vec::new();
+    let mut pos = 1;
+    while pos < chars.len() {
+        let recursive = chars[pos] == '.' && chars.get(pos + 1) == Some(&'.');
+        let segment = if chars[pos] == '.' {
+            pos += if recursive { 2 } else { 1 };
+            if chars.get(pos) == Some(&'[') {
+                if !recursive {
+                    return Err(error(pos, Message::key("json_bracket_after_dot")));
+                }
+                parse_bracket(&chars, &mut pos).map_err(|(p, msg)| error(p, msg))?
+            } else if chars.get(pos) == Some(&'*') {
+                pos += 1;
+                Segment::Wildcard
+            } else {
+                let start = pos;
+                while pos < chars.len() && !matches!(chars[pos], '.' | '[') {
+                    pos += 1;
+                }
+                if start == pos {
+                    return Err(error(start, Message::key("json_missing_field")));
+                }
+                Segment::Child(chars[start..pos].iter().collect())
+            }
+        } else if chars[pos] == '[' {
+            parse_bracket(&chars, &mut pos).map_err(|(p, msg)| error(p, msg))?
+        } else {
+            return Err(error(pos, Message::key("unexpected_char").arg("char", chars[pos])));
+        };
+        segments.push(if recursive { Segment::Recursive(Box::new(segment)) } else { segment });
+    }
+    Ok(segments)
+}
+
+// `pos` apunta al '['; al terminar queda después del ']'
+fn parse_bracket(chars: &[char], pos: &mut usize) -> Result<Segment, (usize, Message)> {
+    let open = *pos;
+    let close = if chars.get(open + 1) == Some(&'?') {
+        // El filtro puede tener ']' dentro de un texto, se busca el ')]' que lo cierra
+        (open..chars.len().saturating_sub(1)).find(|i| chars[*i] == ')' && chars[*i + 1] == ']').map(|i| i + 1)
+    } else {
+        (open..chars.len()).find(|i| chars[*i] == ']')
+    }.ok_or_else(|| (open, Message::key("json_missing_bracket")))?;
+    let inner: String = chars[open + 1..close].iter().collect();
+    let inner = inner.trim();
+    *pos = close + 1;
+
+    let invalid = |id: &'static str| (open + 1, Message::key(id));
+    if inner == "*" {
+        return Ok(Segment::Wildcard);
+    }
+    if let Some(filter) = inner.strip_prefix("?(").and_then(|f| f.strip_suffix(')')) {
+        return parse_predicate(filter.trim()).map(Segment::Filter).ok_or_else(|| invalid("json_invalid_filter"));
+    }
+    if inner.len() >= 2 && (inner.starts_with('\'') && inner.ends_with('\'') || inner.starts_with('"') && inner.ends_with('"')) {
+        return Ok(Segment::Child(inner[1..inner.len() - 1].to_string()));
+    }
+    if let Some((start, end)) = inner.split_once(':') {
+        let bound = |b: &str| if b.trim().is_empty() { Ok(None) } else { b.trim().parse::<i64>().map(Some) };
+        return match (bound(start), bound(end)) {
+            (Ok(start), Ok(end)) => Ok(Segment::Slice(start, end)),
+            _ => Err(invalid("json_invalid_slice")),
+        };
+    }
+    inner.parse::<i64>().map(Segment::Index).map_err(|_| invalid("json_invalid_bracket"))
+}
+
+fn parse_predicate(filter: &str) -> Option<Predicate> {
+    let rest = filter.strip_prefix('@')?;
+    let op_start = rest.find(['=', '!', '<', '>']).unwrap_or(rest.len());
+    let field_part = rest[..op_start].trim();
+    let field: Vec<String> = if field_part.is_empty() {
+        Vec::new()
+    } else {
+        field_part.strip_prefix('.')?.split('.').map(|f| f.to_string()).collect()
+    };
+    if field.iter().any(|f| f.is_empty()) {
+        return None;
+    }
+
+    let condition = rest[op_start..].trim();
+    if condition.is_empty() {
+        return Some(Predicate { field, comparison: None });
+    }
+    let op = ["==", "!=", "<=", ">=", "<", ">"].into_iter().find(|op| condition.starts_with(op))?;
+    let literal = condition[op.len()..].trim();
+    // Se aceptan comillas simples para los textos, como en JSONPath
+    let literal = match literal.strip_prefix('\'').and_then(|l| l.strip_suffix('\'')) {
+        Some(text) => Value::String(text.to_string()),
+        None => serde_json::from_str(literal).ok()?,
+    };
+    Some(Predicate { field, comparison: Some((op.to_string(), literal)) })
+}
+
+pub fn query<'a>(root: &'a Value, segments: &[Segment]) -> Vec<&'a Value> {
+    let mut nodes = vec![root];
+    for segment in segments {
+        nodes = nodes.into_iter().flat_map(|node| apply(node, segment)).collect();
+    }
+    nodes
+}
+
+fn apply<'a>(node: &'a Value, segment: &Segment) -> Vec<&'a Value> {
+    match (segment, node) {
+        (Segment::Child(name), Value::Object(map)) => map.get(name).into_iter().collect(),
+        (Segment::Index(i), Value::Array(items)) => {
+            let index = if *i < 0 { items.len() as i64 + i } else { *i };
+            usize::try_from(index).ok().and_then(|i| items.get(i)).into_iter().collect()
+        }
+        (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+        (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+        (Segment::Slice(start, end), Value::Array(items)) => {
+            let len = items.len() as i64;
+            let clamp = |b: i64| (if b < 0 { len + b } else { b }).clamp(0, len) as usize;
+            let (start, end) = (clamp(start.unwrap_or(0)), clamp(end.unwrap_or(len)));
+            if start < end { items[start..end].iter().collect() } else { Vec::new() }
+        }
+        (Segment::Filter(predicate), Value::Array(items)) => items.iter().filter(|item| predicate.matches(item)).collect(),
+        (Segment::Filter(predicate), Value::Object(map)) => map.values().filter(|item| predicate.matches(item)).collect(),
+        (Segment::Recursive(inner), _) => {
+            let mut descendants = Vec::new();
+            collect_descendants(node, &mut descendants);
+            descendants.into_iter().flat_map(|n| apply(n, inner)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// El propio nodo y todos sus descendientes, en preorden
+fn collect_descendants<'a>(node: &'a Value, out: &mut Vec<&'a Value>) {
+    out.push(node);
+    match node {
+        Value::Array(items) => items.iter().for_each(|item| collect_descendants(item, out)),
+        Value::Object(map) => map.values().for_each(|item| collect_descendants(item, out)),
+        _ => {}
+    }
+}
+
+pub fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, mut item) in entries {
+                sort_keys(&mut item);
+                map.insert(key, item);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
+
+// Diferencias al estilo JSON Patch (RFC 6902): add, remove y replace con rutas JSON Pointer.
+// `replace` incluye el valor anterior en "old"
+pub fn diff(old: &Value, new: &Value, path: &str, changes: &mut Vec<Value>) {
+    match (old, new) {
+        (a, b) if a == b => {}
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match b.get(key) {
+                    Some(other) => diff(value, other, &child, changes),
+                    None => changes.push(json!({"op": "remove", "path": child, "old": value})),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                changes.push(json!({"op": "add", "path": format!("{}/{}", path, escape_pointer(key)), "value": value}));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff(x, y, &format!("{}/{}", path, i), changes);
+            }
+            // Los sobrantes se quitan desde el final para que los ìndices sigan siendo vàlidos al aplicar el parche
+            for i in (b.len()..a.len()).rev() {
+                changes.push(json!({"op": "remove", "path": format!("{}/{}", path, i), "old": a[i]}));
+            }
+            for (i, value) in b.iter().enumerate().skip(a.len()) {
+                changes.push(json!({"op": "add", "path": format!("{}/{}", path, i), "value": value}));
+            }
+        }
+        _ => changes.push(json!({"op": "replace", "path": path, "old": old, "value": new})),
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// `which` es el id del mensaje de error: el documento principal o el segundo de un diff
+fn parse_document(input: &InputSource, which: &'static str) -> Result<Value, ApiError> {
+    let data = input.read_all(MAX_JSON_BYTES)?;
+    serde_json::from_slice(&data).map_err(|e| ApiError::invalid_input(syntax_error(which, &e)))
+}
+
+fn syntax_error(id: &'static str, error: &serde_json::Error) -> Message {
+    Message::key(id).arg("error", describe(error)).arg("line", error.line()).arg("column", error.column())
+}
+
+// Mensaje del error de serde sin la posiciòn, que se agrega aparte
+fn describe(error: &serde_json::Error) -> String {
+    let text = error.to_string();
+    match text.rfind(" at line ") {
+        Some(i) => text[..i].to_string(),
+        None => text,
+    }
+}
+
+fn to_text<T: Serialize>(value: &T, indent: Option<usize>) -> String {
+    match indent {
+        None | Some(0) => serde_json::to_string(value).unwrap(),
+        Some(n) => {
+            let spaces = " ".repeat(n);
+            let mut out = Vec::new();
+            let mut serializer = Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(spaces.as_bytes()));
+            value.serialize(&mut serializer).unwrap();
+            String::from_utf8(out).unwrap()
+        }
+    }
+}
+
+pub fn run_json(input: &InputSource, spec: &JsonSpec) -> Result<Message, ApiError> {
+    if spec.op == JsonOp::Validate {
+        let data = input.read_all(MAX_JSON_BYTES)?;
+        return Ok(match serde_json::from_slice::<Value>(&data) {
+            Ok(value) => Message::key("json_valid").arg_message("summary", summary(&value)),
+            Err(e) => syntax_error("json_invalid", &e),
+        });
+    }
+
+    let mut document = parse_document(input, "json_invalid_document")?;
+    let text = match &spec.op {
+        JsonOp::Pretty => to_text(&document, Some(spec.indent.unwrap_or(2))),
+        JsonOp::Minify => to_text(&document, None),
+        JsonOp::SortKeys => {
+            sort_keys(&mut document);
+            to_text(&document, spec.indent)
+        }
+        JsonOp::Query(segments) => to_text(&query(&document, segments), spec.indent),
+        JsonOp::Diff(other) => {
+            // El segundo documento llega por 'with', no por 'name'
+            let other = parse_document(other, "json_invalid_second_document").map_err(|e| match e {
+                ApiError::NotFound { message, .. } => ApiError::not_found(Some("with"), message),
+                e => e,
+            })?;
+            let mut changes = Vec::new();
+            diff(&document, &other, "", &mut changes);
+            to_text(&changes, spec.indent)
+        }
+        JsonOp::Validate => unreachable!(),
+    };
+    Ok(text.into())
+}
+
+fn summary(value: &Value) -> Message {
+    fn walk(value: &Value, depth: usize, stats: &mut (usize, usize)) {
+        stats.0 += 1;
+        stats.1 = stats.1.max(depth);
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| walk(item, depth + 1, stats)),
+            Value::Object(map) => map.values().for_each(|item| walk(item, depth + 1, stats)),
+            _ => {}
+        }
+    }
+    let mut stats = (0, 0);
+    walk(value, 0, &mut stats);
+
+    let kind = match value {
+        Value::Object(map) => Message::key("json_object").arg("count", map.len()),
+        Value::Array(items) => Message::key("json_array").arg("count", items.len()),
+        Value::String(_) => Message::key("json_string"),
+        Value::Number(_) => Message::key("json_number"),
+        Value::Bool(_) => Message::key("json_boolean"),
+        Value::Null => Message::from("null"),
+    };
+    Message::key("json_summary").arg_message("kind", kind).arg("values", stats.0).arg("depth", stats.1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use crate::error_responses::ApiError;
+    use crate::i18n::Lang;
+    use crate::storage::InputSource;
+
+    use super::{diff, parse_json_params, parse_path, query, run_json, sort_keys};
+
+    fn store() -> Value {
+        json!({
+            "tienda": {
+                "libros": [
+                    {"titulo": "Cien años", "precio": 12.5, "autor": "García"},
+                    {"titulo": "Rayuela", "precio": 8, "autor": "Cortázar", "isbn": "978-0"},
+                    {"titulo": "Ficciones", "precio": 15, "autor": "Borges"}
+                ],
+                "bici": {"color": "rojo", "precio": 200}
+            }
+        })
+    }
+
+    fn run_query(path: &str) -> Vec<Value> {
+        query(&store(), &parse_path(path).unwrap()).into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_query_paths() {
+        assert_eq!(run_query("$.tienda.bici.color"), vec![json!("rojo")]);
+        assert_eq!(run_query("$.tienda.libros[-1].autor"), vec![json!("Borges")]);
+        assert_eq!(run_query("$['tienda']['libros'][0:2].titulo"), vec![json!("Cien años"), json!("Rayuela")]);
+        assert_eq!(run_query("$..precio"), vec![json!(12.5), json!(8), json!(15), json!(200)]);
+        assert_eq!(run_query("$.tienda.libros[*].isbn"), vec![json!("978-0")]);
+        assert_eq!(run_query("$.tienda.libros[?(@.precio < 13)].titulo"), vec![json!("Cien años"), json!("Rayuela")]);
+        assert_eq!(run_query("$..libros[?(@.autor == 'Borges')].precio"), vec![json!(15)]);
+        assert_eq!(run_query("$.tienda.libros[?(@.isbn)].titulo"), vec![json!("Rayuela")]);
+        assert_eq!(run_query("$.tienda.*.color"), vec![json!("rojo")]);
+        assert!(run_query("$.nada[3]").is_empty());
+
+        assert!(parse_path("tienda").unwrap_err().to_string().contains("posiciòn 0"));
+        assert!(parse_path("$.libros[1").unwrap_err().to_string().contains("falta el ']'"));
+        assert!(parse_path("$.libros[abc]").is_err());
+    }
+
+    #[test]
+    fn test_sort_keys_and_diff() {
+        let mut value = json!({"b": 1, "a": {"d": [{"z": 0, "y": 1}], "c": 2}});
+        sort_keys(&mut value);
+        assert_eq!(value.to_string(), r#"{"a":{"c":2,"d":[{"y":1,"z":0}]},"b":1}"#);
+
+        let old = json!({"a": 1, "b": [1, 2, 3], "c/d": true, "e": {"f": "x"}});
+        let new = json!({"a": 2, "b": [1, 5], "e": {"f": "x", "g": null}});
+        let mut changes = Vec::new();
+        diff(&old, &new, "", &mut changes);
+        assert_eq!(Value::Array(changes), json!([
+            {"op": "replace", "path": "/a", "old": 1, "value": 2},
+            {"op": "replace", "path": "/b/1", "old": 2, "value": 5},
+            {"op": "remove", "path": "/b/2", "old": 3},
+            {"op": "remove", "path": "/c~1d", "old": true},
+            {"op": "add", "path": "/e/g", "value": null}
+        ]));
+    }
+
+    #[test]
+    fn test_run_json_operations() {
+        let run = |params: &[(&str, &str)], text: &str| {
+            let params: HashMap<String, String> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            run_json(&InputSource::Inline(text.to_string()), &parse_json_params(&params)?).map(|message| message.to_string())
+        };
+
+        // Se conserva el orden original de las claves
+        assert_eq!(run(&[("op", "minify")], "{ \"z\" : 1,\n \"a\" : [ 1, 2 ] }").unwrap(), r#"{"z":1,"a":[1,2]}"#);
+        assert_eq!(run(&[("op", "pretty"), ("indent", "4")], "{\"a\":[1]}").unwrap(), "{\n    \"a\": [\n        1\n    ]\n}");
+        assert_eq!(run(&[("op", "validate")], "{\"a\": [1, {\"b\": 2}]}").unwrap(), "JSON vàlido: objeto con 1 claves, 5 valores, profundidad 3");
+        assert!(run(&[("op", "validate")], "{\"a\": 1,\n}").unwrap().starts_with("JSON invàlido: trailing comma (lìnea 2, columna 1)"));
+        assert!(run(&[("op", "minify")], "[1, 2").unwrap_err().message().contains("lìnea 1"));
+
+        // El resumen se traduce junto con el mensaje que lo contiene
+        let spec = parse_json_params(&HashMap::from([("op".to_string(), "validate".to_string())])).unwrap();
+        let report = run_json(&InputSource::Inline("[true]".to_string()), &spec).unwrap();
+        assert_eq!(report.render(Lang::En), "Valid JSON: array with 1 items, 2 values, depth 1");
+        assert_eq!(run(&[("op", "diff"), ("with_text", "[1, 3]")], "[1, 2]").unwrap(), r#"[{"op":"replace","path":"/1","old":2,"value":3}]"#);
+        assert_eq!(run(&[("op", "query"), ("path", "$[1]")], "[1, 2]").unwrap(), "[2]");
+        assert_eq!(run(&[("op", "query")], "[]").unwrap_err(), ApiError::missing("path"));
+        assert!(run(&[("op", "jq")], "[]").is_err());
+    }
+}
@@ -1,8 +1,28 @@
-use std::sync::atomic::AtomicUsize;
-
 pub mod task_queue;
 pub mod endpoints;
 pub mod responses;
 pub mod error_responses;
 pub mod handle_connection;
-static CONNECTION_COUNT: AtomicUsize = AtomicUsize::new(0);
\ No newline at end of file
+pub mod storage;
+pub mod file_transfer;
+pub mod grep;
+pub mod random_gen;
+pub mod datetime;
+pub mod text_ops;
+pub mod text_stats;
+pub mod codec;
+pub mod compression;
+pub mod benchmarks;
+pub mod mandelbrot;
+pub mod calc;
+pub mod csv_ops;
+pub mod json_ops;
+pub mod i18n;
+pub mod help;
+pub mod formats;
+pub mod router;
+pub mod routes;
+pub mod middleware;
+pub mod metrics;
+pub mod stats;
+pub mod sysinfo;
@@ -1,18 +1,14 @@
-mod handle_connection;
-mod endpoints;
-mod error_responses;
-mod responses;
-mod task_queue;
-
-use std::{net::TcpListener, sync::{atomic::{AtomicUsize, Ordering}, mpsc::{channel, Receiver, Sender}, Arc, Mutex}, thread, time::Instant};
-use handle_connection::handle_connection;
-use task_queue::{start_workers, Task, WorkerStatus};
-
-static CONNECTION_COUNT: AtomicUsize = AtomicUsize::new(0);
+use std::{net::TcpListener, process, sync::{mpsc::{channel, Receiver, Sender}, Arc, Mutex}, thread};
+use so_server_rust::handle_connection::{handle_connection, ServerState};
+use so_server_rust::middleware::Pipeline;
+use so_server_rust::routes::default_router;
+use so_server_rust::storage::set_versioning;
+use so_server_rust::task_queue::{start_workers, Task, WorkerStatus};
 
 fn main () {
 
-    let start_time = Instant::now();
+    // FILE_VERSIONING=1 guarda la versiòn anterior en cada sobrescritura o eliminaciòn
+    set_versioning(matches!(std::env::var("FILE_VERSIONING").as_deref(), Ok("1") | Ok("true")));
     let workers_states: Arc<Mutex<Vec<WorkerStatus>>> = Arc::new(Mutex::new(vec![]));
 
     let (tx, rx) : (Sender<Task>, Receiver<Task>) = channel();
@@ -21,17 +17,30 @@ fn main () {
     start_workers(shared_rx, workers_states.clone());
 
 
+    // Las rutas propias se agregan aquì, sobre las que trae el servidor
+    let router = Arc::new(default_router());
+    let state = Arc::new(ServerState::new(tx, workers_states));
+
+    // Capas alrededor del router, en el orden de MIDDLEWARE (request_id,access_log,timing,cors,auth,rate_limit por defecto)
+    let pipeline = match Pipeline::from_env() {
+        Ok(pipeline) => Arc::new(pipeline),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
     let listener = TcpListener::bind("127.0.0.1:7878").expect("Fallo al iniciar el server");
     println!("Servidor ejecutandose en http://127.0.0.1:7878");
 
     for stream in listener.incoming() {
-        CONNECTION_COUNT.fetch_add(1, Ordering::SeqCst);
         let stream = stream.expect("Error de conexciòn");
-        let tx_clone = tx.clone();
-        let worker_states = workers_states.clone();
+        let router = router.clone();
+        let pipeline = pipeline.clone();
+        let state = state.clone();
 
         thread::spawn(move || {
-            handle_connection(stream, tx_clone, start_time, worker_states.clone());
+            handle_connection(stream, &router, &pipeline, &state);
         });
     }
 }
\ No newline at end of file
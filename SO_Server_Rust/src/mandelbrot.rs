@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::responses::{Response, Status};
+use crate::storage::{replace_file, resolve_name};
+use crate::task_queue::{reply_channel, submit, Task, TaskType};
+
+// Render del conjunto de Mandelbrot (/mandelbrot). La imagen se divide en franjas de filas
+// que se encolan en el pool, asì con `tiles` > 1 se nota la aceleraciòn en los tiempos reportados
+
+pub const MAX_DIMENSION: usize = 4096;
+pub const MAX_ITERATIONS: u32 = 100_000;
+pub const MAX_TILES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "image/x-portable-pixmap",
+            ImageFormat::Png => "image/png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MandelbrotSpec {
+    pub width: usize,
+    pub height: usize,
+    pub iterations: u32,
+    // Ventana del plano complejo: [x_min, x_max] x [y_min, y_max]
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+    pub format: ImageFormat,
+    pub tiles: usize,
+    // Guarda la imagen en archivos/ en lugar de devolverla
+    pub save: Option<String>,
+}
+
+// Pìxeles RGB compartidos por todas las franjas de un render, junto con la suma de lo que tardò cada franja
+pub struct Canvas {
+    pub pixels: Mutex<Vec<u8>>,
+    pub compute_us: AtomicU64,
+}
+
+// Una franja de filas [start, end) que renderiza un worker
+#[derive(Clone)]
+pub struct Tile {
+    pub spec: MandelbrotSpec,
+    pub start: usize,
+    pub end: usize,
+    pub canvas: Arc<Canvas>,
+}
+
+// Sin los pìxeles, para no volcar toda la imagen al imprimir la tarea
+impl fmt::Debug for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tile {{ filas: {}..{}, {}x{} }}", self.start, self.end, self.spec.width, self.spec.height)
+    }
+}
+
+pub fn parse_mandelbrot_params(params: &HashMap<String, String>) -> Result<MandelbrotSpec, ApiError> {
+    let number = |key: &str, default: f64| -> Result<f64, ApiError> {
+        match params.get(key) {
+            Some(v) => v.parse::<f64>().ok().filter(|v| v.is_finite()).ok_or(ApiError::invalid(key, Message::key("number_expected").arg("field", key))),
+            None => Ok(default),
+        }
+    };
+    let integer = |key: &str, default: usize, max: usize| -> Result<usize, ApiError> {
+        match params.get(key) {
+            Some(v) => v.parse::<usize>().ok().filter(|v| (1..=max).contains(v))
+                .ok_or_else(|| ApiError::not_in_range(key, 1, max)),
+            None => Ok(default),
+        }
+    };
+
+    let width = integer("width", 800, MAX_DIMENSION)?;
+    let height = integer("height", 600, MAX_DIMENSION)?;
+    let iterations = integer("iterations", 256, MAX_ITERATIONS as usize)? as u32;
+    let tiles = integer("tiles", 1, MAX_TILES)?.min(height);
+    let (x_min, x_max) = (number("x_min", -2.5)?, number("x_max", 1.0)?);
+    let (y_min, y_max) = (number("y_min", -1.2)?, number("y_max", 1.2)?);
+    if x_min >= x_max || y_min >= y_max {
+        let field = if x_min >= x_max { "x_min" } else { "y_min" };
+        return Err(ApiError::invalid(field, Message::key("mandelbrot_invalid_window")));
+    }
+
+    let format = match params.get("format").map(|f| f.to_ascii_lowercase()).as_deref() {
+        None | Some("png") => ImageFormat::Png,
+        Some("ppm") => ImageFormat::Ppm,
+        Some(_) => return Err(ApiError::invalid_choice("format", "'png', 'ppm'")),
+    };
+
+    Ok(MandelbrotSpec { width, height, iterations, x_min, x_max, y_min, y_max, format, tiles, save: params.get("save").cloned() })
+}
+
+// Color de un punto a partir de la iteraciòn de escape suavizada; el interior del conjunto es negro
+fn color(spec: &MandelbrotSpec, cx: f64, cy: f64) -> [u8; 3] {
+    let (mut x, mut y) = (0.0f64, 0.0f64);
+    let mut i = 0;
+    while i < spec.iterations && x * x + y * y <= 256.0 {
+        let xt = x * x - y * y + cx;
+        y = 2.0 * x * y + cy;
+        x = xt;
+        i += 1;
+    }
+    if i == spec.iterations {
+        return [0, 0, 0];
+    }
+
+    let smooth = i as f64 + 1.0 - ((x * x + y * y).ln() / 2.0).ln() / std::f64::consts::LN_2;
+    let t = (smooth / spec.iterations as f64).clamp(0.0, 1.0);
+    [
+        (9.0 * (1.0 - t) * t * t * t * 255.0) as u8,
+        (15.0 * (1.0 - t) * (1.0 - t) * t * t * 255.0) as u8,
+        (8.5 * (1.0 - t) * (1.0 - t) * (1.0 - t) * t * 255.0) as u8,
+    ]
+}
+
+pub fn render_rows(spec: &MandelbrotSpec, start: usize, end: usize) -> Vec<u8> {
+    let mut rows = Vec::with_capacity((end - start) * spec.width * 3);
+    let dx = (spec.x_max - spec.x_min) / spec.width as f64;
+    let dy = (spec.y_max - spec.y_min) / spec.height as f64;
+    for py in start..end {
+        let cy = spec.y_max - (py as f64 + 0.5) * dy;
+        for px in 0..spec.width {
+            rows.extend_from_slice(&color(spec, spec.x_min + (px as f64 + 0.5) * dx, cy));
+        }
+    }
+    rows
+}
+
+// Lo ejecuta el worker: renderiza su franja y la copia al lienzo compartido
+pub fn render_tile(tile: &Tile) -> Message {
+    let start = Instant::now();
+    let rows = render_rows(&tile.spec, tile.start, tile.end);
+    let elapsed = start.elapsed();
+    tile.canvas.compute_us.fetch_add(elapsed.as_micros() as u64, Ordering::SeqCst);
+
+    let offset = tile.start * tile.spec.width * 3;
+    tile.canvas.pixels.lock().unwrap()[offset..offset + rows.len()].copy_from_slice(&rows);
+    Message::key("mandelbrot_tile").arg("start", tile.start).arg("end", tile.end).arg("ms", format!("{:.2}", elapsed.as_secs_f64() * 1000.0))
+}
+
+pub fn encode_image(format: ImageFormat, width: usize, height: usize, pixels: &[u8]) -> Result<Vec<u8>, Message> {
+    match format {
+        ImageFormat::Ppm => {
+            let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            image.extend_from_slice(pixels);
+            Ok(image)
+        }
+        ImageFormat::Png => {
+            let mut image = Vec::new();
+            let mut encoder = png::Encoder::new(&mut image, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header().and_then(|mut writer| writer.write_image_data(pixels))
+                .map_err(|e| Message::key("image_encode_failed").arg("error", e))?;
+            Ok(image)
+        }
+    }
+}
+
+// /mandelbrot?width=800&height=600&iterations=500&tiles=8&format=png[&save=nombre]
+pub fn handle_mandelbrot<W: Write>(stream: &mut W, sender: &Sender<Task>, params: &HashMap<String, String>) -> io::Result<()> {
+    let spec = match parse_mandelbrot_params(params) {
+        Ok(spec) => spec,
+        Err(error) => return Response::from(error).write_to(stream),
+    };
+    let save = match &spec.save {
+        Some(name) => {
+            let name = if name.contains('.') { name.clone() } else { format!("{}.{}", name, spec.format.extension()) };
+            match resolve_name(&name) {
+                Some(resolved) => Some(resolved),
+                None => return Response::from(ApiError::invalid_name("save")).write_to(stream),
+            }
+        }
+        None => None,
+    };
+
+    let canvas = Arc::new(Canvas { pixels: Mutex::new(vec![0; spec.width * spec.height * 3]), compute_us: AtomicU64::new(0) });
+    let start = Instant::now();
+
+    // Franjas de alto parecido; las primeras se llevan las filas sobrantes
+    let mut receivers = Vec::with_capacity(spec.tiles);
+    let (base, extra) = (spec.height / spec.tiles, spec.height % spec.tiles);
+    let mut row = 0;
+    for i in 0..spec.tiles {
+        let rows = base + usize::from(i < extra);
+        let tile = Tile { spec: spec.clone(), start: row, end: row + rows, canvas: canvas.clone() };
+        row += rows;
+
+        let (tx, rx) = reply_channel();
+        let task = Task { description: format!("Mandelbrot {:?}", tile), task_type: TaskType::Mandelbrot(tile), response_tx: tx, enqueued: Instant::now() };
+        if let Err(error) = submit(sender, task) {
+            return Response::from(error).write_to(stream);
+        }
+        receivers.push(rx);
+    }
+    if receivers.iter().any(|rx| rx.recv().is_err()) {
+        return Response::from(ApiError::internal(Message::key("task_reply_failed"))).write_to(stream);
+    }
+
+    let wall = start.elapsed().as_secs_f64() * 1000.0;
+    let compute = canvas.compute_us.load(Ordering::SeqCst) as f64 / 1000.0;
+    let speedup = if wall > 0.0 { compute / wall } else { 0.0 };
+
+    let image = {
+        let pixels = canvas.pixels.lock().unwrap();
+        encode_image(spec.format, spec.width, spec.height, &pixels)
+    };
+    let image = match image {
+        Ok(image) => image,
+        Err(msg) => return Response::from(ApiError::internal(msg)).write_to(stream),
+    };
+
+    match save {
+        Some((key, path)) => {
+            let response = match replace_file(&key, &path, &image) {
+                Ok(_) => Response::ok(&Message::key("mandelbrot_saved")
+                    .arg("width", spec.width)
+                    .arg("height", spec.height)
+                    .arg("path", path.display())
+                    .arg("bytes", image.len())
+                    .arg("tiles", spec.tiles)
+                    .arg("wall", format!("{:.2}", wall))
+                    .arg("compute", format!("{:.2}", compute))
+                    .arg("speedup", format!("{:.2}", speedup))
+                    .to_string()),
+                Err(_) => ApiError::internal(Message::key("file_write_failed")).into(),
+            };
+            response.write_to(stream)
+        }
+        None => Response::new(Status::Ok)
+            .header("X-Render-Tiles", spec.tiles)
+            .header("X-Render-Wall-Ms", format!("{:.2}", wall))
+            .header("X-Render-Tiles-Ms", format!("{:.2}", compute))
+            .header("X-Render-Speedup", format!("{:.2}", speedup))
+            .body(spec.format.content_type(), image)
+            .write_to(stream),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use crate::task_queue::{process_task, Task};
+
+    use super::{encode_image, handle_mandelbrot, parse_mandelbrot_params, render_rows, render_tile, Canvas, ImageFormat, Tile};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let spec = parse_mandelbrot_params(&params(&[("width", "64"), ("height", "4"), ("tiles", "10"), ("format", "ppm")])).unwrap();
+        assert_eq!((spec.width, spec.height, spec.tiles, spec.format), (64, 4, 4, ImageFormat::Ppm));
+        assert!(parse_mandelbrot_params(&params(&[("x_min", "1"), ("x_max", "0")])).is_err());
+        assert_eq!(parse_mandelbrot_params(&params(&[("width", "0")])).unwrap_err().field(), Some("width"));
+        assert!(parse_mandelbrot_params(&params(&[("format", "gif")])).is_err());
+    }
+
+    #[test]
+    fn test_tiles_match_single_render() {
+        let spec = parse_mandelbrot_params(&params(&[("width", "40"), ("height", "30"), ("iterations", "50")])).unwrap();
+        let whole = render_rows(&spec, 0, 30);
+        // El centro de la vista por defecto està dentro del conjunto
+        assert_eq!(&whole[(15 * 40 + 20) * 3..(15 * 40 + 21) * 3], &[0, 0, 0]);
+
+        let canvas = Arc::new(Canvas { pixels: Mutex::new(vec![0; whole.len()]), compute_us: AtomicU64::new(0) });
+        for (start, end) in [(0, 7), (7, 19), (19, 30)] {
+            render_tile(&Tile { spec: spec.clone(), start, end, canvas: canvas.clone() });
+        }
+        assert_eq!(*canvas.pixels.lock().unwrap(), whole);
+    }
+
+    #[test]
+    fn test_encode_and_handle() {
+        let ppm = encode_image(ImageFormat::Ppm, 2, 1, &[255, 0, 0, 0, 255, 0]).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\xff\x00\x00\x00\xff\x00");
+        let png = encode_image(ImageFormat::Png, 2, 1, &[255, 0, 0, 0, 255, 0]).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let (task_tx, task_rx) = channel::<Task>();
+        thread::spawn(move || {
+            while let Ok(task) = task_rx.recv() {
+                process_task(task);
+            }
+        });
+
+        let mut output = Vec::new();
+        handle_mandelbrot(&mut output, &task_tx, &params(&[("width", "16"), ("height", "8"), ("tiles", "3"), ("format", "ppm")])).unwrap();
+        let head_end = output.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&output[..head_end]);
+        assert!(head.starts_with("HTTP/1.0 200 OK") && head.contains("X-Render-Tiles: 3"));
+        assert!(output[head_end..].starts_with(b"P6\n16 8\n255\n"));
+        assert_eq!(output.len() - head_end, "P6\n16 8\n255\n".len() + 16 * 8 * 3);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::sysinfo::ProcessInfo;
+use crate::task_queue::WorkerStatus;
+
+// Mètricas del servidor en el formato de texto de Prometheus (/metrics).
+// Las solicitudes y las tareas se anotan en un registro global, igual que los trabajos cancelables:
+// los workers no tienen acceso al estado de las conexiones
+
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// Lìmites de los histogramas, en segundos
+const BUCKETS: [f64; 14] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    // Observaciones <= cada lìmite (sin acumular; se acumulan al exportar)
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, seconds: f64) {
+        if let Some(i) = BUCKETS.iter().position(|le| seconds <= *le) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (le, n) in BUCKETS.iter().zip(self.buckets) {
+            cumulative += n;
+            let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels, le, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels, self.count);
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+pub struct Metrics {
+    // (ruta, mètodo, estado) -> solicitudes
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    request_seconds: Mutex<BTreeMap<String, Histogram>>,
+    task_seconds: Mutex<BTreeMap<&'static str, Histogram>>,
+    // Tareas encoladas que ningùn worker tomò todavìa
+    queue_depth: AtomicUsize,
+}
+
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    pub const fn new() -> Metrics {
+        Metrics {
+            requests: Mutex::new(BTreeMap::new()),
+            request_seconds: Mutex::new(BTreeMap::new()),
+            task_seconds: Mutex::new(BTreeMap::new()),
+            queue_depth: AtomicUsize::new(0),
+        }
+    }
+
+    // `route` es el patròn de la ruta (`/files/{name}`), no la ruta pedida, para no crear una serie por cada url
+    pub fn record_request(&self, route: &str, method: &str, status: u16, elapsed: Duration) {
+        let key = (route.to_string(), method.to_string(), status);
+        *self.requests.lock().unwrap_or_else(|e| e.into_inner()).entry(key).or_insert(0) += 1;
+        self.request_seconds.lock().unwrap_or_else(|e| e.into_inner()).entry(route.to_string()).or_default().observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_task(&self, task: &'static str, elapsed: Duration) {
+        self.task_seconds.lock().unwrap_or_else(|e| e.into_inner()).entry(task).or_default().observe(elapsed.as_secs_f64());
+    }
+
+    pub fn enqueued(&self) {
+        self.queue_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Sin bajar de cero: las pruebas encolan directo en el canal sin pasar por `submit`
+    pub fn dequeued(&self) {
+        let _ = self.queue_depth.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| depth.checked_sub(1));
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.load(Ordering::Relaxed)
+    }
+
+    pub fn requests(&self, route: &str, status: u16) -> u64 {
+        let requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.iter().filter(|((r, _, s), _)| r == route && *s == status).map(|(_, n)| n).sum()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Lo que /metrics necesita ademàs del registro global
+pub struct Snapshot<'a> {
+    pub workers: &'a [WorkerStatus],
+    pub active_connections: usize,
+    pub total_connections: usize,
+    pub uptime: Duration,
+}
+
+pub fn render(metrics: &Metrics, snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+
+    header(&mut out, "http_requests_total", "counter", "Solicitudes atendidas por ruta, mètodo y estado");
+    for ((route, method, status), count) in metrics.requests.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        let _ = writeln!(out, "http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}", escape(route), escape(method), status, count);
+    }
+    header(&mut out, "http_request_duration_seconds", "histogram", "Duraciòn de las solicitudes por ruta");
+    for (route, histogram) in metrics.request_seconds.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        histogram.write(&mut out, "http_request_duration_seconds", &format!("route=\"{}\",", escape(route)));
+    }
+
+    header(&mut out, "task_queue_depth", "gauge", "Tareas en la cola esperando un worker");
+    let _ = writeln!(out, "task_queue_depth {}", metrics.queue_depth());
+    header(&mut out, "task_duration_seconds", "histogram", "Duraciòn de las tareas en los workers por tipo");
+    for (task, histogram) in metrics.task_seconds.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        histogram.write(&mut out, "task_duration_seconds", &format!("task=\"{}\",", task));
+    }
+
+    header(&mut out, "worker_busy", "gauge", "1 si el worker està procesando una tarea");
+    for worker in snapshot.workers {
+        let _ = writeln!(out, "worker_busy{{worker=\"{}\"}} {}", worker.id, u8::from(worker.busy));
+    }
+    header(&mut out, "worker_busy_seconds_total", "counter", "Tiempo que cada worker pasò procesando tareas");
+    for worker in snapshot.workers {
+        let _ = writeln!(out, "worker_busy_seconds_total{{worker=\"{}\"}} {}", worker.id, worker.busy_time.as_secs_f64());
+    }
+    header(&mut out, "worker_tasks_completed_total", "counter", "Tareas terminadas por cada worker");
+    for worker in snapshot.workers {
+        let _ = writeln!(out, "worker_tasks_completed_total{{worker=\"{}\"}} {}", worker.id, worker.completed);
+    }
+
+    header(&mut out, "http_connections_active", "gauge", "Conexiones abiertas");
+    let _ = writeln!(out, "http_connections_active {}", snapshot.active_connections);
+    header(&mut out, "http_connections_total", "counter", "Conexiones aceptadas desde el arranque");
+    let _ = writeln!(out, "http_connections_total {}", snapshot.total_connections);
+
+    header(&mut out, "process_uptime_seconds", "gauge", "Segundos desde el arranque del servidor");
+    let _ = writeln!(out, "process_uptime_seconds {}", snapshot.uptime.as_secs_f64());
+    if let Some(process) = ProcessInfo::read() {
+        if let Some(start) = process.start_time {
+            header(&mut out, "process_start_time_seconds", "gauge", "Arranque del proceso en segundos Unix");
+            let _ = writeln!(out, "process_start_time_seconds {}", start);
+        }
+        header(&mut out, "process_cpu_seconds_total", "counter", "Tiempo de CPU del proceso (usuario y sistema)");
+        let _ = writeln!(out, "process_cpu_seconds_total {}", (process.cpu_user + process.cpu_system).as_secs_f64());
+        header(&mut out, "process_resident_memory_bytes", "gauge", "Memoria residente (VmRSS)");
+        let _ = writeln!(out, "process_resident_memory_bytes {}", process.rss_bytes);
+        header(&mut out, "process_virtual_memory_bytes", "gauge", "Memoria virtual (VmSize)");
+        let _ = writeln!(out, "process_virtual_memory_bytes {}", process.virtual_bytes);
+        header(&mut out, "process_open_fds", "gauge", "Descriptores de archivo abiertos");
+        let _ = writeln!(out, "process_open_fds {}", process.open_fds);
+        header(&mut out, "process_threads", "gauge", "Hilos del proceso");
+        let _ = writeln!(out, "process_threads {}", process.threads);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::task_queue::WorkerStatus;
+
+    use super::{render, Histogram, Metrics, Snapshot};
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::default();
+        histogram.observe(0.003);
+        histogram.observe(0.2);
+        histogram.observe(120.0);
+        let mut out = String::new();
+        histogram.write(&mut out, "x", "route=\"/a\",");
+        assert!(out.contains("x_bucket{route=\"/a\",le=\"0.001\"} 0\n"));
+        assert!(out.contains("x_bucket{route=\"/a\",le=\"0.005\"} 1\n"));
+        assert!(out.contains("x_bucket{route=\"/a\",le=\"60\"} 2\n"));
+        assert!(out.contains("x_bucket{route=\"/a\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_count{route=\"/a\"} 3\n"));
+    }
+
+    #[test]
+    fn test_render_exposition() {
+        let metrics = Metrics::new();
+        metrics.record_request("/files/{name}", "GET", 404, Duration::from_millis(2));
+        metrics.record_request("/files/{name}", "GET", 404, Duration::from_millis(3));
+        metrics.record_task("reverse", Duration::from_millis(1));
+        metrics.enqueued();
+        metrics.enqueued();
+        metrics.dequeued();
+        metrics.dequeued();
+        metrics.dequeued();
+
+        let workers = [WorkerStatus { id: 0, busy: true, description: "x".into(), busy_time: Duration::from_millis(1500), completed: 7 }];
+        let text = render(&metrics, &Snapshot { workers: &workers, active_connections: 2, total_connections: 9, uptime: Duration::from_secs(5) });
+        assert!(text.contains("# TYPE http_requests_total counter\n"));
+        assert!(text.contains("http_requests_total{route=\"/files/{name}\",method=\"GET\",status=\"404\"} 2\n"));
+        assert!(text.contains("http_request_duration_seconds_count{route=\"/files/{name}\"} 2\n"));
+        assert!(text.contains("task_duration_seconds_bucket{task=\"reverse\",le=\"0.001\"} 1\n"));
+        assert!(text.contains("task_queue_depth 0\n"));
+        assert!(text.contains("worker_busy_seconds_total{worker=\"0\"} 1.5\n"));
+        assert!(text.contains("worker_tasks_completed_total{worker=\"0\"} 7\n"));
+        assert!(text.contains("http_connections_active 2\n"));
+        assert_eq!(metrics.requests("/files/{name}", 404), 2);
+        assert!(text.contains("\nprocess_cpu_seconds_total "));
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::IpAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error_responses::ApiError;
+use crate::handle_connection::HttpRequest;
+use crate::i18n::Message;
+use crate::responses::{Response, Status};
+use crate::router::Connection;
+
+// Cadena de middlewares alrededor del router. Cada capa puede revisar o cambiar la solicitud antes de la ruta
+// (y cortar la cadena con su propia respuesta) y revisar o cambiar la respuesta despuès, en orden inverso:
+//   request_id -> access_log -> timing -> ... -> router -> ... -> timing -> access_log -> request_id
+// El orden es el de registro (o el de la variable MIDDLEWARE en `from_env`)
+
+// Lo que las capas saben de la solicitud en curso
+pub struct Exchange {
+    // Lo asigna RequestId; vacìo si esa capa no està
+    pub id: String,
+    pub peer: Option<IpAddr>,
+    pub started: Instant,
+    // Encabezados que se agregan a la respuesta, tambièn a las que la ruta escribe directo al socket
+    pub headers: Vec<(String, String)>,
+}
+
+impl Exchange {
+    pub fn new(peer: Option<IpAddr>) -> Exchange {
+        Exchange { id: String::new(), peer, started: Instant::now(), headers: Vec::new() }
+    }
+
+    pub fn add_header(&mut self, name: &str, value: impl ToString) {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+}
+
+// Resultado de la solicitud tal como lo ven las capas al volver
+pub enum Outcome {
+    Response(Response),
+    // La ruta escribiò su propia respuesta (archivos, imàgenes, streaming): solo se conoce el estado y los bytes
+    Written { status: Option<u16>, bytes: u64 },
+}
+
+impl Outcome {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Outcome::Response(response) => Some(response.status.code()),
+            Outcome::Written { status, .. } => *status,
+        }
+    }
+
+    // La respuesta que todavìa falta escribir, si la ruta no la escribiò ya
+    pub fn into_response(self) -> Option<Response> {
+        match self {
+            Outcome::Response(response) => Some(response),
+            Outcome::Written { .. } => None,
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        match self {
+            Outcome::Response(response) => response.body.len() as u64,
+            Outcome::Written { bytes, .. } => *bytes,
+        }
+    }
+}
+
+pub trait Middleware: Send + Sync {
+    // Antes de la ruta. Una respuesta corta la cadena: ni las capas siguientes ni la ruta se ejecutan
+    fn before(&self, _request: &mut HttpRequest, _exchange: &mut Exchange) -> Option<Response> {
+        None
+    }
+
+    // Despuès de la ruta o del corte. Solo corre en las capas cuyo `before` corriò
+    fn after(&self, _request: &HttpRequest, _exchange: &Exchange, _outcome: &mut Outcome) {}
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    layers: Vec<Box<dyn Middleware>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    pub fn with(mut self, layer: impl Middleware + 'static) -> Pipeline {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    // Capas por nombre, en el orden indicado. `var` da la configuraciòn de cada una (API_TOKENS, RATE_LIMIT,
+    // CORS_ORIGINS); auth, rate_limit y cors se omiten si no estàn configuradas
+    pub fn from_config(order: &str, var: impl Fn(&str) -> Option<String>) -> Result<Pipeline, String> {
+        let mut pipeline = Pipeline::new();
+        for name in order.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            pipeline = match name {
+                "request_id" => pipeline.with(RequestId::new()),
+                "access_log" => pipeline.with(AccessLog),
+                "timing" => pipeline.with(Timing),
+                "auth" => match var("API_TOKENS") {
+                    Some(tokens) => pipeline.with(Auth::new(tokens.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect())),
+                    None => pipeline,
+                },
+                "rate_limit" => match var("RATE_LIMIT") {
+                    Some(limit) => pipeline.with(RateLimit::parse(&limit)?),
+                    None => pipeline,
+                },
+                "cors" => match var("CORS_ORIGINS") {
+                    Some(origins) => pipeline.with(Cors::new(origins.split(',').map(str::trim).map(String::from).collect())),
+                    None => pipeline,
+                },
+                other => return Err(format!("Middleware desconocido: '{}'", other)),
+            };
+        }
+        Ok(pipeline)
+    }
+
+    pub fn from_env() -> Result<Pipeline, String> {
+        let order = std::env::var("MIDDLEWARE").unwrap_or_else(|_| DEFAULT_ORDER.to_string());
+        Pipeline::from_config(&order, |name| std::env::var(name).ok())
+    }
+
+    // Corre la cadena alrededor de `inner` (el router). Devuelve la respuesta a escribir o, si la ruta ya la escribiò, su estado y tamaño
+    pub fn run<F>(&self, request: &mut HttpRequest, exchange: &mut Exchange, stream: &mut dyn Connection, inner: F) -> Outcome
+    where
+        F: FnOnce(&HttpRequest, &mut dyn Connection) -> Option<Response>,
+    {
+        let mut ran = 0;
+        let mut response = None;
+        for layer in &self.layers {
+            ran += 1;
+            if let Some(short) = layer.before(request, exchange) {
+                response = Some(short);
+                break;
+            }
+        }
+
+        let mut outcome = match response {
+            Some(response) => Outcome::Response(response),
+            None => {
+                let mut tap = Tap::new(stream, &exchange.headers);
+                // Una ruta que entra en pànico responde 500 en vez de cerrar la conexiòn sin respuesta
+                let result = panic::catch_unwind(AssertUnwindSafe(|| inner(request, &mut tap)));
+                match result {
+                    Ok(Some(response)) => Outcome::Response(response),
+                    Err(_) if tap.status.is_none() => Outcome::Response(ApiError::internal(Message::key("handler_panicked")).into()),
+                    Ok(None) | Err(_) => Outcome::Written { status: tap.status, bytes: tap.bytes },
+                }
+            }
+        };
+        if let Outcome::Response(response) = &mut outcome {
+            for (name, value) in &exchange.headers {
+                if response.get_header(name).is_none() {
+                    response.set_header(name, value);
+                }
+            }
+        }
+
+        for layer in self.layers[..ran].iter().rev() {
+            layer.after(request, exchange, &mut outcome);
+        }
+        outcome
+    }
+}
+
+// rate_limit va antes de auth: las solicitudes sin token tambièn gastan fichas, asì no se pueden probar tokens sin lìmite
+pub const DEFAULT_ORDER: &str = "request_id,access_log,timing,cors,rate_limit,auth";
+
+// Envuelve el socket para las rutas que escriben su propia respuesta: agrega los encabezados de las capas,
+// y anota el estado y los bytes enviados para el registro
+struct Tap<'a> {
+    inner: &'a mut dyn Connection,
+    extra: &'a [(String, String)],
+    head: Vec<u8>,
+    in_body: bool,
+    status: Option<u16>,
+    bytes: u64,
+}
+
+impl<'a> Tap<'a> {
+    fn new(inner: &'a mut dyn Connection, extra: &'a [(String, String)]) -> Tap<'a> {
+        Tap { inner, extra, head: Vec::new(), in_body: false, status: None, bytes: 0 }
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.bytes += bytes.len() as u64;
+        self.inner.write_all(bytes)
+    }
+}
+
+impl Read for Tap<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for Tap<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.in_body {
+            self.send(buf)?;
+            return Ok(buf.len());
+        }
+        self.head.extend_from_slice(buf);
+        let end = match self.head.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => end,
+            None => return Ok(buf.len()),
+        };
+
+        self.in_body = true;
+        let head = String::from_utf8_lossy(&self.head[..end]).into_owned();
+        let rest = self.head.split_off(end + 4);
+        self.status = head.split(' ').nth(1).and_then(|code| code.parse().ok());
+
+        let mut out = head.clone();
+        for (name, value) in self.extra {
+            let present = head.lines().skip(1).any(|line| line.split(':').next().is_some_and(|n| n.trim().eq_ignore_ascii_case(name)));
+            if !present {
+                out.push_str(&format!("\r\n{}: {}", name, value));
+            }
+        }
+        out.push_str("\r\n\r\n");
+        self.inner.write_all(out.as_bytes())?;
+        self.send(&rest)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Id por solicitud en `X-Request-Id`. Se respeta el que manda el cliente si es razonable
+pub struct RequestId {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl RequestId {
+    pub fn new() -> RequestId {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        RequestId { prefix: format!("{:x}", seconds), next: AtomicU64::new(1) }
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId::new()
+    }
+}
+
+impl Middleware for RequestId {
+    fn before(&self, request: &mut HttpRequest, exchange: &mut Exchange) -> Option<Response> {
+        let valid = |id: &&String| !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        exchange.id = match request.headers.get("x-request-id").filter(valid) {
+            Some(id) => id.clone(),
+            None => format!("{}-{}", self.prefix, self.next.fetch_add(1, Ordering::Relaxed)),
+        };
+        let id = exchange.id.clone();
+        exchange.add_header("X-Request-Id", id);
+        None
+    }
+}
+
+// Una lìnea por solicitud: cliente, id, mètodo y ruta, estado, bytes y duraciòn
+pub struct AccessLog;
+
+impl Middleware for AccessLog {
+    fn after(&self, request: &HttpRequest, exchange: &Exchange, outcome: &mut Outcome) {
+        let peer = exchange.peer.map_or("-".to_string(), |p| p.to_string());
+        let id = if exchange.id.is_empty() { "-" } else { &exchange.id };
+        let status = outcome.status().map_or("-".to_string(), |s| s.to_string());
+        let millis = exchange.started.elapsed().as_secs_f64() * 1000.0;
+        println!("{} {} \"{} {}\" {} {} {:.1}ms", peer, id, request.method, request.path, status, outcome.bytes(), millis);
+    }
+}
+
+// Tiempo de atenciòn en `X-Response-Time` (las respuestas escritas directo ya enviaron sus encabezados)
+pub struct Timing;
+
+impl Middleware for Timing {
+    fn after(&self, _request: &HttpRequest, exchange: &Exchange, outcome: &mut Outcome) {
+        if let Outcome::Response(response) = outcome {
+            let millis = exchange.started.elapsed().as_secs_f64() * 1000.0;
+            response.set_header("X-Response-Time", format!("{:.3}ms", millis));
+        }
+    }
+}
+
+// Token en `Authorization: Bearer <token>`. OPTIONS y las rutas pùblicas no lo piden
+pub struct Auth {
+    tokens: Vec<String>,
+    public: Vec<String>,
+}
+
+impl Auth {
+    pub fn new(tokens: Vec<String>) -> Auth {
+        Auth { tokens, public: vec!["/help".into(), "/openapi.json".into()] }
+    }
+
+    pub fn public(mut self, paths: &[&str]) -> Auth {
+        self.public = paths.iter().map(|p| p.to_string()).collect();
+        self
+    }
+}
+
+// Compara sin cortar en el primer byte distinto, para no filtrar el token por tiempos
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl Middleware for Auth {
+    fn before(&self, request: &mut HttpRequest, _exchange: &mut Exchange) -> Option<Response> {
+        let path = request.path.split('?').next().unwrap_or("");
+        if request.method == "OPTIONS" || self.public.iter().any(|p| p == path) {
+            return None;
+        }
+        let token = request.headers.get("authorization").and_then(|h| h.strip_prefix("Bearer ")).map(str::trim);
+        match token {
+            Some(token) if self.tokens.iter().any(|t| same_token(t, token)) => None,
+            _ => Some(Response::from(ApiError::Unauthorized { message: Message::key("unauthorized") }).header("WWW-Authenticate", "Bearer")),
+        }
+    }
+}
+
+// Cubeta de fichas por IP: `capacity` solicitudes seguidas y `per_second` fichas nuevas por segundo
+pub struct RateLimit {
+    capacity: f64,
+    per_second: f64,
+    buckets: Mutex<HashMap<IpAddr, (f64, Instant)>>,
+}
+
+// Sobre este tamaño se olvidan las IPs con la cubeta llena y, si no alcanza, las que llevan màs tiempo sin solicitudes
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+impl RateLimit {
+    pub fn new(capacity: u32, per_second: f64) -> RateLimit {
+        RateLimit { capacity: capacity.max(1) as f64, per_second, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    // "<solicitudes>/<segundos>", por ejemplo 100/60
+    pub fn parse(spec: &str) -> Result<RateLimit, String> {
+        let error = || format!("RATE_LIMIT invàlido: '{}' (se espera <solicitudes>/<segundos>)", spec);
+        let (count, seconds) = spec.split_once('/').ok_or_else(error)?;
+        let count: u32 = count.trim().parse().map_err(|_| error())?;
+        let seconds: f64 = seconds.trim().parse().map_err(|_| error())?;
+        if count == 0 || !seconds.is_finite() || seconds <= 0.0 {
+            return Err(error());
+        }
+        Ok(RateLimit::new(count, count as f64 / seconds))
+    }
+}
+
+impl Middleware for RateLimit {
+    fn before(&self, _request: &mut HttpRequest, exchange: &mut Exchange) -> Option<Response> {
+        let peer = exchange.peer?;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, (tokens, last)| *tokens + now.duration_since(*last).as_secs_f64() * self.per_second < self.capacity);
+        }
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            // Se libera una dècima parte de una vez para no repetir el recorrido en cada solicitud
+            let mut lasts: Vec<Instant> = buckets.values().map(|(_, last)| *last).collect();
+            let cutoff = *lasts.select_nth_unstable(MAX_TRACKED_CLIENTS / 10).1;
+            buckets.retain(|_, (_, last)| *last > cutoff);
+        }
+
+        let (tokens, last) = buckets.entry(peer).or_insert((self.capacity, now));
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.per_second).min(self.capacity);
+        *last = now;
+        exchange.add_header("X-RateLimit-Limit", self.capacity);
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            exchange.add_header("X-RateLimit-Remaining", tokens.floor());
+            return None;
+        }
+
+        let wait = ((1.0 - *tokens) / self.per_second).ceil().max(1.0) as u64;
+        exchange.add_header("X-RateLimit-Remaining", 0);
+        let error = ApiError::RateLimited { message: Message::key("rate_limited").arg("seconds", wait) };
+        Some(Response::from(error).header("Retry-After", wait))
+    }
+}
+
+// CORS para los orìgenes indicados ("*" = cualquiera). Responde las consultas previas (preflight) sin llegar al router
+pub struct Cors {
+    origins: Vec<String>,
+}
+
+const CORS_METHODS: &str = "GET, HEAD, POST, PUT, DELETE, OPTIONS";
+const CORS_EXPOSED: &str = "X-Request-Id, X-Response-Time, Retry-After, Content-Language";
+
+impl Cors {
+    pub fn new(origins: Vec<String>) -> Cors {
+        Cors { origins }
+    }
+}
+
+impl Middleware for Cors {
+    fn before(&self, request: &mut HttpRequest, exchange: &mut Exchange) -> Option<Response> {
+        let origin = request.headers.get("origin")?.clone();
+        let allowed = if self.origins.iter().any(|o| o == "*") {
+            "*".to_string()
+        } else if self.origins.contains(&origin) {
+            exchange.add_header("Vary", "Origin");
+            origin
+        } else {
+            return None;
+        };
+        exchange.add_header("Access-Control-Allow-Origin", allowed);
+        exchange.add_header("Access-Control-Expose-Headers", CORS_EXPOSED);
+
+        if request.method != "OPTIONS" || !request.headers.contains_key("access-control-request-method") {
+            return None;
+        }
+        let headers = request.headers.get("access-control-request-headers").map_or("Authorization, Content-Type", String::as_str);
+        Some(Response::new(Status::NoContent)
+            .header("Access-Control-Allow-Methods", CORS_METHODS)
+            .header("Access-Control-Allow-Headers", headers)
+            .header("Access-Control-Max-Age", 600))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use crate::handle_connection::HttpRequest;
+    use crate::responses::{write_head, Response, Status};
+
+    use super::{Auth, Cors, Exchange, Middleware, Outcome, Pipeline, RateLimit, RequestId, Timing, MAX_TRACKED_CLIENTS};
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        let headers: HashMap<String, String> = headers.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+        HttpRequest { method: method.into(), path: path.into(), headers, body_start: Vec::new() }
+    }
+
+    // Corre la cadena con una ruta que responde 200 "ok"
+    fn run(pipeline: &Pipeline, request: &mut HttpRequest) -> Response {
+        let mut exchange = Exchange::new(Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        pipeline.run(request, &mut exchange, &mut Cursor::new(Vec::new()), |_, _| Some(Response::ok("ok"))).into_response().unwrap()
+    }
+
+    struct Record(&'static str, Arc<Mutex<Vec<String>>>, bool);
+
+    impl Middleware for Record {
+        fn before(&self, _request: &mut HttpRequest, _exchange: &mut Exchange) -> Option<Response> {
+            self.1.lock().unwrap().push(format!("antes {}", self.0));
+            self.2.then(|| Response::new(Status::Conflict))
+        }
+
+        fn after(&self, _request: &HttpRequest, _exchange: &Exchange, _outcome: &mut Outcome) {
+            self.1.lock().unwrap().push(format!("despuès {}", self.0));
+        }
+    }
+
+    #[test]
+    fn test_order_and_short_circuit() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pipeline = Pipeline::new().with(Record("a", log.clone(), false)).with(Record("b", log.clone(), true)).with(Record("c", log.clone(), false));
+        let mut exchange = Exchange::new(None);
+        let response = pipeline.run(&mut request("GET", "/", &[]), &mut exchange, &mut Cursor::new(Vec::new()), |_, _| panic!("no debìa llegar a la ruta"));
+        assert_eq!(response.into_response().unwrap().status, Status::Conflict);
+        assert_eq!(*log.lock().unwrap(), ["antes a", "antes b", "despuès b", "despuès a"]);
+
+        // Una ruta que entra en pànico responde 500
+        let response = Pipeline::new().run(&mut request("GET", "/", &[]), &mut Exchange::new(None), &mut Cursor::new(Vec::new()), |_, _| panic!("falla"));
+        assert_eq!(response.status(), Some(500));
+    }
+
+    #[test]
+    fn test_headers_reach_written_responses() {
+        let pipeline = Pipeline::new().with(RequestId::new()).with(Timing);
+        let mut out = Cursor::new(Vec::new());
+        let mut exchange = Exchange::new(None);
+        let mut req = request("GET", "/files/a.txt", &[("x-request-id", "abc-1")]);
+        let response = pipeline.run(&mut req, &mut exchange, &mut out, |_, stream| {
+            write_head(stream, Status::PartialContent, &[("Content-Type", "text/plain".into())]).unwrap();
+            stream.write_all(b"hola").unwrap();
+            None
+        });
+        assert_eq!((response.status(), response.bytes()), (Some(206), 4));
+        assert_eq!(String::from_utf8(out.into_inner()).unwrap(), "HTTP/1.0 206 Partial Content\r\nContent-Type: text/plain\r\nX-Request-Id: abc-1\r\n\r\nhola");
+
+        let response = run(&pipeline, &mut request("GET", "/", &[("x-request-id", "no vàlido")]));
+        assert_ne!(response.get_header("X-Request-Id"), Some("no vàlido"));
+        assert!(response.get_header("X-Response-Time").is_some_and(|t| t.ends_with("ms")));
+    }
+
+    #[test]
+    fn test_auth_and_rate_limit() {
+        let pipeline = Pipeline::new().with(RateLimit::new(3, 0.001)).with(Auth::new(vec!["secreto".into()]));
+
+        let denied = run(&pipeline, &mut request("GET", "/reverse?text=a", &[]));
+        assert_eq!((denied.status, denied.get_header("WWW-Authenticate")), (Status::Unauthorized, Some("Bearer")));
+        assert_eq!(denied.body_json().unwrap()["code"], "unauthorized");
+        assert_eq!(run(&pipeline, &mut request("GET", "/help", &[])).status, Status::Ok);
+
+        let auth = [("authorization", "Bearer secreto")];
+        assert_eq!(run(&pipeline, &mut request("GET", "/reverse", &auth)).status, Status::Ok);
+        let limited = run(&pipeline, &mut request("GET", "/reverse", &auth));
+        assert_eq!(limited.status, Status::TooManyRequests);
+        assert_eq!(limited.body_json().unwrap()["code"], "rate_limited");
+        assert!(limited.get_header("Retry-After").is_some());
+        // Los intentos sin token tambièn cuentan
+        assert_eq!(run(&pipeline, &mut request("GET", "/reverse", &[])).status, Status::TooManyRequests);
+
+        assert!(RateLimit::parse("100/60").is_ok());
+        assert!(RateLimit::parse("100").is_err() && RateLimit::parse("0/1").is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_forgets_oldest_clients() {
+        let limit = RateLimit::new(5, 0.001);
+        {
+            let mut buckets = limit.buckets.lock().unwrap();
+            let start = Instant::now();
+            for i in 0..MAX_TRACKED_CLIENTS as u32 {
+                // Cubetas gastadas, asì ninguna se olvida por estar llena
+                buckets.insert(IpAddr::V4(Ipv4Addr::from(i)), (0.0, start + Duration::from_nanos(i as u64)));
+            }
+        }
+        let mut exchange = Exchange::new(Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(limit.before(&mut request("GET", "/", &[]), &mut exchange).is_none());
+
+        let buckets = limit.buckets.lock().unwrap();
+        assert!(buckets.len() < MAX_TRACKED_CLIENTS);
+        assert!(!buckets.contains_key(&IpAddr::V4(Ipv4Addr::from(0))));
+        assert!(buckets.contains_key(&IpAddr::V4(Ipv4Addr::from(MAX_TRACKED_CLIENTS as u32 - 1))));
+    }
+
+    #[test]
+    fn test_cors() {
+        let pipeline = Pipeline::new().with(Cors::new(vec!["http://app.local".into()]));
+
+        let preflight = run(&pipeline, &mut request("OPTIONS", "/reverse", &[("origin", "http://app.local"), ("access-control-request-method", "GET")]));
+        assert_eq!(preflight.status, Status::NoContent);
+        assert_eq!(preflight.get_header("Access-Control-Allow-Origin"), Some("http://app.local"));
+        assert!(preflight.get_header("Access-Control-Allow-Methods").is_some());
+
+        let other = run(&pipeline, &mut request("GET", "/reverse", &[("origin", "http://otro.local")]));
+        assert_eq!((other.status, other.get_header("Access-Control-Allow-Origin")), (Status::Ok, None));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let var = |name: &str| (name == "RATE_LIMIT").then(|| "10/1".to_string());
+        assert_eq!(Pipeline::from_config(super::DEFAULT_ORDER, var).unwrap().len(), 4);
+        assert_eq!(Pipeline::from_config("timing, request_id", |_| None).unwrap().len(), 2);
+        assert!(Pipeline::from_config("request_id,gzip", |_| None).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use rand::distr::Uniform;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution as _, Exp, Normal, Poisson};
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Generaciòn de valores aleatorios para /random: semillas, distribuciones,
+// muestreo sin reemplazo, barajado, cadenas y UUIDs
+
+// A partir de esta cantidad el resultado se envìa por partes en lugar de un solo String
+pub const STREAM_THRESHOLD: usize = 10_000;
+pub const MAX_COUNT: usize = 10_000_000;
+pub const MAX_STRING_LENGTH: usize = 4096;
+const CHUNK_ITEMS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distribution {
+    UniformInt { min: i32, max: i32 },
+    UniformFloat { min: f64, max: f64 },
+    Normal { mean: f64, std_dev: f64 },
+    Exponential { lambda: f64 },
+    Poisson { lambda: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RandomKind {
+    Numbers { count: usize, dist: Distribution },
+    // Enteros distintos dentro de [min, max]
+    Sample { count: usize, min: i32, max: i32 },
+    Shuffle(Vec<String>),
+    Strings { count: usize, length: usize, charset: String },
+    Uuids { count: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomSpec {
+    pub kind: RandomKind,
+    pub seed: Option<u64>,
+}
+
+impl RandomSpec {
+    pub fn count(&self) -> usize {
+        match &self.kind {
+            RandomKind::Numbers { count, .. } | RandomKind::Sample { count, .. } | RandomKind::Strings { count, .. } | RandomKind::Uuids { count } => *count,
+            RandomKind::Shuffle(items) => items.len(),
+        }
+    }
+
+    pub fn streams(&self) -> bool {
+        self.count() > STREAM_THRESHOLD
+    }
+}
+
+// Construye la especificaciòn desde los paràmetros de la query, validando todo antes de encolar
+pub fn parse_random_params(params: &HashMap<String, String>) -> Result<RandomSpec, ApiError> {
+    let seed = match params.get("seed") {
+        Some(s) => Some(s.parse::<u64>().map_err(|_| ApiError::not_positive_integer("seed"))?),
+        None => None,
+    };
+
+    let count = || -> Result<usize, ApiError> {
+        let count = params.get("count").ok_or(ApiError::missing("count"))?
+            .parse::<usize>().map_err(|_| ApiError::not_positive_integer("count"))?;
+        if count > MAX_COUNT {
+            return Err(ApiError::invalid("count", Message::key("max_exceeded").arg("field", "count").arg("max", MAX_COUNT)));
+        }
+        Ok(count)
+    };
+    let float = |key: &str| -> Result<f64, ApiError> {
+        params.get(key).ok_or(ApiError::missing(key))?
+            .parse::<f64>().ok().filter(|v| v.is_finite()).ok_or(ApiError::invalid(key, Message::key("number_expected").arg("field", key)))
+    };
+    let int_range = || -> Result<(i32, i32), ApiError> {
+        if let Some(key) = ["min", "max"].into_iter().find(|key| !params.contains_key(*key)) {
+            let message = Message::key("random_range_missing").arg("field", key);
+            return Err(ApiError::MissingParameter { field: key.to_string(), message });
+        }
+        let min = params["min"].parse::<i32>().map_err(|_| ApiError::not_positive_integer("min"))?;
+        let max = params["max"].parse::<i32>().map_err(|_| ApiError::not_positive_integer("max"))?;
+        if min >= max {
+            return Err(ApiError::invalid("min", Message::key("min_below_max")));
+        }
+        Ok((min, max))
+    };
+
+    let kind = match params.get("kind").map(|k| k.as_str()).unwrap_or("numbers") {
+        "numbers" => {
+            let count = count()?;
+            let dist = match params.get("dist").map(|d| d.as_str()).unwrap_or("uniform") {
+                "uniform" if params.get("type").is_some_and(|t| t == "float") => {
+                    let (min, max) = (float("min")?, float("max")?);
+                    if min >= max {
+                        return Err(ApiError::invalid("min", Message::key("min_below_max")));
+                    }
+                    // Con extremos como -1e308 y 1e308 el ancho del rango se desborda a infinito
+                    if !(max - min).is_finite() {
+                        return Err(ApiError::invalid("max", Message::key("random_range_overflow")));
+                    }
+                    Distribution::UniformFloat { min, max }
+                }
+                "uniform" => {
+                    let (min, max) = int_range()?;
+                    Distribution::UniformInt { min, max }
+                }
+                "normal" => {
+                    let std_dev = float("std_dev")?;
+                    if std_dev < 0.0 {
+                        return Err(ApiError::invalid("std_dev", Message::key("random_negative").arg("field", "std_dev")));
+                    }
+                    Distribution::Normal { mean: float("mean")?, std_dev }
+                }
+                "exponential" | "poisson" => {
+                    let lambda = float("lambda")?;
+                    if lambda <= 0.0 {
+                        return Err(ApiError::invalid("lambda", Message::key("random_not_positive").arg("field", "lambda")));
+                    }
+                    if params["dist"] == "poisson" { Distribution::Poisson { lambda } } else { Distribution::Exponential { lambda } }
+                }
+                _ => return Err(ApiError::invalid_choice("dist", "'uniform', 'normal', 'exponential', 'poisson'")),
+            };
+            RandomKind::Numbers { count, dist }
+        }
+        "sample" => {
+            let count = count()?;
+            let (min, max) = int_range()?;
+            if count as u64 > (max as i64 - min as i64 + 1) as u64 {
+                return Err(ApiError::invalid("count", Message::key("random_sample_too_large")));
+            }
+            RandomKind::Sample { count, min, max }
+        }
+        "shuffle" => {
+            let message = Message::key("random_items_missing");
+            let items = params.get("items").ok_or(ApiError::MissingParameter { field: "items".into(), message })?;
+            RandomKind::Shuffle(items.split(',').map(|i| i.to_string()).collect())
+        }
+        "string" => {
+            let length = match params.get("length") {
+                Some(l) => l.parse::<usize>().ok().filter(|l| *l > 0 && *l <= MAX_STRING_LENGTH)
+                    .ok_or(ApiError::not_in_range("length", 1, MAX_STRING_LENGTH))?,
+                None => 16,
+            };
+            let charset = match params.get("charset").map(|c| c.as_str()).unwrap_or("alnum") {
+                "alnum" => "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "alpha" => "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+                "digits" => "0123456789",
+                "hex" => "0123456789abcdef",
+                _ => return Err(ApiError::invalid_choice("charset", "'alnum', 'alpha', 'digits', 'hex'")),
+            };
+            RandomKind::Strings { count: count()?, length, charset: charset.to_string() }
+        }
+        "uuid" => RandomKind::Uuids { count: count()? },
+        _ => return Err(ApiError::invalid_choice("kind", "'numbers', 'sample', 'shuffle', 'string', 'uuid'")),
+    };
+
+    Ok(RandomSpec { kind, seed })
+}
+
+// Junta los valores en bloques con el formato "[a, b, c]" y los entrega a `emit`.
+// `emit` devuelve false si ya nadie espera el resultado (por ejemplo el cliente se desconectò)
+struct Chunker<F: FnMut(String) -> bool> {
+    buffer: String,
+    items: usize,
+    first: bool,
+    emit: F,
+}
+
+impl<F: FnMut(String) -> bool> Chunker<F> {
+    fn new(emit: F) -> Self {
+        Chunker { buffer: String::from("["), items: 0, first: true, emit }
+    }
+
+    fn push(&mut self, item: &str) -> bool {
+        if !self.first {
+            self.buffer.push_str(", ");
+        }
+        self.first = false;
+        self.buffer.push_str(item);
+        self.items += 1;
+
+        if self.items >= CHUNK_ITEMS {
+            self.items = 0;
+            return (self.emit)(std::mem::take(&mut self.buffer));
+        }
+        true
+    }
+
+    fn finish(mut self) {
+        self.buffer.push(']');
+        (self.emit)(self.buffer);
+    }
+}
+
+fn rng_for(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    }
+}
+
+fn invalid_distribution(error: impl ToString) -> ApiError {
+    ApiError::invalid("dist", Message::key("random_invalid_distribution").arg("error", error))
+}
+
+// Genera los valores pedidos. Con la misma semilla el resultado siempre es el mismo
+pub fn generate_random<F: FnMut(String) -> bool>(spec: &RandomSpec, emit: F) -> Result<(), ApiError> {
+    let mut rng = rng_for(spec.seed);
+    let mut out = Chunker::new(emit);
+
+    match &spec.kind {
+        RandomKind::Numbers { count, dist } => {
+            let mut next: Box<dyn FnMut(&mut StdRng) -> String> = match dist {
+                Distribution::UniformInt { min, max } => {
+                    let (min, max) = (*min, *max);
+                    Box::new(move |rng| rng.random_range(min..=max).to_string())
+                }
+                Distribution::UniformFloat { min, max } => {
+                    let uniform = Uniform::new(*min, *max).map_err(invalid_distribution)?;
+                    Box::new(move |rng| uniform.sample(rng).to_string())
+                }
+                Distribution::Normal { mean, std_dev } => {
+                    let normal = Normal::new(*mean, *std_dev).map_err(invalid_distribution)?;
+                    Box::new(move |rng| normal.sample(rng).to_string())
+                }
+                Distribution::Exponential { lambda } => {
+                    let exp = Exp::new(*lambda).map_err(invalid_distribution)?;
+                    Box::new(move |rng| exp.sample(rng).to_string())
+                }
+                Distribution::Poisson { lambda } => {
+                    let poisson = Poisson::new(*lambda).map_err(invalid_distribution)?;
+                    Box::new(move |rng| (poisson.sample(rng) as u64).to_string())
+                }
+            };
+
+            for _ in 0..*count {
+                if !out.push(&next(&mut rng)) {
+                    return Ok(());
+                }
+            }
+        }
+        RandomKind::Sample { count, min, max } => {
+            let range = (*max as i64 - *min as i64 + 1) as usize;
+            for index in rand::seq::index::sample(&mut rng, range, *count) {
+                if !out.push(&(*min as i64 + index as i64).to_string()) {
+                    return Ok(());
+                }
+            }
+        }
+        RandomKind::Shuffle(items) => {
+            let mut items = items.clone();
+            items.shuffle(&mut rng);
+            for item in &items {
+                if !out.push(&serde_json::to_string(item).unwrap()) {
+                    return Ok(());
+                }
+            }
+        }
+        RandomKind::Strings { count, length, charset } => {
+            let chars: Vec<char> = charset.chars().collect();
+            for _ in 0..*count {
+                let s: String = (0..*length).map(|_| chars[rng.random_range(0..chars.len())]).collect();
+                if !out.push(&serde_json::to_string(&s).unwrap()) {
+                    return Ok(());
+                }
+            }
+        }
+        RandomKind::Uuids { count } => {
+            for _ in 0..*count {
+                if !out.push(&format!("\"{}\"", uuid_v4(&mut rng))) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    out.finish();
+    Ok(())
+}
+
+// UUID versiòn 4 (RFC 4122) a partir de 16 bytes aleatorios
+pub fn uuid_v4<R: Rng>(rng: &mut R) -> String {
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{generate_random, parse_random_params, uuid_v4, Distribution, RandomKind, RandomSpec};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn collect(spec: &RandomSpec) -> String {
+        let mut out = String::new();
+        generate_random(spec, |chunk| {
+            out.push_str(&chunk);
+            true
+        }).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_same_seed_same_output() {
+        let spec = parse_random_params(&params(&[("count", "50"), ("min", "1"), ("max", "1000"), ("seed", "42")])).unwrap();
+        assert_eq!(collect(&spec), collect(&spec));
+
+        let other = RandomSpec { seed: Some(43), ..spec.clone() };
+        assert_ne!(collect(&spec), collect(&other));
+    }
+
+    #[test]
+    fn test_distributions() {
+        let normal = parse_random_params(&params(&[("count", "2000"), ("dist", "normal"), ("mean", "10"), ("std_dev", "2"), ("seed", "1")])).unwrap();
+        let values: Vec<f64> = collect(&normal).trim_matches(['[', ']']).split(", ").map(|v| v.parse().unwrap()).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 10.0).abs() < 0.3, "media fuera de rango: {}", mean);
+
+        let poisson = parse_random_params(&params(&[("count", "10"), ("dist", "poisson"), ("lambda", "3")])).unwrap();
+        assert!(collect(&poisson).trim_matches(['[', ']']).split(", ").all(|v| v.parse::<u64>().is_ok()));
+
+        let floats = parse_random_params(&params(&[("count", "10"), ("type", "float"), ("min", "0.5"), ("max", "0.6")])).unwrap();
+        assert_eq!(floats.kind, RandomKind::Numbers { count: 10, dist: Distribution::UniformFloat { min: 0.5, max: 0.6 } });
+        assert!(collect(&floats).trim_matches(['[', ']']).split(", ").all(|v| (0.5..0.6).contains(&v.parse::<f64>().unwrap())));
+
+        assert_eq!(parse_random_params(&params(&[("count", "1"), ("dist", "exponential"), ("lambda", "-1")])).unwrap_err().field(), Some("lambda"));
+        assert!(parse_random_params(&params(&[("count", "1"), ("type", "float"), ("min", "-1e308"), ("max", "1e308")])).is_err());
+
+        // Aunque la especificaciòn no pase por la validaciòn, el rango desbordado es un error y no un pànico
+        let huge = RandomSpec { kind: RandomKind::Numbers { count: 1, dist: Distribution::UniformFloat { min: -1e308, max: 1e308 } }, seed: None };
+        assert!(generate_random(&huge, |_| true).is_err());
+    }
+
+    #[test]
+    fn test_sample_shuffle_strings_and_uuids() {
+        let sample = parse_random_params(&params(&[("kind", "sample"), ("count", "10"), ("min", "1"), ("max", "10")])).unwrap();
+        let mut values: Vec<i64> = collect(&sample).trim_matches(['[', ']']).split(", ").map(|v| v.parse().unwrap()).collect();
+        values.sort();
+        assert_eq!(values, (1..=10).collect::<Vec<_>>());
+        assert!(parse_random_params(&params(&[("kind", "sample"), ("count", "11"), ("min", "1"), ("max", "10")])).is_err());
+
+        let shuffle = parse_random_params(&params(&[("kind", "shuffle"), ("items", "a,b,c"), ("seed", "7")])).unwrap();
+        let shuffled = collect(&shuffle);
+        assert!(shuffled.contains("\"a\"") && shuffled.contains("\"b\"") && shuffled.contains("\"c\""));
+
+        let strings = parse_random_params(&params(&[("kind", "string"), ("count", "3"), ("length", "8"), ("charset", "hex")])).unwrap();
+        assert_eq!(collect(&strings).len(), 2 + 3 * 10 + 2 * 2);
+
+        let uuid = uuid_v4(&mut rand::rng());
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+
+    #[test]
+    fn test_large_counts_are_chunked() {
+        let spec = parse_random_params(&params(&[("count", "25000"), ("min", "0"), ("max", "9")])).unwrap();
+        assert!(spec.streams());
+
+        let mut chunks = 0;
+        generate_random(&spec, |_| {
+            chunks += 1;
+            true
+        }).unwrap();
+        assert!(chunks > 20);
+
+        // Si el receptor desaparece se deja de generar
+        let mut calls = 0;
+        generate_random(&spec, |_| {
+            calls += 1;
+            false
+        }).unwrap();
+        assert_eq!(calls, 1);
+    }
+}
@@ -1,26 +1,222 @@
-// Archivo para definir el formato de las respuestas cuando son existosas
-
-pub fn http_response_200(body : &str) -> String {
-    let json = format!("{{\"status\":200,\"message\":\"{}\"}}", body);
-    format!(
-        "HTTP/1.0 202 Accepted\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
-        json.len(),
-        json
-    )
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+// Archivo para definir el formato de las respuestas HTTP.
+// Toda respuesta se arma con `Response`: còdigo de estado, encabezados y cuerpo.
+// Los cuerpos JSON se serializan con serde. Los èxitos usan el sobre {"status":200,"message":...};
+// los errores se arman desde `ApiError` (error_responses.rs) con su còdigo y el campo que los causò
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Created,
+    NoContent,
+    PartialContent,
+    NotModified,
+    BadRequest,
+    Unauthorized,
+    NotFound,
+    MethodNotAllowed,
+    NotAcceptable,
+    Conflict,
+    LengthRequired,
+    PayloadTooLarge,
+    RangeNotSatisfiable,
+    TooManyRequests,
+    InternalServerError,
+    ServiceUnavailable,
+    GatewayTimeout,
+}
+
+impl Status {
+    pub fn code(self) -> u16 {
+        match self {
+            Status::Ok => 200,
+            Status::Created => 201,
+            Status::NoContent => 204,
+            Status::PartialContent => 206,
+            Status::NotModified => 304,
+            Status::BadRequest => 400,
+            Status::Unauthorized => 401,
+            Status::NotFound => 404,
+            Status::MethodNotAllowed => 405,
+            Status::NotAcceptable => 406,
+            Status::Conflict => 409,
+            Status::LengthRequired => 411,
+            Status::PayloadTooLarge => 413,
+            Status::RangeNotSatisfiable => 416,
+            Status::TooManyRequests => 429,
+            Status::InternalServerError => 500,
+            Status::ServiceUnavailable => 503,
+            Status::GatewayTimeout => 504,
+        }
+    }
+
+    pub fn reason(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Created => "Created",
+            Status::NoContent => "No Content",
+            Status::PartialContent => "Partial Content",
+            Status::NotModified => "Not Modified",
+            Status::BadRequest => "Bad Request",
+            Status::Unauthorized => "Unauthorized",
+            Status::NotFound => "Not Found",
+            Status::MethodNotAllowed => "Method Not Allowed",
+            Status::NotAcceptable => "Not Acceptable",
+            Status::Conflict => "Conflict",
+            Status::LengthRequired => "Length Required",
+            Status::PayloadTooLarge => "Payload Too Large",
+            Status::RangeNotSatisfiable => "Range Not Satisfiable",
+            Status::TooManyRequests => "Too Many Requests",
+            Status::InternalServerError => "Internal Server Error",
+            Status::ServiceUnavailable => "Service Unavailable",
+            Status::GatewayTimeout => "Gateway Timeout",
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self.code() < 400
+    }
+}
+
+// Sobre comùn de las respuestas JSON exitosas
+#[derive(Serialize)]
+struct Envelope<'a> {
+    status: u16,
+    message: &'a Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: Status,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: Status) -> Response {
+        Response { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    // Respuesta exitosa con un texto como mensaje
+    pub fn ok(message: &str) -> Response {
+        Response::success(Status::Ok, Value::String(message.to_string()))
+    }
+
+    // Respuesta exitosa cuyo mensaje es un valor JSON (objeto, arreglo...) en vez de texto
+    pub fn ok_json(message: Value) -> Response {
+        Response::success(Status::Ok, message)
+    }
+
+    pub fn success(status: Status, message: Value) -> Response {
+        Response::new(status).json(&Envelope { status: status.code(), message: &message })
+    }
+
+    // Agrega o reemplaza un encabezado (sin distinguir mayùsculas)
+    pub fn header(mut self, name: &str, value: impl ToString) -> Response {
+        self.set_header(name, value);
+        self
+    }
+
+    // Igual que `header`, para quien tiene la respuesta prestada (middlewares)
+    pub fn set_header(&mut self, name: &str, value: impl ToString) {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn body(mut self, content_type: &str, body: impl Into<Vec<u8>>) -> Response {
+        self.body = body.into();
+        self.header("Content-Type", content_type)
+    }
+
+    pub fn json<T: Serialize + ?Sized>(self, value: &T) -> Response {
+        let body = serde_json::to_vec(value).expect("los valores de respuesta siempre se pueden serializar");
+        self.body("application/json", body)
+    }
+
+    // Cuerpo JSON ya parseado, para las pruebas y para quien reenvìa la respuesta
+    pub fn body_json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+
+    // Lìnea de estado y encabezados. Content-Length se calcula del cuerpo salvo que ya venga puesto
+    pub fn head_bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.0 {} {}\r\n", self.status.code(), self.status.reason());
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if self.get_header("Content-Length").is_none() && self.status != Status::NotModified {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.head_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    pub fn write_to<W: Write + ?Sized>(&self, stream: &mut W) -> io::Result<()> {
+        stream.write_all(&self.to_bytes())?;
+        stream.flush()
+    }
+}
+
+// Solo la lìnea de estado y los encabezados, para respuestas cuyo cuerpo se escribe despuès por partes
+// (archivos grandes, streaming). No agrega Content-Length
+pub fn write_head<W: Write + ?Sized>(stream: &mut W, status: Status, headers: &[(&str, String)]) -> io::Result<()> {
+    let mut head = format!("HTTP/1.0 {} {}\r\n", status.code(), status.reason());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())
 }
 
 #[cfg(test)]
 mod test {
-    use super::http_response_200;
+    use serde_json::json;
+
+    use super::{write_head, Response, Status};
 
     #[test]
-    fn test_http_response_200() {
-        let msg = "Tarea existosa";
-        let response = http_response_200(msg);
+    fn test_ok_response() {
+        let response = Response::ok("Tarea existosa");
+        let bytes = String::from_utf8(response.to_bytes()).unwrap();
 
-        assert!(response.contains("202 Accepted"));
-        assert!(response.contains("Content-Type: application/json"));
-        assert!(response.contains("\"status\":200"));
-        assert!(response.contains(msg));
+        assert!(bytes.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(bytes.contains("Content-Type: application/json\r\n"));
+        assert!(bytes.ends_with("\r\n\r\n{\"status\":200,\"message\":\"Tarea existosa\"}"));
+        assert!(bytes.contains(&format!("Content-Length: {}\r\n", response.body.len())));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_json_is_escaped() {
+        let response = Response::ok("comillas \" y \\ barra\nlìnea");
+        assert_eq!(response.body_json().unwrap(), json!({"status": 200, "message": "comillas \" y \\ barra\nlìnea"}));
+
+        let response = Response::ok_json(json!({"workers": [1, 2]}));
+        assert_eq!(response.body_json().unwrap()["message"]["workers"][1], 2);
+    }
+
+    #[test]
+    fn test_headers_and_raw_body() {
+        let response = Response::new(Status::Created).header("ETag", "\"a\"").header("etag", "\"b\"").body("text/plain", "hola");
+        assert_eq!(response.get_header("ETag"), Some("\"b\""));
+        assert_eq!(response.headers.len(), 2);
+        assert!(String::from_utf8(response.to_bytes()).unwrap().starts_with("HTTP/1.0 201 Created\r\n"));
+
+        let mut out = Vec::new();
+        write_head(&mut out, Status::PartialContent, &[("Content-Length", "3".to_string())]).unwrap();
+        assert_eq!(out, b"HTTP/1.0 206 Partial Content\r\nContent-Length: 3\r\n\r\n");
+    }
+}
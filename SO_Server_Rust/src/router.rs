@@ -0,0 +1,502 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::error_responses::ApiError;
+use crate::formats::Format;
+use crate::handle_connection::{HttpRequest, ServerState};
+use crate::i18n::{Message, Translated};
+use crate::responses::{Response, Status};
+
+// Tabla de rutas del servidor. Cada ruta se registra con su mètodo, un patròn de ruta (`/jobs/{id}`),
+// el esquema de sus paràmetros y el handler. El router responde 404 si ninguna ruta coincide,
+// 405 con `Allow` si la ruta existe con otro mètodo, y atiende HEAD y OPTIONS sin que cada ruta los registre.
+// Quien use la biblioteca puede agregar sus propias rutas a `default_router()` antes de arrancar el servidor
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+}
+
+// Orden en que se listan en `Allow`
+const METHODS: [Method; 7] = [Method::Get, Method::Head, Method::Post, Method::Put, Method::Patch, Method::Delete, Method::Options];
+
+impl Method {
+    pub fn parse(method: &str) -> Option<Method> {
+        METHODS.iter().copied().find(|m| m.as_str() == method)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+            Method::Options => "OPTIONS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Text,
+    // Entero sin signo
+    Integer,
+    Number,
+    // true/false o 1/0
+    Boolean,
+}
+
+// Paràmetro de la query string. El router valida que estè presente si es obligatorio y que tenga el tipo indicado;
+// los paràmetros que no estàn en el esquema se pasan tal cual al handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub required: bool,
+}
+
+impl Param {
+    pub const fn required(name: &'static str, kind: ParamKind) -> Param {
+        Param { name, kind, required: true }
+    }
+
+    pub const fn optional(name: &'static str, kind: ParamKind) -> Param {
+        Param { name, kind, required: false }
+    }
+
+    fn validate(&self, value: Option<&String>) -> Result<(), ApiError> {
+        let value = match value {
+            Some(value) => value,
+            None if self.required => return Err(ApiError::missing(self.name)),
+            None => return Ok(()),
+        };
+        let valid = match self.kind {
+            ParamKind::Text => true,
+            ParamKind::Integer => value.parse::<u64>().is_ok(),
+            ParamKind::Number => value.parse::<f64>().is_ok_and(|v| v.is_finite()),
+            ParamKind::Boolean => matches!(value.as_str(), "true" | "false" | "1" | "0"),
+        };
+        match (valid, self.kind) {
+            (true, _) => Ok(()),
+            (false, ParamKind::Integer) => Err(ApiError::not_positive_integer(self.name)),
+            (false, ParamKind::Number) => Err(ApiError::invalid(self.name, Message::key("number_expected").arg("field", self.name))),
+            (false, _) => Err(ApiError::invalid(self.name, Message::key("boolean_expected").arg("field", self.name))),
+        }
+    }
+}
+
+// Documentaciòn de una ruta para /help y /openapi.json. El tipo y si es obligatorio cada paràmetro
+// salen del esquema; aquì va lo que el esquema no sabe
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Doc {
+    pub summary: Translated,
+    // Descripciòn de cada paràmetro del esquema, por nombre
+    pub params: &'static [(&'static str, Translated)],
+    // Ejemplo de uso. Sus valores sirven tambièn de ejemplo para cada paràmetro
+    pub example: &'static str,
+    // Còdigos de error propios de la ruta, ademàs de los que implica el esquema (missing_parameter, invalid_parameter)
+    pub errors: &'static [&'static str],
+    // Tipos de contenido de las rutas `raw`; las demàs responden con el sobre JSON en cualquier formato
+    pub produces: &'static [&'static str],
+}
+
+impl Doc {
+    pub const fn new(summary: Translated, params: &'static [(&'static str, Translated)], example: &'static str) -> Doc {
+        Doc { summary, params, example, errors: &[], produces: &[] }
+    }
+
+    pub const fn errors(mut self, errors: &'static [&'static str]) -> Doc {
+        self.errors = errors;
+        self
+    }
+
+    pub const fn produces(mut self, produces: &'static [&'static str]) -> Doc {
+        self.produces = produces;
+        self
+    }
+
+    pub fn param(&self, name: &str) -> Option<Translated> {
+        self.params.iter().find(|(n, _)| *n == name).map(|(_, text)| *text)
+    }
+}
+
+// El socket de la conexiòn (o un Cursor en las pruebas)
+pub trait Connection: Read + Write {}
+impl<T: Read + Write> Connection for T {}
+
+// Todo lo que un handler necesita de la solicitud
+pub struct Context<'a> {
+    pub method: Method,
+    // Ruta sin la query string
+    pub path: String,
+    pub params: HashMap<String, String>,
+    // Segmentos `{nombre}` del patròn
+    pub path_params: HashMap<String, String>,
+    pub request: &'a HttpRequest,
+    // Para leer el cuerpo o escribir la respuesta directo (archivos, imàgenes, streaming)
+    pub stream: &'a mut dyn Connection,
+    pub state: &'a ServerState,
+    // Para las rutas que describen al resto (/help, /openapi.json)
+    pub router: &'a Router,
+    // Formato de salida ya negociado
+    pub format: Format,
+}
+
+// Un handler devuelve la respuesta o avisa que ya la escribiò en la conexiòn
+pub enum Reply {
+    Response(Response),
+    Written,
+}
+
+impl From<Response> for Reply {
+    fn from(response: Response) -> Reply {
+        Reply::Response(response)
+    }
+}
+
+// Handlers que no devuelven nada: ya escribieron en la conexiòn
+impl From<()> for Reply {
+    fn from(_: ()) -> Reply {
+        Reply::Written
+    }
+}
+
+impl From<ApiError> for Reply {
+    fn from(error: ApiError) -> Reply {
+        Reply::Response(error.into())
+    }
+}
+
+type Handler = Box<dyn Fn(&mut Context) -> Reply + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+pub struct Route {
+    pub method: Method,
+    pub pattern: String,
+    pub params: Vec<Param>,
+    // La ruta escribe su propia respuesta (binaria o por partes): no se le aplica el formato de salida
+    pub raw: bool,
+    // GET con efectos (crea, borra o restaura archivos, cancela trabajos): HEAD no se atiende con esta ruta
+    pub mutating: bool,
+    pub doc: Doc,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    pub fn raw(&mut self) -> &mut Route {
+        self.raw = true;
+        self
+    }
+
+    pub fn mutating(&mut self) -> &mut Route {
+        self.mutating = true;
+        self
+    }
+
+    pub fn doc(&mut self, doc: Doc) -> &mut Route {
+        self.doc = doc;
+        self
+    }
+
+    // Nombres de los segmentos `{nombre}` del patròn
+    pub fn path_params(&self) -> Vec<&str> {
+        self.segments.iter().filter_map(|s| match s {
+            Segment::Param(name) => Some(name.as_str()),
+            Segment::Literal(_) => None,
+        }).collect()
+    }
+
+    pub fn declares(&self, name: &str) -> bool {
+        self.params.iter().any(|p| p.name == name)
+    }
+
+    fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = path.trim_end_matches('/').split('/').skip(1).collect();
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+        let mut path_params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Param(name) if !part.is_empty() => {
+                    path_params.insert(name.clone(), part.to_string());
+                }
+                _ => return None,
+            }
+        }
+        Some(path_params)
+    }
+
+    pub fn validate(&self, params: &HashMap<String, String>) -> Result<(), ApiError> {
+        self.params.iter().try_for_each(|param| param.validate(params.get(param.name)))
+    }
+
+    // Valida el esquema de paràmetros y ejecuta el handler
+    pub fn call(&self, ctx: &mut Context) -> Reply {
+        match self.validate(&ctx.params) {
+            Ok(()) => (self.handler)(ctx),
+            Err(error) => error.into(),
+        }
+    }
+}
+
+// Resultado de buscar una ruta
+pub enum Resolution<'r> {
+    Found(&'r Route, HashMap<String, String>),
+    // OPTIONS o mètodo no permitido: los mètodos que sì acepta la ruta
+    Options(String),
+    MethodNotAllowed(String),
+    NotFound,
+}
+
+impl Resolution<'_> {
+    // Respuesta para todo lo que no es una ruta encontrada
+    pub fn response(&self, method: &str, path: &str) -> Option<Response> {
+        match self {
+            Resolution::Found(..) => None,
+            Resolution::Options(allow) => Some(Response::new(Status::NoContent).header("Allow", allow)),
+            Resolution::MethodNotAllowed(allow) => {
+                let error = ApiError::MethodNotAllowed { message: Message::key("method_not_allowed").arg("method", method).arg("path", path) };
+                Some(Response::from(error).header("Allow", allow))
+            }
+            Resolution::NotFound => Some(ApiError::RouteNotFound { path: path.to_string() }.into()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    pub fn route<R, F>(&mut self, method: Method, pattern: &str, params: &[Param], handler: F) -> &mut Route
+    where
+        R: Into<Reply>,
+        F: Fn(&mut Context) -> R + Send + Sync + 'static,
+    {
+        let segments = pattern.trim_end_matches('/').split('/').skip(1).map(|s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => Segment::Param(name.to_string()),
+            None => Segment::Literal(s.to_string()),
+        }).collect();
+
+        // Una ruta registrada dos veces reemplaza a la anterior, asì se puede cambiar un handler de default_router()
+        self.routes.retain(|r| !(r.method == method && r.pattern == pattern));
+        self.routes.push(Route {
+            method,
+            pattern: pattern.to_string(),
+            params: params.to_vec(),
+            raw: false,
+            mutating: false,
+            doc: Doc::default(),
+            segments,
+            handler: Box::new(move |ctx| handler(ctx).into()),
+        });
+        self.routes.last_mut().unwrap()
+    }
+
+    pub fn get<R: Into<Reply>, F: Fn(&mut Context) -> R + Send + Sync + 'static>(&mut self, pattern: &str, params: &[Param], handler: F) -> &mut Route {
+        self.route(Method::Get, pattern, params, handler)
+    }
+
+    pub fn post<R: Into<Reply>, F: Fn(&mut Context) -> R + Send + Sync + 'static>(&mut self, pattern: &str, params: &[Param], handler: F) -> &mut Route {
+        self.route(Method::Post, pattern, params, handler)
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    // Mètodos que acepta una ruta, para el encabezado Allow. GET implica HEAD (salvo en rutas que modifican algo)
+    // y siempre se acepta OPTIONS
+    fn allowed(&self, path: &str) -> Option<String> {
+        let matching: Vec<&Route> = self.routes.iter().filter(|r| r.matches(path).is_some()).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        let registered: Vec<Method> = matching.iter().map(|r| r.method).collect();
+        let head = matching.iter().any(|r| r.method == Method::Get && !r.mutating);
+        let allowed: Vec<&str> = METHODS.iter()
+            .filter(|m| registered.contains(m) || (**m == Method::Head && head) || **m == Method::Options)
+            .map(|m| m.as_str())
+            .collect();
+        Some(allowed.join(", "))
+    }
+
+    pub fn resolve(&self, method: &str, path: &str) -> Resolution<'_> {
+        let allowed = match self.allowed(path) {
+            Some(allowed) => allowed,
+            None => return Resolution::NotFound,
+        };
+        let method = match Method::parse(method) {
+            Some(method) => method,
+            None => return Resolution::MethodNotAllowed(allowed),
+        };
+
+        let find = |method: Method| self.routes.iter().filter(|r| r.method == method).find_map(|r| r.matches(path).map(|p| (r, p)));
+        match (find(method), method) {
+            (Some((route, path_params)), _) => Resolution::Found(route, path_params),
+            // HEAD se atiende como GET y se descarta el cuerpo, pero nunca ejecuta una ruta que modifica algo
+            (None, Method::Head) => match find(Method::Get).filter(|(route, _)| !route.mutating) {
+                Some((route, path_params)) => Resolution::Found(route, path_params),
+                None => Resolution::MethodNotAllowed(allowed),
+            },
+            (None, Method::Options) => Resolution::Options(allowed),
+            (None, _) => Resolution::MethodNotAllowed(allowed),
+        }
+    }
+
+    // Patròn con el que se agrupa una solicitud en las mètricas: el de la ruta que la atendiò o, para OPTIONS
+    // y mètodos no permitidos, el de la url. Asì cada ruta es una sola serie sin importar sus paràmetros
+    pub fn label(&self, method: &str, path: &str) -> &str {
+        if let Resolution::Found(route, _) = self.resolve(method, path) {
+            return &route.pattern;
+        }
+        self.routes.iter().find(|r| r.matches(path).is_some()).map_or("unmatched", |r| r.pattern.as_str())
+    }
+}
+
+// Respuesta a HEAD: se conserva Content-Length pero no se envìa el cuerpo
+pub fn head_response(response: Response) -> Response {
+    let length = response.body.len();
+    let mut response = match response.get_header("Content-Length") {
+        Some(_) => response,
+        None => response.header("Content-Length", length),
+    };
+    response.body.clear();
+    response
+}
+
+// Para las rutas que escriben directo al socket y se piden con HEAD: deja pasar los encabezados y descarta el cuerpo
+pub struct HeadOnly<'a> {
+    inner: &'a mut dyn Connection,
+    // Ùltimos bytes escritos, para detectar el fin de los encabezados
+    tail: Vec<u8>,
+    body: bool,
+}
+
+impl<'a> HeadOnly<'a> {
+    pub fn new(inner: &'a mut dyn Connection) -> HeadOnly<'a> {
+        HeadOnly { inner, tail: Vec::new(), body: false }
+    }
+}
+
+impl Read for HeadOnly<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for HeadOnly<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.body {
+            return Ok(buf.len());
+        }
+        let before = self.tail.len();
+        self.tail.extend_from_slice(buf);
+        match self.tail.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => {
+                self.body = true;
+                self.inner.write_all(&self.tail[before.min(end + 4)..end + 4])?;
+            }
+            None => self.inner.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::responses::{Response, Status};
+
+    use super::{head_response, HeadOnly, Method, Param, ParamKind, Resolution, Router};
+    use std::io::{Cursor, Write};
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        router.get("/jobs/{id}", &[], |_| Response::ok("job"));
+        router.route(Method::Delete, "/jobs/{id}", &[], |_| Response::ok("cancelado"));
+        router.post("/csv", &[Param::required("text", ParamKind::Text)], |_| Response::ok("csv"));
+        router.get("/deletefile", &[], |_| Response::ok("borrado")).mutating();
+        router
+    }
+
+    #[test]
+    fn test_resolve_methods_and_path_params() {
+        let router = router();
+
+        match router.resolve("DELETE", "/jobs/b1") {
+            Resolution::Found(route, params) => {
+                assert_eq!(route.method, Method::Delete);
+                assert_eq!(params.get("id").unwrap(), "b1");
+            }
+            _ => panic!("la ruta deberìa existir"),
+        }
+        assert!(matches!(router.resolve("HEAD", "/jobs/b1"), Resolution::Found(route, _) if route.method == Method::Get));
+        assert!(matches!(router.resolve("GET", "/jobs"), Resolution::NotFound));
+        assert!(matches!(router.resolve("GET", "/jobs/a/b"), Resolution::NotFound));
+
+        let not_allowed = router.resolve("PUT", "/jobs/b1").response("PUT", "/jobs/b1").unwrap();
+        assert_eq!(not_allowed.status, Status::MethodNotAllowed);
+        assert_eq!(not_allowed.get_header("Allow"), Some("GET, HEAD, DELETE, OPTIONS"));
+        assert_eq!(not_allowed.body_json().unwrap()["code"], "method_not_allowed");
+
+        let options = router.resolve("OPTIONS", "/csv").response("OPTIONS", "/csv").unwrap();
+        assert_eq!((options.status, options.get_header("Allow")), (Status::NoContent, Some("POST, OPTIONS")));
+        assert!(matches!(router.resolve("BREW", "/csv"), Resolution::MethodNotAllowed(_)));
+
+        // HEAD no ejecuta una ruta GET que modifica algo
+        let head = router.resolve("HEAD", "/deletefile").response("HEAD", "/deletefile").unwrap();
+        assert_eq!((head.status, head.get_header("Allow")), (Status::MethodNotAllowed, Some("GET, OPTIONS")));
+    }
+
+    #[test]
+    fn test_param_schema() {
+        let integer = Param::required("num", ParamKind::Integer);
+        assert_eq!(integer.validate(None).unwrap_err().code(), "missing_parameter");
+        assert_eq!(integer.validate(Some(&"-3".to_string())).unwrap_err().field(), Some("num"));
+        assert!(integer.validate(Some(&"3".to_string())).is_ok());
+
+        assert!(Param::optional("x", ParamKind::Number).validate(None).is_ok());
+        assert!(Param::optional("x", ParamKind::Number).validate(Some(&"-2.5".to_string())).is_ok());
+        assert!(Param::optional("regex", ParamKind::Boolean).validate(Some(&"si".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_head_strips_body() {
+        let length = Response::ok("hola").body.len().to_string();
+        let response = head_response(Response::ok("hola"));
+        assert!(response.body.is_empty());
+        assert_eq!(response.get_header("Content-Length"), Some(length.as_str()));
+
+        let mut socket = Cursor::new(Vec::new());
+        let mut head = HeadOnly::new(&mut socket);
+        head.write_all(b"HTTP/1.0 200 OK\r\nContent-Type: image/png\r").unwrap();
+        head.write_all(b"\n\r\nPNG...").unwrap();
+        head.write_all(b"mas bytes").unwrap();
+        assert_eq!(socket.into_inner(), b"HTTP/1.0 200 OK\r\nContent-Type: image/png\r\n\r\n");
+    }
+}
@@ -0,0 +1,910 @@
+use std::collections::HashMap;
+use std::process;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::benchmarks::parse_benchmark;
+use crate::calc::parse_variables;
+use crate::codec::{parse_output, parse_steps, CodecOutput};
+use crate::compression::parse_compress_params;
+use crate::csv_ops::parse_csv_params;
+use crate::datetime::{parse_duration, parse_time_format, parse_zone, DateOp, DateRequest, Zone};
+use crate::endpoints::WriteMode;
+use crate::error_responses::ApiError;
+use crate::file_transfer::handle_file_request;
+use crate::formats::{csv_passthrough, Format};
+use crate::grep::{GrepOptions, MAX_CONTEXT, MAX_MATCHES};
+use crate::handle_connection::{enqueue_and_reply, stage_body, stream_task};
+use crate::i18n::{current_lang, Message, Translated};
+use crate::json_ops::parse_json_params;
+use crate::mandelbrot::handle_mandelbrot;
+use crate::random_gen::{parse_random_params, STREAM_THRESHOLD};
+use crate::help::{help_json, openapi_json};
+use crate::metrics::{render, Snapshot, METRICS, METRICS_CONTENT_TYPE};
+use crate::stats::STATS;
+use crate::sysinfo::{ProcessInfo, SystemInfo};
+use crate::responses::{Response, Status};
+use crate::router::{Context, Doc, Method, Param, ParamKind::{Boolean, Integer, Number, Text}, Reply, Router};
+use crate::storage::InputSource;
+use crate::task_queue::{cancel_job, finish_job, register_job, CancelToken, reply_channel, submit, Task, TaskType};
+use crate::text_ops::parse_text_request;
+use crate::text_stats::parse_stats_params;
+
+// Rutas del servidor y sus handlers. Cada ruta declara los paràmetros que valida el router;
+// los handlers siguen revisando las reglas que dependen de varios paràmetros
+
+const fn req(name: &'static str, kind: crate::router::ParamKind) -> Param {
+    Param::required(name, kind)
+}
+
+const fn opt(name: &'static str, kind: crate::router::ParamKind) -> Param {
+    Param::optional(name, kind)
+}
+
+const TEXT_DOC: &[(&str, Translated)] = &[("text", ("texto a convertir", "text to convert"))];
+
+const TEXT_ROUTES: [(&str, &[Param], Doc); 7] = [
+    ("/tolower", &[req("text", Text)], Doc::new(("Convierte el texto a minúsculas (Unicode)", "Converts the text to lowercase (Unicode)"), TEXT_DOC, "/tolower?text=ÁRBOL")),
+    ("/titlecase", &[req("text", Text)], Doc::new(("Primera letra de cada palabra en mayúscula", "Capitalizes the first letter of every word"), TEXT_DOC, "/titlecase?text=hola mundo")),
+    ("/casefold", &[req("text", Text)], Doc::new(("Plegado de mayùsculas para comparar sin distinguir (Straße => strasse)", "Case folding for case-insensitive comparison (Straße => strasse)"), TEXT_DOC, "/casefold?text=Straße")),
+    ("/normalize", &[req("text", Text), opt("form", Text)], Doc::new(
+        ("Normaliza el texto a una forma Unicode", "Normalizes the text to a Unicode form"),
+        &[("text", ("texto a normalizar", "text to normalize")), ("form", ("nfc (por defecto), nfd, nfkc o nfkd", "nfc (default), nfd, nfkc or nfkd"))],
+        "/normalize?text=ﬁ&form=nfkc",
+    )),
+    ("/trim", &[req("text", Text)], Doc::new(("Quita los espacios Unicode al inicio y al final", "Removes leading and trailing Unicode whitespace"), TEXT_DOC, "/trim?text=%20hola%20")),
+    ("/slugify", &[req("text", Text)], Doc::new(("Convierte el texto en un slug ascii-friendly", "Turns the text into an ascii-friendly slug"), TEXT_DOC, "/slugify?text=¡Hola Señor!")),
+    ("/text", &[req("text", Text), req("ops", Text)], Doc::new(
+        ("Aplica varias transformaciones en orden", "Applies several transformations in order"),
+        &[("text", ("texto", "text")), ("ops", ("lista separada por comas (reverse, upper, lower, title, casefold, nfc, nfd, nfkc, nfkd, trim, slugify)",
+                                                "comma separated list (reverse, upper, lower, title, casefold, nfc, nfd, nfkc, nfkd, trim, slugify)"))],
+        "/text?ops=trim,lower,slugify&text=Hola Mundo",
+    )),
+];
+
+// Rutas con texto, archivo guardado o cuerpo POST: (ruta, paràmetros, documentaciòn, ejemplo del POST).
+// Los dos primeros paràmetros documentados son `text` y `name`, que en POST se reemplazan por el cuerpo
+const INPUT_ROUTES: [(&str, &[Param], Doc, &str); 6] = [
+    ("/textstats", &[opt("text", Text), opt("name", Text), opt("top", Integer), opt("ngram", Integer)], Doc::new(
+        ("Conteos de caracteres, bytes, palabras y lìneas, palabras màs frecuentes y n-gramas", "Character, byte, word and line counts, most frequent words and n-grams"),
+        &[TEXT_PARAM, NAME_PARAM, ("top", ("cantidad de palabras/n-gramas (10)", "number of words/n-grams (10)")), ("ngram", ("tamaño de los n-gramas de caracteres (3)", "size of the character n-grams (3)"))],
+        "/textstats?name=miarchivo&top=5",
+    ), "/textstats?top=5"),
+    ("/hash", &[opt("text", Text), opt("name", Text), opt("decode", Text)], Doc::new(
+        ("Devuelve el hash SHA-256 del texto, de un archivo o del cuerpo POST", "Returns the SHA-256 hash of the text, a file or the POST body"),
+        &[TEXT_PARAM, NAME_PARAM, ("decode", ("decodifica antes de hashear", "decode before hashing"))],
+        "/hash?text=aG9sYQ==&decode=base64",
+    ), "/hash?decode=base64"),
+    ("/encode", &[req("to", Text), opt("text", Text), opt("name", Text), opt("hash", Boolean), opt("save", Text)], Doc::new(
+        ("Codifica texto, un archivo o el cuerpo POST", "Encodes text, a file or the POST body"),
+        &[TEXT_PARAM, NAME_PARAM, ("to", ("base64, base64url, base32, hex, url o qp (separadas por comas para encadenar)", "base64, base64url, base32, hex, url or qp (comma separated to chain)")),
+          ("hash", ("true para devolver el SHA-256 del resultado", "true to return the SHA-256 of the result")), ("save", ("archivo donde guardar el resultado", "file where the result is stored"))],
+        "/encode?to=base64&text=hola",
+    ), "/encode?to=base64"),
+    ("/decode", &[req("from", Text), opt("text", Text), opt("name", Text), opt("hash", Boolean), opt("save", Text)], Doc::new(
+        ("Decodifica de forma estricta (400 con la posiciòn del error si la entrada es invàlida)", "Strict decoding (400 with the error position if the input is invalid)"),
+        &[TEXT_PARAM, NAME_PARAM, ("from", ("base64, base64url, base32, hex, url o qp", "base64, base64url, base32, hex, url or qp")),
+          ("hash", ("true para devolver el SHA-256 del resultado", "true to return the SHA-256 of the result")), ("save", ("archivo (necesario si el resultado no es UTF-8)", "file (required if the result is not UTF-8)"))],
+        "/decode?from=hex&text=686f6c61",
+    ), "/decode?from=hex"),
+    ("/csv", &[opt("text", Text), opt("name", Text), opt("where", Text), opt("sort", Text), opt("group", Text), opt("agg", Text), opt("limit", Integer), opt("delimiter", Text), opt("format", Text), opt("save", Text)], Doc::new(
+        ("Filtra, ordena y agrupa datos CSV; ordena en disco si la entrada es grande", "Filters, sorts and groups CSV data; sorts on disk when the input is large"),
+        &[TEXT_PARAM, NAME_PARAM, ("where", ("condiciones separadas por coma (=, !=, <, <=, >, >=, ~)", "comma separated conditions (=, !=, <, <=, >, >=, ~)")),
+          ("sort", ("columna (-columna descendente)", "column (-column descending)")), ("group", ("columna", "column")),
+          ("agg", ("count, sum:col, avg:col, min:col, max:col", "count, sum:col, avg:col, min:col, max:col")), ("limit", ("filas", "rows")),
+          ("delimiter", ("carácter o tab", "character or tab")), ("format", ("csv o json", "csv or json")), ("save", ("archivo de salida", "output file"))],
+        "/csv?name=ventas&group=ciudad&agg=sum:precio&sort=-sum(precio)",
+    ), "/csv?sort=-precio&limit=10"),
+    ("/json", &[req("op", Text), opt("text", Text), opt("name", Text), opt("path", Text), opt("with", Text), opt("with_text", Text), opt("indent", Integer)], Doc::new(
+        ("Consulta (tipo JSONPath), formatea, valida, ordena claves o compara documentos JSON", "Queries (JSONPath style), formats, validates, sorts keys or diffs JSON documents"),
+        &[TEXT_PARAM, NAME_PARAM, ("op", ("pretty, minify, validate, sort, query o diff", "pretty, minify, validate, sort, query or diff")),
+          ("path", ("ruta para query ($.a[0].b, $..b, [?(@.x > 1)])", "query path ($.a[0].b, $..b, [?(@.x > 1)])")),
+          ("with", ("archivo con el segundo documento para diff", "file with the second document for diff")), ("with_text", ("segundo documento para diff", "second document for diff")),
+          ("indent", ("espacios (0-8)", "spaces (0-8)"))],
+        "/json?name=datos.json&op=query&path=$..precio",
+    ), "/json?op=pretty&indent=4"),
+];
+const TEXT_PARAM: (&str, Translated) = ("text", ("texto", "text"));
+const NAME_PARAM: (&str, Translated) = ("name", ("archivo guardado", "stored file"));
+
+const BENCH_DOC: &[(&str, Translated)] = &[("size", ("tamaño", "size")), ("seed", ("semilla (42)", "seed (42)"))];
+const BENCH_ROUTES: [(&str, Doc); 6] = [
+    ("/primes", Doc::new(("Cuenta los primos <= size con una criba", "Counts the primes <= size with a sieve"), BENCH_DOC, "/primes?size=10000000")),
+    ("/factorize", Doc::new(("Factoriza size por divisiòn de prueba", "Factorizes size by trial division"), BENCH_DOC, "/factorize?size=600851475143")),
+    ("/matrix", Doc::new(("Multiplica dos matrices aleatorias de size x size", "Multiplies two random size x size matrices"), BENCH_DOC, "/matrix?size=300")),
+    ("/montecarlo", Doc::new(("Estima pi con size muestras aleatorias", "Estimates pi with size random samples"), BENCH_DOC, "/montecarlo?size=10000000")),
+    ("/sort", Doc::new(("Ordena size enteros aleatorios", "Sorts size random integers"), BENCH_DOC, "/sort?size=1000000")),
+    ("/collatz", Doc::new(("Busca el nùmero menor a size con la secuencia de Collatz màs larga", "Finds the number below size with the longest Collatz sequence"), BENCH_DOC, "/collatz?size=1000000")),
+];
+
+const DATE_PARAMS: [Param; 6] = [opt("input", Text), opt("input_format", Text), opt("from", Text), opt("tz", Text), opt("precision", Text), opt("format", Text)];
+const DATE_DOC: [(&str, Translated); 6] = [
+    ("input", ("fecha (la hora actual si falta)", "date (the current time if missing)")),
+    ("input_format", ("patròn strftime de la entrada", "strftime pattern of the input")),
+    ("from", ("zona de las entradas sin desfase", "zone for inputs without offset")),
+    ("tz", ("zona IANA o desfase como %2B02:00", "IANA zone or offset like %2B02:00")),
+    ("precision", ("s, ms, us o ns", "s, ms, us or ns")),
+    ("format", ("rfc3339, rfc2822, unix o patròn strftime", "rfc3339, rfc2822, unix or strftime pattern")),
+];
+const TIMESTAMP_DOC: [(&str, Translated); 3] = [DATE_DOC[3], DATE_DOC[4], DATE_DOC[5]];
+const DATECONVERT_DOC: [(&str, Translated); 6] = [DATE_DOC[0], DATE_DOC[1], DATE_DOC[2], ("to", ("zona de destino", "target zone")), DATE_DOC[4], DATE_DOC[5]];
+const DURATION_DOC: [(&str, Translated); 7] = [
+    DATE_DOC[0], DATE_DOC[1], DATE_DOC[2], DATE_DOC[3], DATE_DOC[4], DATE_DOC[5],
+    ("duration", ("por ejemplo 1d2h30m, 1mo, 90s", "for example 1d2h30m, 1mo, 90s")),
+];
+
+const FILE_DOC: &[(&str, Translated)] = &[("name", ("nombre del archivo", "file name"))];
+const VERSION_PARAMS: [Param; 2] = [req("name", Text), req("version", Integer)];
+const VERSION_DOC: [(&str, Translated); 2] = [("name", ("nombre del archivo", "file name")), ("version", ("nùmero de versiòn", "version number"))];
+
+// Todas las rutas que trae el servidor. Quien use la biblioteca puede agregar o reemplazar rutas sobre este router.
+// La documentaciòn de cada ruta alimenta /help y /openapi.json
+pub fn default_router() -> Router {
+    let mut router = Router::new();
+
+    router.get("/reverse", &[req("text", Text)], reverse)
+        .doc(Doc::new(("Invierte el texto recibido", "Reverses the given text"), &[("text", ("texto que se desea invertir", "text to reverse"))], "/reverse?text=abc"));
+    router.get("/toupper", &[req("text", Text)], toupper)
+        .doc(Doc::new(("Convierte el texto a mayúsculas", "Converts the text to uppercase"), TEXT_DOC, "/toupper?text=hola"));
+    for (route, params, doc) in TEXT_ROUTES {
+        router.get(route, params, text).doc(doc);
+    }
+
+    // Tareas que leen un texto, un archivo guardado o el cuerpo POST
+    for (route, params, doc, body_example) in INPUT_ROUTES {
+        let get = router.get(route, params, input_task).doc(doc.errors(&["not_found", "invalid_input"]));
+        // Con save= el resultado se escribe en un archivo, asì que HEAD no puede ejecutarla
+        if params.iter().any(|p| p.name == "save") {
+            get.mutating();
+        }
+        // En POST el texto llega en el cuerpo
+        let body_params: Vec<Param> = params.iter().copied().filter(|p| p.name != "text" && p.name != "name").collect();
+        let body_doc = Doc { params: &doc.params[2..], example: body_example, ..doc };
+        router.post(route, &body_params, body_task).doc(body_doc.errors(&["invalid_input", "length_required", "payload_too_large"]));
+    }
+
+    let compress_params = [req("name", Text), opt("format", Text), opt("level", Integer), opt("output", Text), opt("overwrite", Boolean)];
+    router.get("/compress", &compress_params, compression).mutating().doc(Doc::new(
+        ("Comprime un archivo guardado y reporta ratio y MB/s", "Compresses a stored file and reports ratio and MB/s"),
+        &[("name", ("archivo", "file")), ("format", ("gzip o deflate", "gzip or deflate")), ("level", ("0-9 (6)", "0-9 (6)")),
+          ("output", ("archivo de salida (nombre.gz por defecto)", "output file (name.gz by default)")), ("overwrite", ("true/false", "true/false"))],
+        "/compress?name=miarchivo&level=9",
+    ).errors(&["not_found", "conflict", "invalid_input"]));
+    router.get("/decompress", &compress_params, compression).mutating().doc(Doc::new(
+        ("Descomprime un archivo gzip o deflate guardado", "Decompresses a stored gzip or deflate file"),
+        &[("name", ("archivo comprimido", "compressed file")), ("format", ("gzip o deflate (segùn la extensiòn)", "gzip or deflate (from the extension)")),
+          ("level", ("se ignora", "ignored")), ("output", ("archivo de salida", "output file")), ("overwrite", ("true/false", "true/false"))],
+        "/decompress?name=miarchivo.gz&output=copia",
+    ).errors(&["not_found", "conflict", "invalid_input"]));
+
+    router.get("/fibonacci", &[req("num", Integer)], fibonacci)
+        .doc(Doc::new(("Calcula el n-ésimo número de Fibonacci (recursivo)", "Computes the n-th Fibonacci number (recursive)"), &[("num", ("número a calcular", "number to compute"))], "/fibonacci?num=10"));
+    for (route, doc) in BENCH_ROUTES {
+        router.get(route, &[req("size", Integer), opt("seed", Integer)], benchmark).doc(doc);
+    }
+    router.get("/calc", &[req("expr", Text)], calc).doc(Doc::new(
+        ("Evalùa una expresiòn aritmètica con constantes (pi, e, tau), funciones (sqrt, sin, log, min, max...) y variables, que se pasan como paràmetros (x=3)",
+         "Evaluates an arithmetic expression with constants (pi, e, tau), functions (sqrt, sin, log, min, max...) and variables, passed as parameters (x=3)"),
+        &[("expr", ("expresiòn", "expression"))],
+        "/calc?expr=2*x^2%2Bsqrt(y)&x=3&y=16",
+    ).errors(&["invalid_input"]));
+    router.get("/mandelbrot", &[
+        opt("width", Integer), opt("height", Integer), opt("iterations", Integer), opt("x_min", Number), opt("x_max", Number),
+        opt("y_min", Number), opt("y_max", Number), opt("format", Text), opt("tiles", Integer), opt("save", Text),
+    ], mandelbrot).raw().mutating().doc(Doc::new(
+        ("Renderiza el conjunto de Mandelbrot en PNG o PPM, opcionalmente en franjas repartidas en el pool", "Renders the Mandelbrot set as PNG or PPM, optionally split in strips across the pool"),
+        &[("width", ("ancho (800)", "width (800)")), ("height", ("alto (600)", "height (600)")), ("iterations", ("iteraciones (256)", "iterations (256)")),
+          ("x_min", ("ventana", "window")), ("x_max", ("ventana", "window")), ("y_min", ("ventana", "window")), ("y_max", ("ventana", "window")),
+          ("format", ("png o ppm", "png or ppm")), ("tiles", ("franjas en paralelo (1)", "parallel strips (1)")), ("save", ("archivo donde guardar la imagen", "file where the image is stored"))],
+        "/mandelbrot?width=1024&height=768&tiles=8",
+    ).errors(&["conflict"]).produces(&["image/png", "image/x-portable-pixmap"]));
+    router.get("/random", &[
+        opt("count", Integer), opt("min", Number), opt("max", Number), opt("seed", Integer), opt("type", Text), opt("dist", Text),
+        opt("mean", Number), opt("std_dev", Number), opt("lambda", Number), opt("kind", Text), opt("items", Text), opt("length", Integer), opt("charset", Text),
+    ], random).doc(Doc::new(
+        ("Genera valores aleatorios reproducibles con 'seed' (cantidades grandes se envìan por partes)", "Generates random values, reproducible with 'seed' (large amounts are streamed)"),
+        &[("count", ("cantidad", "amount")), ("min", ("mínimo", "minimum")), ("max", ("máximo", "maximum")), ("seed", ("semilla", "seed")),
+          ("type", ("int o float", "int or float")), ("dist", ("uniform, normal, exponential o poisson", "uniform, normal, exponential or poisson")),
+          ("mean", ("media de normal", "mean for normal")), ("std_dev", ("desviaciòn de normal", "standard deviation for normal")),
+          ("lambda", ("tasa de exponential y poisson", "rate for exponential and poisson")),
+          ("kind", ("numbers, sample (sin reemplazo), shuffle, string o uuid", "numbers, sample (without replacement), shuffle, string or uuid")),
+          ("items", ("valores separados por coma para shuffle", "comma separated values for shuffle")), ("length", ("largo de string", "length for string")),
+          ("charset", ("caracteres de string (alnum por defecto)", "characters for string (alnum by default)"))],
+        "/random?count=5&min=10&max=100&seed=42",
+    ));
+
+    router.get("/timestamp", &[opt("tz", Text), opt("precision", Text), opt("format", Text)], timestamp).doc(Doc::new(
+        ("Devuelve la hora actual (por defecto UTC en RFC 3339)", "Returns the current time (UTC in RFC 3339 by default)"),
+        &TIMESTAMP_DOC,
+        "/timestamp?tz=America/Costa_Rica&precision=ms",
+    ));
+    router.get("/dateparse", &DATE_PARAMS, date).doc(Doc::new(
+        ("Interpreta una fecha y la devuelve normalizada", "Parses a date and returns it normalized"),
+        &DATE_DOC, "/dateparse?input=2025-01-15 10:30&from=Europe/Madrid",
+    ));
+    router.get("/dateconvert", &[req("input", Text), opt("input_format", Text), opt("from", Text), req("to", Text), opt("precision", Text), opt("format", Text)], date).doc(Doc::new(
+        ("Convierte una fecha de una zona a otra", "Converts a date from one zone to another"),
+        &DATECONVERT_DOC,
+        "/dateconvert?input=2025-01-15 10:30&from=Europe/Madrid&to=Asia/Tokyo",
+    ));
+    router.get("/dateadd", &[&DATE_PARAMS[..], &[req("duration", Text)]].concat(), date).doc(Doc::new(
+        ("Suma una duraciòn a una fecha (o a la hora actual)", "Adds a duration to a date (or to the current time)"),
+        &DURATION_DOC, "/dateadd?input=2025-01-31T00:00:00Z&duration=1mo",
+    ));
+    router.get("/datesub", &[&DATE_PARAMS[..], &[req("duration", Text)]].concat(), date).doc(Doc::new(
+        ("Resta una duraciòn a una fecha (o a la hora actual)", "Subtracts a duration from a date (or from the current time)"),
+        &DURATION_DOC, "/datesub?duration=2w",
+    ));
+    router.get("/sleep", &[req("seconds", Integer)], sleep)
+        .doc(Doc::new(("Simula una espera bloqueante de N segundos", "Simulates a blocking wait of N seconds"), &[("seconds", ("segundos a esperar", "seconds to wait"))], "/sleep?seconds=3"));
+
+    // Rutas GET que modifican archivos o trabajos: se marcan para que HEAD no las ejecute
+    router.get("/createfile", &[req("name", Text), req("content", Text), opt("mode", Text)], createfile).mutating().doc(Doc::new(
+        ("Crea un archivo con el contenido indicado (escritura atòmica)", "Creates a file with the given content (atomic write)"),
+        &[("name", ("nombre del archivo", "file name")), ("content", ("contenido", "content")), ("mode", ("create (por defecto), overwrite o append", "create (default), overwrite or append"))],
+        "/createfile?name=miarchivo&content=hola&mode=append",
+    ).errors(&["conflict"]));
+    router.get("/deletefile", &[req("name", Text)], deletefile).mutating()
+        .doc(Doc::new(("Elimina un archivo existente", "Deletes an existing file"), FILE_DOC, "/deletefile?name=miarchivo").errors(&["not_found"]));
+    router.get("/listversions", &[req("name", Text)], listversions).doc(Doc::new(
+        ("Lista las versiones guardadas de un archivo (requiere FILE_VERSIONING=1)", "Lists the saved versions of a file (requires FILE_VERSIONING=1)"),
+        FILE_DOC, "/listversions?name=miarchivo",
+    ).errors(&["not_found"]));
+    router.get("/getversion", &VERSION_PARAMS, version)
+        .doc(Doc::new(("Devuelve el contenido de una versiòn", "Returns the content of a version"), &VERSION_DOC, "/getversion?name=miarchivo&version=1").errors(&["not_found"]));
+    router.get("/restoreversion", &VERSION_PARAMS, version).mutating().doc(Doc::new(
+        ("Restaura (o recupera si fue eliminado) un archivo a una versiòn", "Restores a file to a version (or recovers it if it was deleted)"),
+        &VERSION_DOC, "/restoreversion?name=miarchivo&version=1",
+    ).errors(&["not_found"]));
+    router.get("/purgeversions", &[req("name", Text), opt("keep", Integer), opt("older_than", Integer)], purgeversions).mutating().doc(Doc::new(
+        ("Elimina versiones viejas por cantidad o antigüedad", "Deletes old versions by count or age"),
+        &[("name", ("nombre del archivo", "file name")), ("keep", ("versiones a conservar", "versions to keep")), ("older_than", ("antigüedad en segundos", "age in seconds"))],
+        "/purgeversions?name=miarchivo&keep=3",
+    ).errors(&["not_found"]));
+    // Descargas y subidas directo al socket
+    router.get("/files/{name}", &[], files).raw().doc(Doc::new(
+        ("Descarga un archivo guardado con ETag y soporte de Range e If-None-Match", "Downloads a stored file with ETag and support for Range and If-None-Match"),
+        &[("name", ("nombre del archivo", "file name"))], "/files/notas.txt",
+    ).errors(&["invalid_parameter", "not_found"]));
+    router.route(Method::Put, "/files/{name}", &[], files).raw().doc(Doc::new(
+        ("Sube un archivo (el cuerpo, con Content-Length) y lo guarda de forma atòmica", "Uploads a file (the body, with Content-Length) and stores it atomically"),
+        &[("name", ("nombre del archivo", "file name"))], "/files/notas.txt",
+    ).errors(&["invalid_parameter", "length_required", "payload_too_large"]).produces(&["application/json"]));
+
+    router.get("/grep", &[
+        req("pattern", Text), opt("name", Text), opt("regex", Boolean), opt("ignore_case", Boolean), opt("context", Integer),
+        opt("max_matches", Integer), opt("max_bytes", Integer), opt("timeout_ms", Integer), opt("job", Text),
+    ], grep_route).doc(Doc::new(
+        ("Busca un texto o regex en los archivos guardados, con lìneas de contexto", "Searches stored files for a text or regex, with context lines"),
+        &[("pattern", ("texto a buscar", "text to search for")), ("name", ("archivo (todos si falta)", "file (all of them if missing)")),
+          ("regex", ("true/false", "true/false")), ("ignore_case", ("true/false", "true/false")), ("context", ("lìneas de contexto", "context lines")),
+          ("max_matches", ("coincidencias màximas", "maximum matches")), ("max_bytes", ("bytes màximos a revisar", "maximum bytes to scan")),
+          ("timeout_ms", ("tiempo màximo", "time limit")), ("job", ("id para /cancel", "id for /cancel"))],
+        "/grep?pattern=hola&context=1&job=b1",
+    ).errors(&["not_found", "cancelled", "timeout"]));
+    router.get("/cancel", &[req("job", Text)], cancel_route).mutating()
+        .doc(Doc::new(("Cancela un trabajo en curso por su id", "Cancels a running job by its id"), &[("job", ("id del trabajo", "job id"))], "/cancel?job=b1").errors(&["not_found"]));
+    router.route(Method::Delete, "/jobs/{id}", &[], delete_job)
+        .doc(Doc::new(("Igual que /cancel?job={id}", "Same as /cancel?job={id}"), &[("id", ("id del trabajo", "job id"))], "/jobs/b1").errors(&["not_found"]));
+
+    router.get("/simulate", &[req("seconds", Integer), req("task", Text)], simulate).mutating().doc(Doc::new(
+        ("Simula un endpoint como reverse, toupper, etc., con retardo; los demàs paràmetros son los de la tarea", "Simulates an endpoint such as reverse, toupper, etc., with a delay; the other parameters are the task's"),
+        &[("seconds", ("retardo", "delay")), ("task", ("nombre del endpoint interno", "name of the inner endpoint"))],
+        "/simulate?seconds=2&task=reverse&text=hola",
+    ));
+    router.get("/loadtest", &[opt("task", Text), opt("count", Integer), opt("text", Text)], loadtest).doc(Doc::new(
+        ("Encola múltiples tareas para medir carga del sistema (compress y decompress usan name; las de CPU, size)", "Enqueues many tasks to measure system load (compress and decompress use name; CPU tasks, size)"),
+        &[("task", ("reverse, toupper, sha256, timestamp, tareas de texto, compress, decompress, primes, factorize, matrix, montecarlo, sort o collatz",
+                    "reverse, toupper, sha256, timestamp, text tasks, compress, decompress, primes, factorize, matrix, montecarlo, sort or collatz")),
+          ("count", ("cuántas tareas", "how many tasks")), ("text", ("valor base si aplica", "base value if applicable"))],
+        "/loadtest?task=reverse&count=5&text=hola",
+    ));
+    router.get("/help", &[], help_route)
+        .doc(Doc::new(("Devuelve este manual de uso de endpoints", "Returns this endpoint manual"), &[], "/help"));
+    router.get("/openapi.json", &[], openapi_route).raw()
+        .doc(Doc::new(("Documento OpenAPI 3 de todas las rutas", "OpenAPI 3 document of every route"), &[], "/openapi.json").produces(&["application/json"]));
+    router.get("/status", &[], status_route)
+        .doc(Doc::new(("Estado del proceso (pid, memoria, CPU, hilos, descriptores, carga), uptime, conexiones, workers y estadìsticas por ruta y tarea del ùltimo minuto", "Process state (pid, memory, CPU, threads, descriptors, load), uptime, connections, workers and per route and task statistics for the last minute"), &[], "/status"));
+    router.get("/status/system", &[], system_route)
+        .doc(Doc::new(("CPUs, memoria, carga y uptime del equipo", "CPUs, memory, load and uptime of the host"), &[], "/status/system"));
+    router.route(Method::Delete, "/admin/stats", &[], reset_stats)
+        .doc(Doc::new(("Reinicia las estadìsticas de la ventana de /status (no las de /metrics)", "Resets the /status window statistics (not /metrics)"), &[], "/admin/stats"));
+    router.get("/metrics", &[], metrics_route).raw()
+        .doc(Doc::new(("Mètricas en el formato de texto de Prometheus", "Metrics in the Prometheus text format"), &[], "/metrics").produces(&[METRICS_CONTENT_TYPE]));
+
+    router
+}
+
+fn fibonacci(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(n_str) = params.get("num") {
+        match n_str.parse::<u64>() {
+            Ok(n) => {
+                enqueue_and_reply(sender, TaskType::Fibonacci(n), &format!("Fibonacci para {}", n))
+            }
+            Err(_) => ApiError::not_positive_integer("num").into(),
+        }
+    } else {
+        ApiError::missing("num").into()
+    }
+}
+
+fn reverse(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(text) = params.get("text") {
+       enqueue_and_reply(sender, TaskType::Reverse(text.clone()), &format!("Reverse de {}", text))
+    } else {
+        ApiError::missing("text").into()
+    }
+}
+
+fn toupper(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(text) = params.get("text") {
+        enqueue_and_reply(sender, TaskType::Toupper(text.clone()), &format!("Touper de {}", text))
+    } else {
+        ApiError::missing("text").into()
+    }
+}
+
+fn text(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    match parse_text_request(&route[1..], params) {
+        Some(Ok((ops, text))) => enqueue_and_reply(sender, TaskType::Text { ops, text }, &format!("Texto {}", &route[1..])),
+        Some(Err(error)) => error.into(),
+        None => ApiError::RouteNotFound { path: route.clone() }.into(),
+    }
+}
+
+fn input_task(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    let input = match (params.get("text"), params.get("name")) {
+        (Some(text), _) => InputSource::Inline(text.clone()),
+        (None, Some(name)) => InputSource::Stored(name.clone()),
+        (None, None) => return ApiError::MissingParameter { field: "text".into(), message: Message::key("missing_input") }.into(),
+    };
+    let response = input_task_reply(route, params, input, sender);
+    raw_csv_output(ctx, response)
+}
+
+// La salida de /csv ya es CSV: si el cliente la pide en CSV se entrega tal cual en lugar de convertir el sobre
+fn raw_csv_output(ctx: &Context, response: Response) -> Response {
+    let csv_output = ctx.path == "/csv" && ctx.format == Format::Csv && ctx.params.get("format").is_none_or(|f| f == "csv");
+    if csv_output { csv_passthrough(response) } else { response }
+}
+
+fn benchmark(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    match parse_benchmark(&route[1..], params) {
+        Some(Ok(benchmark)) => enqueue_and_reply(sender, TaskType::Bench(benchmark), &format!("Benchmark {:?}", benchmark)),
+        Some(Err(error)) => error.into(),
+        None => ApiError::RouteNotFound { path: route.clone() }.into(),
+    }
+}
+
+fn compression(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    match parse_compress_params(params, route == "/decompress", true) {
+        Ok(spec) => {
+            let desc = format!("{} {}", &route[1..], spec.name);
+            enqueue_and_reply(sender, TaskType::Compress(spec), &desc)
+        }
+        Err(error) => error.into(),
+    }
+}
+
+fn calc(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let expr = match params.get("expr") {
+        Some(expr) => expr.clone(),
+        None => return ApiError::missing("expr").into(),
+    };
+    match parse_variables(params) {
+        Ok(variables) => enqueue_and_reply(sender, TaskType::Calc { expr, variables }, "Calcular expresiòn"),
+        Err(error) => error.into(),
+    }
+}
+
+fn sleep(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(sec) = params.get("seconds") {
+        match sec.parse::<u64>() {
+            Ok(seconds) => {
+                return enqueue_and_reply(sender, TaskType::Sleep(seconds), &format!("Simulaciòn por {} segundos", sec));
+            }
+            Err(_) => {
+                return ApiError::not_positive_integer("seconds").into();
+            }
+        }
+    }
+    ApiError::missing("seconds").into()
+}
+
+fn timestamp(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if params.is_empty() {
+        return enqueue_and_reply(sender, TaskType::TimeStamp, "TimeStamp actual en formato Iso");
+    }
+
+    match parse_time_format(params, "tz") {
+        Ok(output) => enqueue_and_reply(sender, TaskType::Date(DateRequest { op: DateOp::Now, from: Zone::Utc, output }), "TimeStamp actual con formato"),
+        Err(error) => error.into(),
+    }
+}
+
+fn date(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    let zone_key = if route == "/dateconvert" { "to" } else { "tz" };
+    let output = match parse_time_format(params, zone_key) {
+        Ok(output) => output,
+        Err(error) => return error.into(),
+    };
+
+    let from = match params.get("from").map(|z| parse_zone(z)) {
+        Some(Ok(zone)) => zone,
+        Some(Err(msg)) => return ApiError::invalid("from", msg).into(),
+        None => Zone::Utc,
+    };
+
+    let input = params.get("input").cloned();
+    let input_format = params.get("input_format").cloned();
+
+    let op = match route.as_str() {
+        "/dateadd" | "/datesub" => {
+            let mut duration = match params.get("duration").map(|d| parse_duration(d)) {
+                Some(Ok(duration)) => duration,
+                Some(Err(msg)) => return ApiError::invalid("duration", msg).into(),
+                None => return ApiError::missing("duration").into(),
+            };
+            if route == "/datesub" {
+                duration.negative = !duration.negative;
+            }
+            DateOp::Add { input, input_format, duration }
+        }
+        _ => match input {
+            Some(input) => DateOp::Parse { input, input_format },
+            None => return ApiError::missing("input").into(),
+        },
+    };
+
+    if route == "/dateconvert" && !params.contains_key("to") {
+        return ApiError::missing("to").into();
+    }
+
+    enqueue_and_reply(sender, TaskType::Date(DateRequest { op, from, output }), &format!("Fecha {}", route))
+}
+
+fn random(ctx: &mut Context) -> Reply {
+    match parse_random_params(&ctx.params) {
+        // Las cantidades grandes se envìan por partes directo al socket (solo en JSON;
+        // en los demàs formatos se juntan y se convierten como cualquier respuesta)
+        Ok(spec) if spec.streams() && ctx.format == Format::Json && ctx.method == Method::Get => {
+            stream_task(&mut ctx.stream, &ctx.state.sender, TaskType::Random(spec), "Generar números aleatorios");
+            Reply::Written
+        }
+        Ok(spec) => enqueue_and_reply(&ctx.state.sender, TaskType::Random(spec), "Generar números aleatorios").into(),
+        Err(error) => error.into(),
+    }
+}
+
+fn createfile(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let mode = match params.get("mode") {
+        Some(m) => match WriteMode::parse(m) {
+            Some(mode) => mode,
+            None => return ApiError::invalid("mode", Message::key("invalid_mode")).into(),
+        },
+        None => WriteMode::Create,
+    };
+
+    if let (Some(name), Some(content)) = (params.get("name"), params.get("content")) {
+        return enqueue_and_reply(sender, TaskType::CreateFile { name: name.clone(), content: content.clone(), mode }, &format!("Crear archivo '{}'", name));
+    }
+    ApiError::missing(if params.contains_key("name") { "content" } else { "name" }).into()
+}
+
+fn deletefile(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(name) = params.get("name") {
+        return enqueue_and_reply(sender, TaskType::DeleteFile(name.clone()), &format!("Eliminar archivo '{}'", name));
+    }
+    ApiError::missing("name").into()
+}
+
+fn listversions(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    if let Some(name) = params.get("name") {
+        return enqueue_and_reply(sender, TaskType::ListVersions(name.clone()), &format!("Listar versiones de '{}'", name));
+    }
+    ApiError::missing("name").into()
+}
+
+fn version(ctx: &mut Context) -> Response {
+    let (params, sender, route) = (&ctx.params, &ctx.state.sender, &ctx.path);
+    let name = match params.get("name") {
+        Some(n) => n.clone(),
+        None => return ApiError::missing("name").into(),
+    };
+
+    let version = match params.get("version").map(|v| v.parse::<u64>()) {
+        Some(Ok(v)) => v,
+        Some(Err(_)) => return ApiError::not_positive_integer("version").into(),
+        None => return ApiError::missing("version").into(),
+    };
+
+    if route == "/getversion" {
+        enqueue_and_reply(sender, TaskType::GetVersion { name: name.clone(), version }, &format!("Leer versiòn {} de '{}'", version, name))
+    } else {
+        enqueue_and_reply(sender, TaskType::RestoreVersion { name: name.clone(), version }, &format!("Restaurar versiòn {} de '{}'", version, name))
+    }
+}
+
+fn purgeversions(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let name = match params.get("name") {
+        Some(n) => n.clone(),
+        None => return ApiError::missing("name").into(),
+    };
+
+    let keep = match params.get("keep").map(|v| v.parse::<usize>()) {
+        Some(Ok(k)) => Some(k),
+        Some(Err(_)) => return ApiError::not_positive_integer("keep").into(),
+        None => None,
+    };
+
+    let older_than = match params.get("older_than").map(|v| v.parse::<u64>()) {
+        Some(Ok(o)) => Some(o),
+        Some(Err(_)) => return ApiError::not_positive_integer("older_than").into(),
+        None => None,
+    };
+
+    if keep.is_none() && older_than.is_none() {
+        return ApiError::MissingParameter { field: "keep".into(), message: Message::key("missing_keep_or_older_than") }.into();
+    }
+
+    enqueue_and_reply(sender, TaskType::PurgeVersions { name: name.clone(), keep, older_than }, &format!("Purgar versiones de '{}'", name))
+}
+
+fn grep_route(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let pattern = match params.get("pattern") {
+        Some(p) => p.clone(),
+        None => return ApiError::missing("pattern").into(),
+    };
+
+    let flag = |key: &str| params.get(key).is_some_and(|v| v == "true" || v == "1");
+    let number = |key: &str, default: u64| -> Result<u64, ApiError> {
+        match params.get(key) {
+            Some(v) => v.parse::<u64>().map_err(|_| ApiError::not_positive_integer(key)),
+            None => Ok(default),
+        }
+    };
+
+    let defaults = GrepOptions::default();
+    let (context, max_matches, max_bytes, timeout_ms) = match (number("context", 0), number("max_matches", defaults.max_matches as u64), number("max_bytes", defaults.max_bytes), number("timeout_ms", 0)) {
+        (Ok(c), Ok(m), Ok(b), Ok(t)) => (c as usize, m as usize, b, t),
+        (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => return e.into(),
+    };
+
+    let options = GrepOptions {
+        regex: flag("regex"),
+        ignore_case: flag("ignore_case"),
+        context: context.min(MAX_CONTEXT),
+        max_matches: max_matches.min(MAX_MATCHES),
+        max_bytes,
+        deadline: if timeout_ms > 0 { Some(Instant::now() + Duration::from_millis(timeout_ms)) } else { None },
+    };
+
+    let job = params.get("job").cloned();
+    let cancel = match &job {
+        Some(id) => match register_job(id) {
+            Some(token) => token,
+            None => return ApiError::conflict(Some("job"), Message::key("job_exists").arg("job", id)).into(),
+        },
+        None => CancelToken::new(),
+    };
+
+    let task = TaskType::Grep { pattern: pattern.clone(), name: params.get("name").cloned(), options, cancel };
+    let response = enqueue_and_reply(sender, task, &format!("Grep de '{}'", pattern));
+    if let Some(id) = job {
+        finish_job(&id);
+    }
+    response
+}
+
+fn cancel_route(ctx: &mut Context) -> Response {
+    let params = &ctx.params;
+    match params.get("job") {
+        Some(id) if cancel_job(id) => Response::ok(&Message::key("job_cancelled").arg("job", id).to_string()),
+        Some(id) => ApiError::not_found(Some("job"), Message::key("job_not_found").arg("job", id)).into(),
+        None => ApiError::missing("job").into(),
+    }
+}
+
+fn simulate(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let delay = match params.get("seconds") {
+        Some(s) => match s.parse::<u64>() {
+            Ok(val) => val,
+            Err(_) => return ApiError::not_positive_integer("seconds").into()
+        },
+        None => return ApiError::missing("seconds").into(),
+    };
+
+    let task = match params.get("task") {
+        Some(t) => t.as_str(),
+        None => return ApiError::missing("task").into(),
+    };
+
+    match task {
+        "reverse" => {
+            if let Some(text) = params.get("text") {
+                let inner = TaskType::Reverse(text.clone());
+                enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simular reverse")
+             } else {
+                ApiError::missing("text").into()
+             }
+        }
+
+        "toupper" => {
+            if let Some(text) = params.get("text") {
+                let inner = TaskType::Toupper(text.clone());
+                enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate toupper")
+            } else {
+                ApiError::missing("text").into()
+            }
+        }
+
+        "fibonacci" => {
+            if let Some(n_str) = params.get("num") {
+                match n_str.parse::<u64>() {
+                    Ok(n) => {
+                        let inner = TaskType::Fibonacci(n);
+                        enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate fibonacci")
+                    }
+                    Err(_e) => ApiError::not_positive_integer("num").into(),
+                }
+            } else {
+                ApiError::missing("num").into()
+            }
+        }
+
+        "hash" => {
+            if let Some(text) = params.get("text") {
+                let inner = TaskType::Sha256(text.clone());
+                enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate hash")
+            } else {
+                ApiError::missing("text").into()
+            }
+        }
+
+        "timestamp" => {
+            let inner = TaskType::TimeStamp;
+            enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate timestamp")
+        }
+        
+        "random" => {
+            match parse_random_params(params) {
+                Ok(spec) if spec.streams() => ApiError::invalid("count", Message::key("simulate_random_limit").arg("max", STREAM_THRESHOLD)).into(),
+                Ok(spec) => {
+                    let inner = TaskType::Random(spec);
+                    enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate random")
+                }
+                Err(error) => error.into(),
+            }
+        }
+
+        "createfile" => {
+            let mode = match params.get("mode") {
+                Some(m) => match WriteMode::parse(m) {
+                    Some(mode) => mode,
+                    None => return ApiError::invalid("mode", Message::key("invalid_mode")).into(),
+                },
+                None => WriteMode::Create,
+            };
+
+            if let (Some(name), Some(content)) = (params.get("name"), params.get("content")) {
+                let inner = TaskType::CreateFile { name: name.clone(), content: content.clone(), mode };
+                return enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate createfile");
+            }
+            ApiError::missing(if params.contains_key("name") { "content" } else { "name" }).into()
+        }
+
+        "deletefile" => {
+            if let Some(name) = params.get("name") {
+                let inner = TaskType::DeleteFile(name.clone());
+                return enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, "Simulate deletefile");
+            }
+            ApiError::missing("name").into()
+        }
+        _ => {
+            let inner = match (parse_text_request(task, params), parse_benchmark(task, params)) {
+                (Some(Ok((ops, text))), _) => TaskType::Text { ops, text },
+                (_, Some(Ok(benchmark))) => TaskType::Bench(benchmark),
+                (Some(Err(error)), _) | (_, Some(Err(error))) => return error.into(),
+                (None, None) => return ApiError::invalid("task", Message::key("unsupported_task").arg("route", "simulate")).into(),
+            };
+            enqueue_and_reply(sender, TaskType::Simulate { delay, inner: Box::new(inner) }, &format!("Simulate {}", task))
+        }
+    }
+}
+
+fn loadtest(ctx: &mut Context) -> Response {
+    let (params, sender) = (&ctx.params, &ctx.state.sender);
+    let count = params.get("count").and_then(|v| v.parse::<usize>().ok()).unwrap_or(10);
+    let task_name = params.get("task").map(|s| s.as_str()).unwrap_or("default");
+    let text = params.get("text").cloned().unwrap_or_else(|| "default".to_string());
+
+    let task_type_template = match task_name {
+        "reverse" => TaskType::Reverse(text),
+        "toupper" => TaskType::Toupper(text),
+        "sha256" => TaskType::Sha256(text),
+        "timestamp" => TaskType::TimeStamp,
+        // Carga real de CPU: se comprime el archivo y el resultado se descarta
+        "compress" | "decompress" => match parse_compress_params(params, task_name == "decompress", false) {
+            Ok(spec) => TaskType::Compress(spec),
+            Err(error) => return error.into(),
+        },
+        _ => match (parse_text_request(task_name, params), parse_benchmark(task_name, params)) {
+            (Some(Ok((ops, _))), _) => TaskType::Text { ops, text },
+            (_, Some(Ok(benchmark))) => TaskType::Bench(benchmark),
+            (Some(Err(error)), _) | (_, Some(Err(error))) => return error.into(),
+            (None, None) => return ApiError::invalid("task", Message::key("unsupported_task").arg("route", "loadtest")).into(),
+        },
+    };
+
+    let start = Instant::now();
+    let mut receivers = Vec::with_capacity(count);
+
+    //Vamos a encolar las tareas
+    for _ in 0..count {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : format!("Loadtest para {}", task_name),
+            task_type : task_type_template.clone(),
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        if let Err(error) = submit(sender, task) {
+            return error.into();
+        }
+        receivers.push(rx);
+    }
+
+    //Aquì en esta secciòn esperamos todas las respuestas
+    let mut results = Vec::new();
+    for rx in receivers {
+        match rx.recv() {
+            Ok(Ok(res)) => results.push(res.to_string()),
+            Ok(Err(error)) => results.push(format!("ERROR {}: {}", error.code(), error)),
+            Err(_) => results.push(Message::key("reply_error").to_string()),
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let sample = results.iter().take(5).cloned().collect::<Vec<_>>();
+
+    Response::ok_json(json!({"task": task_name, "total_tasks": count, "duration_ms": elapsed.as_millis() as u64, "results_sample": sample}))
+}
+
+// El manual y el documento OpenAPI se arman con la tabla de rutas, sin pasar por los workers
+fn help_route(ctx: &mut Context) -> Response {
+    Response::ok_json(help_json(ctx.router, current_lang()))
+}
+
+// El documento va sin el sobre {"status","message"}, como lo esperan las herramientas de OpenAPI
+fn openapi_route(ctx: &mut Context) -> Response {
+    Response::new(Status::Ok).json(&openapi_json(ctx.router, current_lang()))
+}
+
+// Prometheus lee el texto tal cual: sin sobre y sin conversiòn de formato
+fn metrics_route(ctx: &mut Context) -> Response {
+    let workers = match ctx.state.worker_states.lock() {
+        Ok(workers) => workers.clone(),
+        Err(_) => return ApiError::internal(Message::key("workers_unavailable")).into(),
+    };
+    let snapshot = Snapshot {
+        workers: &workers,
+        active_connections: ctx.state.active.load(Ordering::Relaxed),
+        total_connections: ctx.state.connections.load(Ordering::Relaxed),
+        uptime: ctx.state.start_time.elapsed(),
+    };
+    Response::new(Status::Ok).body(METRICS_CONTENT_TYPE, render(&METRICS, &snapshot))
+}
+
+fn status_route(ctx: &mut Context) -> Response {
+    let uptime = ctx.state.start_time.elapsed().as_secs();
+
+    let worker_states_clone = match ctx.state.worker_states.lock() {
+        Ok(ws) => ws.clone(),
+        Err(_) => return ApiError::internal(Message::key("workers_unavailable")).into(),
+    };
+
+    let workers_json : Vec<Value> = worker_states_clone.iter().map(|w| {
+        json!({"id": w.id, "status": Message::key(if w.busy {"worker_busy"} else {"worker_idle"}).to_string(), "description": w.description})
+    }).collect();
+
+    // Sin /proc la secciòn `process` queda en null, pero el pid sale igual
+    let process = ProcessInfo::read();
+    Response::ok_json(json!({
+        "pid": process::id(),
+        "uptime_secs": uptime,
+        "conexiones": ctx.state.connections.load(Ordering::Relaxed),
+        "process": process.map(|info| info.to_json()),
+        "workers": workers_json,
+        "stats": STATS.summary(),
+    }))
+}
+
+fn system_route(_ctx: &mut Context) -> Response {
+    Response::ok_json(SystemInfo::read().to_json())
+}
+
+fn reset_stats(_ctx: &mut Context) -> Response {
+    STATS.reset();
+    Response::ok(&Message::key("stats_reset").to_string())
+}
+
+// POST a /textstats, /encode, /decode, /hash, /csv o /json: el cuerpo se copia a un temporal y el worker lo lee por bloques
+fn body_task(ctx: &mut Context) -> Response {
+    match stage_body(&mut ctx.stream, ctx.request) {
+        Ok(body) => {
+            let response = input_task_reply(&ctx.path, &ctx.params, InputSource::Body(Arc::new(body)), &ctx.state.sender);
+            raw_csv_output(ctx, response)
+        }
+        Err(error) => error.into(),
+    }
+}
+
+// Tareas que leen un texto, un archivo guardado o el cuerpo de la solicitud
+fn input_task_reply(route: &str, params: &HashMap<String, String>, input: InputSource, sender: &Sender<Task>) -> Response {
+    let task = match route {
+        "/textstats" => parse_stats_params(params).map(|options| (TaskType::TextStats { input: input.clone(), options }, "Estadìsticas de texto")),
+        "/csv" => parse_csv_params(params).map(|spec| (TaskType::Csv { input: input.clone(), spec }, "Operaciones CSV")),
+        "/json" => parse_json_params(params).map(|spec| (TaskType::Json { input: input.clone(), spec }, "Operaciones JSON")),
+        "/encode" | "/decode" => {
+            let (key, decode) = if route == "/encode" { ("to", false) } else { ("from", true) };
+            match params.get(key) {
+                Some(names) => parse_steps(names, decode).map_err(|e| ApiError::invalid(key, e))
+                    .and_then(|steps| parse_output(params).map(|output| (steps, output)))
+                    .map(|(steps, output)| (TaskType::Codec { input: input.clone(), steps, output }, if decode { "Decodificar" } else { "Codificar" })),
+                None => Err(ApiError::missing(key)),
+            }
+        }
+        _ => match (&input, params.get("decode")) {
+            (InputSource::Inline(text), None) => Ok((TaskType::Sha256(text.clone()), "Sha256_hash")),
+            (_, decode) => decode.map(|names| parse_steps(names, true).map_err(|e| ApiError::invalid("decode", e))).unwrap_or(Ok(Vec::new()))
+                .map(|steps| (TaskType::Codec { input: input.clone(), steps, output: CodecOutput::Sha256 }, "Sha256_hash")),
+        },
+    };
+
+    match task {
+        Ok((task_type, desc)) => enqueue_and_reply(sender, task_type, desc),
+        Err(error) => error.into(),
+    }
+}
+
+// Las imàgenes son binarias: se escriben directo al socket
+fn mandelbrot(ctx: &mut Context) {
+    if let Err(e) = handle_mandelbrot(&mut ctx.stream, &ctx.state.sender, &ctx.params) {
+        eprintln!("Error en /mandelbrot: {}", e);
+    }
+}
+
+fn files(ctx: &mut Context) {
+    handle_file_request(&mut ctx.stream, ctx.request, &ctx.path_params["name"]);
+}
+
+// DELETE /jobs/{id}: igual que /cancel?job={id}
+fn delete_job(ctx: &mut Context) -> Response {
+    let id = &ctx.path_params["id"];
+    if cancel_job(id) {
+        Response::ok(&Message::key("job_cancelled").arg("job", id).to_string())
+    } else {
+        ApiError::not_found(Some("id"), Message::key("job_not_found").arg("job", id)).into()
+    }
+}
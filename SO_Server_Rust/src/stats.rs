@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+// Estadìsticas de /status en una ventana mòvil: cantidad, tasa de error y percentiles de latencia
+// por ruta y por tipo de tarea. A diferencia de /metrics (contadores desde el arranque), solo cuentan
+// los ùltimos WINDOW_SECS segundos y se pueden reiniciar con DELETE /admin/stats
+
+// La ventana se divide en ranuras; al avanzar el reloj la ranura màs vieja se vacìa y se reutiliza
+const SLOT_SECS: u64 = 10;
+const SLOTS: usize = 6;
+pub const WINDOW_SECS: u64 = SLOT_SECS * SLOTS as u64;
+
+// Cubetas logarìtmicas: la cubeta i cubre hasta GROWTH^i microsegundos (de 1 µs a unos 25 minutos).
+// El percentil se reporta como el lìmite de su cubeta, con a lo sumo un 25% de error
+const GROWTH: f64 = 1.25;
+const BUCKETS: usize = 96;
+
+fn bucket_of(micros: u64) -> usize {
+    if micros <= 1 {
+        return 0;
+    }
+    ((micros as f64).ln() / GROWTH.ln()).ceil().min((BUCKETS - 1) as f64) as usize
+}
+
+fn bucket_limit(bucket: usize) -> u64 {
+    GROWTH.powi(bucket as i32).round() as u64
+}
+
+// Histograma de tamaño fijo: ocupa lo mismo con una observaciòn que con millones
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    counts: [u32; BUCKETS],
+    count: u64,
+    max_micros: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram { counts: [0; BUCKETS], count: 0, max_micros: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = &mut self.counts[bucket_of(micros)];
+        *bucket = bucket.saturating_add(1);
+        self.count += 1;
+        self.max_micros = self.max_micros.max(micros);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (mine, theirs) in self.counts.iter_mut().zip(other.counts) {
+            *mine = mine.saturating_add(theirs);
+        }
+        self.count += other.count;
+        self.max_micros = self.max_micros.max(other.max_micros);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    // `q` entre 0 y 1. Nunca pasa del màximo observado
+    pub fn percentile(&self, q: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, n) in self.counts.iter().enumerate() {
+            seen += u64::from(*n);
+            // La ùltima cubeta no tiene lìmite: junta todo lo que pasa del anterior
+            if seen >= rank && bucket < BUCKETS - 1 {
+                return Duration::from_micros(bucket_limit(bucket).min(self.max_micros));
+            }
+        }
+        self.max()
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros)
+    }
+
+    fn summary(&self) -> Value {
+        let ms = |d: Duration| (d.as_secs_f64() * 1000.0 * 1000.0).round() / 1000.0;
+        json!({"p50": ms(self.percentile(0.5)), "p90": ms(self.percentile(0.9)), "p99": ms(self.percentile(0.99)), "max": ms(self.max())})
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    // Nùmero de ranura desde el arranque; si no coincide con la actual, la ranura es vieja
+    index: u64,
+    errors: u64,
+    // Duraciòn total (la solicitud o la ejecuciòn de la tarea) y espera en la cola (solo tareas)
+    latency: LatencyHistogram,
+    wait: LatencyHistogram,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Window {
+    slots: Vec<Slot>,
+}
+
+impl Window {
+    fn slot(&mut self, index: u64) -> &mut Slot {
+        if self.slots.is_empty() {
+            self.slots = vec![Slot::default(); SLOTS];
+        }
+        let slot = &mut self.slots[(index % SLOTS as u64) as usize];
+        if slot.index != index || slot.latency.count() == 0 {
+            *slot = Slot { index, ..Slot::default() };
+        }
+        slot
+    }
+
+    pub fn record(&mut self, index: u64, ok: bool, latency: Duration, wait: Option<Duration>) {
+        let slot = self.slot(index);
+        slot.errors += u64::from(!ok);
+        slot.latency.record(latency);
+        if let Some(wait) = wait {
+            slot.wait.record(wait);
+        }
+    }
+
+    // Junta las ranuras que siguen dentro de la ventana que termina en `index`
+    pub fn collect(&self, index: u64) -> (u64, LatencyHistogram, LatencyHistogram) {
+        let mut errors = 0;
+        let (mut latency, mut wait) = (LatencyHistogram::default(), LatencyHistogram::default());
+        for slot in self.slots.iter().filter(|s| s.index + (SLOTS as u64) > index && s.index <= index) {
+            errors += slot.errors;
+            latency.merge(&slot.latency);
+            wait.merge(&slot.wait);
+        }
+        (errors, latency, wait)
+    }
+}
+
+pub struct Stats {
+    // Clave "MÈTODO /patròn" (la misma agrupaciòn de /metrics)
+    routes: Mutex<BTreeMap<String, Window>>,
+    tasks: Mutex<BTreeMap<&'static str, Window>>,
+    clock: OnceLock<Instant>,
+}
+
+pub static STATS: Stats = Stats::new();
+
+impl Stats {
+    pub const fn new() -> Stats {
+        Stats { routes: Mutex::new(BTreeMap::new()), tasks: Mutex::new(BTreeMap::new()), clock: OnceLock::new() }
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.get_or_init(Instant::now).elapsed().as_secs() / SLOT_SECS
+    }
+
+    // Se cuentan como error las respuestas 4xx y 5xx
+    pub fn record_route(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let now = self.now();
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.entry(format!("{} {}", method, route)).or_default().record(now, status < 400, elapsed, None);
+    }
+
+    pub fn record_task(&self, task: &'static str, ok: bool, wait: Duration, exec: Duration) {
+        let now = self.now();
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.entry(task).or_default().record(now, ok, exec, Some(wait));
+    }
+
+    pub fn reset(&self) {
+        self.routes.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    // Resumen para /status. Las claves sin actividad en la ventana no aparecen
+    pub fn summary(&self) -> Value {
+        let now = self.now();
+        let entry = |errors: u64, latency: &LatencyHistogram| {
+            json!({"count": latency.count(), "errors": errors, "error_rate": errors as f64 / latency.count() as f64})
+        };
+
+        let mut routes = serde_json::Map::new();
+        for (key, window) in self.routes.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let (errors, latency, _) = window.collect(now);
+            if latency.count() > 0 {
+                let mut route = entry(errors, &latency);
+                route["latency_ms"] = latency.summary();
+                routes.insert(key.clone(), route);
+            }
+        }
+        let mut tasks = serde_json::Map::new();
+        for (name, window) in self.tasks.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let (errors, exec, wait) = window.collect(now);
+            if exec.count() > 0 {
+                let mut task = entry(errors, &exec);
+                task["queue_wait_ms"] = wait.summary();
+                task["exec_ms"] = exec.summary();
+                tasks.insert(name.to_string(), task);
+            }
+        }
+        json!({"window_secs": WINDOW_SECS, "routes": routes, "tasks": tasks})
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{LatencyHistogram, Stats, Window, BUCKETS, SLOTS};
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(0.5), Duration::ZERO);
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        // Cada percentil cae a menos de un 25% del valor real
+        for (q, real) in [(0.5, 50.0), (0.9, 90.0), (0.99, 99.0)] {
+            let got = histogram.percentile(q).as_secs_f64() * 1000.0;
+            assert!(got >= real && got <= real * 1.25, "p{} = {}", q * 100.0, got);
+        }
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+
+        // Duraciones enormes van a la ùltima cubeta sin crecer el histograma
+        histogram.record(Duration::from_secs(100_000));
+        assert_eq!(histogram.counts.len(), BUCKETS);
+        assert_eq!(histogram.percentile(1.0), Duration::from_secs(100_000));
+    }
+
+    #[test]
+    fn test_window_forgets_old_slots() {
+        let mut window = Window::default();
+        window.record(0, true, Duration::from_millis(5), None);
+        window.record(0, false, Duration::from_millis(7), None);
+        window.record(3, true, Duration::from_millis(1), Some(Duration::from_millis(2)));
+
+        let (errors, latency, wait) = window.collect(3);
+        assert_eq!((errors, latency.count(), wait.count()), (1, 3, 1));
+
+        // La ranura 0 sale de la ventana y la ranura SLOTS ocupa su lugar sin mezclarse
+        let (errors, latency, _) = window.collect(SLOTS as u64);
+        assert_eq!((errors, latency.count()), (0, 1));
+        window.record(SLOTS as u64, true, Duration::from_millis(1), None);
+        assert_eq!(window.collect(SLOTS as u64).1.count(), 2);
+        assert_eq!(window.slots.len(), SLOTS);
+    }
+
+    #[test]
+    fn test_summary_and_reset() {
+        let stats = Stats::new();
+        stats.record_route("GET", "/reverse", 200, Duration::from_millis(2));
+        stats.record_route("GET", "/reverse", 400, Duration::from_millis(4));
+        stats.record_task("reverse", true, Duration::from_millis(1), Duration::from_millis(3));
+
+        let summary = stats.summary();
+        let route = &summary["routes"]["GET /reverse"];
+        assert_eq!((route["count"].as_u64(), route["errors"].as_u64(), route["error_rate"].as_f64()), (Some(2), Some(1), Some(0.5)));
+        assert_eq!(route["latency_ms"]["max"], 4.0);
+        assert_eq!(summary["tasks"]["reverse"]["queue_wait_ms"]["max"], 1.0);
+        assert_eq!(summary["tasks"]["reverse"]["exec_ms"]["p50"], 3.0);
+
+        stats.reset();
+        assert_eq!(stats.summary()["routes"], serde_json::json!({}));
+    }
+}
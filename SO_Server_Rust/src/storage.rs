@@ -0,0 +1,358 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::SystemTime;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Mòdulo con la lògica compartida del directorio de archivos:
+// rutas, bloqueos por archivo y escrituras atòmicas
+
+pub const STORAGE_DIR: &str = "archivos";
+pub const VERSIONS_DIR: &str = ".versiones";
+
+// Cantidad fija de candados: cada ruta usa el de su hash, asì la memoria no crece con los nombres consultados
+const LOCK_STRIPES: usize = 64;
+
+static FILE_LOCKS: [RwLock<()>; LOCK_STRIPES] = [const { RwLock::new(()) }; LOCK_STRIPES];
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+static VERSIONING: AtomicBool = AtomicBool::new(false);
+
+pub struct VersionInfo {
+    pub number: u64,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+// Solo se permiten nombres alfanumèricos o con '_'
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+pub fn file_path(name: &str) -> PathBuf {
+    Path::new(STORAGE_DIR).join(format!("{}.txt", name))
+}
+
+// Acepta `nombre` o `nombre.ext` y devuelve la clave del archivo junto con su ruta.
+// `nombre` y `nombre.txt` son el mismo archivo, cualquier otra extensiòn forma parte de la clave
+pub fn resolve_name(name: &str) -> Option<(String, PathBuf)> {
+    let (stem, ext) = match name.split_once('.') {
+        Some((stem, ext)) => (stem, Some(ext)),
+        None => (name, None),
+    };
+
+    if !valid_name(stem) {
+        return None;
+    }
+
+    match ext {
+        None | Some("txt") => Some((stem.to_string(), file_path(stem))),
+        Some(ext) if !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            Some((name.to_string(), Path::new(STORAGE_DIR).join(name)))
+        }
+        _ => None,
+    }
+}
+
+// Candado de un archivo. Los lectores toman `read_lock` y los escritores `write_lock`,
+// asì ninguna tarea del pool ve el contenido a medio escribir. Dos rutas pueden compartir candado,
+// por eso nunca se debe tomar uno mientras se sostiene otro
+fn file_lock(path: &Path) -> &'static RwLock<()> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    &FILE_LOCKS[hasher.finish() as usize % LOCK_STRIPES]
+}
+
+// El candado no protege datos, asì que si una tarea entrò en pànico con èl tomado se sigue usando
+pub fn read_lock(path: &Path) -> RwLockReadGuard<'static, ()> {
+    file_lock(path).read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn write_lock(path: &Path) -> RwLockWriteGuard<'static, ()> {
+    file_lock(path).write().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Escribe en un archivo temporal, hace fsync y luego lo mueve al destino.
+// Si `replace` es falso se usa un hard link, que falla con `AlreadyExists` si el destino ya existe
+pub fn write_atomic(path: &Path, content: &[u8], replace: bool) -> io::Result<()> {
+    let (tmp_path, _) = stage(path, content)?;
+    commit_staged(&tmp_path, path, replace)
+}
+
+// Copia todo `reader` a un temporal junto a `path` (sin cargarlo en memoria) y hace fsync.
+// Devuelve la ruta del temporal y los bytes escritos; se completa con `commit_staged`
+pub fn stage<R: Read>(path: &Path, mut reader: R) -> io::Result<(PathBuf, u64)> {
+    let (tmp_path, mut file) = create_temp(path)?;
+
+    let result = io::copy(&mut reader, &mut file).and_then(|written| {
+        file.flush()?;
+        file.sync_all()?;
+        Ok(written)
+    });
+
+    match result {
+        Ok(written) => Ok((tmp_path, written)),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+// Crea un temporal oculto y vacìo junto a `path` para escribirlo por partes
+pub fn create_temp(path: &Path) -> io::Result<(PathBuf, File)> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("archivo");
+    let tmp_path = dir.join(format!(".{}.tmp.{}.{}", file_name, std::process::id(), TMP_COUNTER.fetch_add(1, Ordering::SeqCst)));
+    let file = OpenOptions::new().write(true).create_new(true).open(&tmp_path)?;
+    Ok((tmp_path, file))
+}
+
+pub fn commit_staged(tmp_path: &Path, path: &Path, replace: bool) -> io::Result<()> {
+    let result = if replace {
+        fs::rename(tmp_path, path)
+    } else {
+        fs::hard_link(tmp_path, path)
+    };
+
+    let _ = fs::remove_file(tmp_path);
+    result?;
+
+    // Sincronizamos el directorio para que el rename sobreviva a una caìda
+    if let Ok(d) = File::open(path.parent().unwrap_or(Path::new("."))) {
+        let _ = d.sync_all();
+    }
+    Ok(())
+}
+
+// Activa o desactiva el versionado de archivos para todo el servidor
+pub fn set_versioning(enabled: bool) {
+    VERSIONING.store(enabled, Ordering::SeqCst);
+}
+
+pub fn versioning_enabled() -> bool {
+    VERSIONING.load(Ordering::SeqCst)
+}
+
+// Las versiones de `name` viven en archivos/.versiones/<name>/<n>.txt
+pub fn versions_dir(name: &str) -> PathBuf {
+    Path::new(STORAGE_DIR).join(VERSIONS_DIR).join(name)
+}
+
+pub fn version_path(name: &str, number: u64) -> PathBuf {
+    versions_dir(name).join(format!("{}.txt", number))
+}
+
+// Lista las versiones guardadas de un archivo, ordenadas de la màs vieja a la màs nueva
+pub fn list_versions(name: &str) -> io::Result<Vec<VersionInfo>> {
+    let mut versions = Vec::new();
+    let entries = match fs::read_dir(versions_dir(name)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(versions),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let number = match file_name.to_str().and_then(|n| n.strip_suffix(".txt")).and_then(|n| n.parse::<u64>().ok()) {
+            Some(n) => n,
+            None => continue,
+        };
+        let meta = entry.metadata()?;
+        versions.push(VersionInfo { number, size: meta.len(), modified: meta.modified()? });
+    }
+
+    versions.sort_by_key(|v| v.number);
+    Ok(versions)
+}
+
+// Copia el contenido actual de `path` como una nueva versiòn numerada.
+// Se debe llamar con el candado de escritura del archivo tomado
+pub fn save_version(name: &str, path: &Path) -> io::Result<Option<u64>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let next = list_versions(name)?.last().map(|v| v.number + 1).unwrap_or(1);
+    write_atomic(&version_path(name, next), &content, false)?;
+    Ok(Some(next))
+}
+
+// Lee el archivo completo mientras se sostiene el candado de lectura
+pub fn read_locked(path: &Path) -> io::Result<Vec<u8>> {
+    let _guard = read_lock(path);
+    fs::read(path)
+}
+
+// Reemplaza el contenido de un archivo bajo su candado, guardando antes la versiòn anterior si el versionado està activo
+pub fn replace_file(key: &str, path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let (tmp_path, _) = stage(path, content)?;
+    replace_staged(key, &tmp_path, path)
+}
+
+// Igual que `replace_file` pero con el contenido ya escrito en un temporal de `stage` o `create_temp`
+pub fn replace_staged(key: &str, tmp_path: &Path, path: &Path) -> io::Result<()> {
+    let _guard = write_lock(path);
+    if path.exists() && versioning_enabled() && let Err(e) = save_version(key, path) {
+        let _ = fs::remove_file(tmp_path);
+        return Err(e);
+    }
+    commit_staged(tmp_path, path, true)
+}
+
+// Origen de los datos de una tarea: texto del query, archivo guardado o cuerpo de la solicitud
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputSource {
+    Inline(String),
+    // Archivo guardado en archivos/
+    Stored(String),
+    // Cuerpo de la solicitud ya copiado a un temporal; se borra al soltar la ùltima copia
+    Body(Arc<StagedBody>),
+}
+
+// Temporal con el cuerpo de una solicitud. Se borra al soltarse, asì no queda en archivos/
+// aunque la tarea falle, se rechace por cola llena o nunca llegue a ejecutarse
+#[derive(Debug, PartialEq)]
+pub struct StagedBody(PathBuf);
+
+impl StagedBody {
+    pub fn new(path: PathBuf) -> StagedBody {
+        StagedBody(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for StagedBody {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl InputSource {
+    // Abre el origen para leerlo por bloques. El candado de un archivo guardado sòlo cubre la apertura:
+    // la lectura es segura porque toda escritura va a un temporal que luego se renombra, asì que el
+    // descriptor abierto sigue apuntando a una versiòn completa aunque el archivo se reemplace despuès
+    pub fn open(&self) -> Result<Box<dyn Read + '_>, ApiError> {
+        match self {
+            InputSource::Inline(text) => Ok(Box::new(text.as_bytes())),
+            InputSource::Stored(name) => {
+                let path = match resolve_name(name) {
+                    Some((_, path)) => path,
+                    None => return Err(ApiError::invalid_name("name")),
+                };
+                let _guard = read_lock(&path);
+                let file = File::open(&path).map_err(|_| ApiError::not_found(Some("name"), Message::key("file_not_found").arg("path", name)))?;
+                Ok(Box::new(file))
+            }
+            InputSource::Body(body) => {
+                let file = File::open(body.path()).map_err(|_| ApiError::internal(Message::key("body_read_failed")))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+
+    // Lee todo el origen, con un lìmite de bytes
+    pub fn read_all(&self, limit: u64) -> Result<Vec<u8>, ApiError> {
+        let mut data = Vec::new();
+        self.open()?.take(limit + 1).read_to_end(&mut data).map_err(|_| ApiError::internal(Message::key("input_read_failed")))?;
+        if data.len() as u64 > limit {
+            return Err(ApiError::PayloadTooLarge { message: Message::key("input_too_large").arg("max", limit) });
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    use super::{file_lock, list_versions, read_lock, read_locked, resolve_name, save_version, valid_name, version_path, write_atomic, write_lock};
+
+    #[test]
+    fn test_valid_name() {
+        assert!(valid_name("archivo_1"));
+        assert!(!valid_name("../etc"));
+        assert!(!valid_name(""));
+    }
+
+    #[test]
+    fn test_resolve_name() {
+        assert_eq!(resolve_name("notas").unwrap().1, Path::new("archivos/notas.txt"));
+        assert_eq!(resolve_name("notas.txt").unwrap().0, "notas");
+        assert_eq!(resolve_name("foto.png").unwrap(), ("foto.png".to_string(), Path::new("archivos/foto.png").to_path_buf()));
+        assert!(resolve_name("a.b.c").is_none());
+        assert!(resolve_name("../x").is_none());
+    }
+
+    #[test]
+    fn test_write_atomic_create_and_replace() {
+        let path = Path::new("archivos_test_storage/atomico.txt");
+        let _ = fs::remove_file(path);
+
+        write_atomic(path, b"uno", false).unwrap();
+        let err = write_atomic(path, b"dos", false).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+        write_atomic(path, b"tres", true).unwrap();
+        assert_eq!(read_locked(path).unwrap(), b"tres");
+
+        // No deben quedar temporales en el directorio
+        let leftovers = fs::read_dir("archivos_test_storage").unwrap().filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().starts_with('.')).count();
+        assert_eq!(leftovers, 0);
+        let _ = fs::remove_dir_all("archivos_test_storage");
+    }
+
+    #[test]
+    fn test_file_lock_is_shared_per_path() {
+        let a = file_lock(Path::new("archivos/x.txt"));
+        let b = file_lock(Path::new("archivos/x.txt"));
+        assert!(std::ptr::eq(a, b));
+
+        // Un pànico con el candado tomado no deja el archivo inaccesible
+        let path = Path::new("archivos/envenenado.txt");
+        let _ = std::thread::spawn(move || {
+            let _guard = write_lock(path);
+            panic!("tarea fallida");
+        }).join();
+        drop(read_lock(path));
+        drop(write_lock(path));
+    }
+
+    #[test]
+    fn test_save_version_numbers_are_sequential() {
+        let name = "storage_versiones";
+        let path = Path::new("archivos/storage_versiones.txt");
+        let _ = fs::remove_dir_all(super::versions_dir(name));
+
+        write_atomic(path, b"v1", true).unwrap();
+        assert_eq!(save_version(name, path).unwrap(), Some(1));
+        write_atomic(path, b"v2", true).unwrap();
+        assert_eq!(save_version(name, path).unwrap(), Some(2));
+
+        let versions = list_versions(name).unwrap();
+        assert_eq!(versions.iter().map(|v| v.number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(fs::read(version_path(name, 1)).unwrap(), b"v1");
+
+        let _ = fs::remove_file(path);
+        assert_eq!(save_version(name, path).unwrap(), None);
+        let _ = fs::remove_dir_all(super::versions_dir(name));
+    }
+}
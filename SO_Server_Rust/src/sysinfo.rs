@@ -0,0 +1,223 @@
+use std::fs;
+use std::os::unix::process;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{json, Value};
+
+// Informaciòn del proceso y del equipo para /status, /status/system y /metrics.
+// Se lee de /proc (memoria, hilos, descriptores, carga) y de getrusage (tiempo de CPU)
+
+// Memoria, hilos, CPU y descriptores del proceso del servidor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    // Segundos Unix del arranque del proceso
+    pub start_time: Option<i64>,
+    pub threads: u64,
+    pub rss_bytes: u64,
+    pub virtual_bytes: u64,
+    pub open_fds: u64,
+    pub cpu_user: Duration,
+    pub cpu_system: Duration,
+    pub load_average: Option<[f64; 3]>,
+}
+
+impl ProcessInfo {
+    // None si no hay /proc (el servidor solo corre en Linux, pero las pruebas no lo asumen)
+    pub fn read() -> Option<ProcessInfo> {
+        let status = fs::read_to_string("/proc/self/status").ok()?;
+        let mut info = ProcessInfo { pid: std::process::id(), ppid: process::parent_id(), ..ProcessInfo::default() };
+        for (key, number) in proc_fields(&status) {
+            match key {
+                "VmRSS" => info.rss_bytes = number * 1024,
+                "VmSize" => info.virtual_bytes = number * 1024,
+                "Threads" => info.threads = number,
+                _ => {}
+            }
+        }
+        info.open_fds = fs::read_dir("/proc/self/fd").map(|dir| dir.count() as u64).unwrap_or(0);
+        info.start_time = start_time();
+        (info.cpu_user, info.cpu_system) = cpu_times().unwrap_or_default();
+        info.load_average = load_average();
+        Some(info)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "pid": self.pid,
+            "ppid": self.ppid,
+            "start_time": self.start_time.and_then(|secs| DateTime::from_timestamp(secs, 0)).map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "threads": self.threads,
+            "memory": {"rss_bytes": self.rss_bytes, "virtual_bytes": self.virtual_bytes},
+            "cpu": {"user_secs": self.cpu_user.as_secs_f64(), "system_secs": self.cpu_system.as_secs_f64()},
+            "open_fds": self.open_fds,
+            "load_average": self.load_average,
+        })
+    }
+}
+
+// CPUs y memoria del equipo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemInfo {
+    // CPUs en lìnea y las que puede usar el proceso (afinidad, cgroups)
+    pub cpus: u64,
+    pub available_cpus: u64,
+    pub memory_total: u64,
+    pub memory_available: u64,
+    pub memory_free: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+    pub uptime: Option<Duration>,
+    pub load_average: Option<[f64; 3]>,
+    pub hostname: Option<String>,
+    pub kernel: Option<String>,
+}
+
+impl SystemInfo {
+    pub fn read() -> SystemInfo {
+        let mut info = SystemInfo {
+            cpus: sysconf(libc::_SC_NPROCESSORS_ONLN).unwrap_or(0),
+            available_cpus: thread::available_parallelism().map(|n| n.get() as u64).unwrap_or(0),
+            uptime: fs::read_to_string("/proc/uptime").ok().and_then(|text| parse_uptime(&text)),
+            load_average: load_average(),
+            hostname: read_line("/proc/sys/kernel/hostname"),
+            kernel: read_line("/proc/sys/kernel/osrelease"),
+            ..SystemInfo::default()
+        };
+        if let Ok(meminfo) = fs::read_to_string("/proc/meminfo") {
+            for (key, number) in proc_fields(&meminfo) {
+                match key {
+                    "MemTotal" => info.memory_total = number * 1024,
+                    "MemAvailable" => info.memory_available = number * 1024,
+                    "MemFree" => info.memory_free = number * 1024,
+                    "SwapTotal" => info.swap_total = number * 1024,
+                    "SwapFree" => info.swap_free = number * 1024,
+                    _ => {}
+                }
+            }
+        }
+        info
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "hostname": self.hostname,
+            "kernel": self.kernel,
+            "cpus": self.cpus,
+            "available_cpus": self.available_cpus,
+            "memory": {
+                "total_bytes": self.memory_total,
+                "available_bytes": self.memory_available,
+                "free_bytes": self.memory_free,
+                "swap_total_bytes": self.swap_total,
+                "swap_free_bytes": self.swap_free,
+            },
+            "uptime_secs": self.uptime.map(|d| d.as_secs()),
+            "load_average": self.load_average,
+        })
+    }
+}
+
+// Lìneas `Clave:   123 kB` de /proc/self/status y /proc/meminfo
+fn proc_fields(text: &str) -> impl Iterator<Item = (&str, u64)> {
+    text.lines().filter_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        Some((key, value.split_whitespace().next()?.parse().ok()?))
+    })
+}
+
+fn read_line(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|text| text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn sysconf(name: libc::c_int) -> Option<u64> {
+    // SAFETY: sysconf solo consulta un valor de configuraciòn, no toca memoria nuestra
+    let value = unsafe { libc::sysconf(name) };
+    (value > 0).then_some(value as u64)
+}
+
+// Tiempo de CPU en modo usuario y en modo sistema de todos los hilos del proceso
+fn cpu_times() -> Option<(Duration, Duration)> {
+    // SAFETY: rusage son solo nùmeros, asì que ceros es un valor vàlido, y getrusage solo escribe en ella
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64);
+    Some((time(usage.ru_utime), time(usage.ru_stime)))
+}
+
+// El arranque està en /proc/self/stat en ticks desde el inicio del equipo, que està en /proc/stat (btime)
+fn start_time() -> Option<i64> {
+    let ticks = parse_start_ticks(&fs::read_to_string("/proc/self/stat").ok()?)?;
+    let boot = parse_boot_time(&fs::read_to_string("/proc/stat").ok()?)?;
+    let per_second = sysconf(libc::_SC_CLK_TCK)?;
+    Some(boot + (ticks / per_second) as i64)
+}
+
+// El nombre del programa va entre parèntesis y puede tener espacios: los campos se cuentan despuès del ùltimo ')'.
+// `starttime` es el campo 22 y el primero despuès del nombre es el 3
+fn parse_start_ticks(stat: &str) -> Option<u64> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+fn parse_boot_time(stat: &str) -> Option<i64> {
+    stat.lines().find_map(|line| line.strip_prefix("btime ")).and_then(|secs| secs.trim().parse().ok())
+}
+
+fn parse_uptime(text: &str) -> Option<Duration> {
+    text.split_whitespace().next()?.parse::<f64>().ok().map(Duration::from_secs_f64)
+}
+
+fn load_average() -> Option<[f64; 3]> {
+    parse_load_average(&fs::read_to_string("/proc/loadavg").ok()?)
+}
+
+fn parse_load_average(text: &str) -> Option<[f64; 3]> {
+    let mut values = text.split_whitespace().map(|v| v.parse::<f64>().ok());
+    Some([values.next()??, values.next()??, values.next()??])
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{parse_boot_time, parse_load_average, parse_start_ticks, parse_uptime, ProcessInfo, SystemInfo};
+
+    #[test]
+    fn test_parse_proc_files() {
+        let stat = "4242 (so server (x)) S 1 4242 4242 0 -1 4194560 500 0 0 0 3 1 0 0 20 0 6 0 987654 361500672 2718";
+        assert_eq!(parse_start_ticks(stat), Some(987654));
+        assert_eq!(parse_start_ticks("4242 (corto) S 1"), None);
+        assert_eq!(parse_boot_time("cpu  1 2 3\nbtime 1700000000\nprocesses 10\n"), Some(1700000000));
+        assert_eq!(parse_load_average("0.52 0.58 0.59 2/1234 5678\n"), Some([0.52, 0.58, 0.59]));
+        assert_eq!(parse_load_average("0.52\n"), None);
+        assert_eq!(parse_uptime("3600.25 7000.00\n"), Some(Duration::from_secs_f64(3600.25)));
+    }
+
+    #[test]
+    fn test_process_info_is_this_process() {
+        let info = ProcessInfo::read().unwrap();
+        // El pid es el del servidor y no el del shell que lo lanzò
+        assert_eq!(info.pid, std::process::id());
+        assert_ne!(info.ppid, info.pid);
+        assert!(info.threads > 0 && info.rss_bytes > 0 && info.virtual_bytes >= info.rss_bytes && info.open_fds > 0);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let start = info.start_time.unwrap();
+        assert!(start <= now && start > now - 24 * 3600, "arranque {} fuera de rango", start);
+        assert!(info.to_json()["start_time"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_system_info() {
+        let info = SystemInfo::read();
+        assert!(info.cpus >= 1 && info.available_cpus >= 1);
+        assert!(info.memory_total > 0 && info.memory_available <= info.memory_total);
+        assert_eq!(info.to_json()["memory"]["total_bytes"], info.memory_total);
+    }
+}
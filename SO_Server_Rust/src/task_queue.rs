@@ -1,35 +1,186 @@
 use std::{sync::{mpsc::Receiver, Arc, Mutex}, thread};
-use std::sync::mpsc::Sender as MpscSender;
-
-use crate::endpoints::{create_file, delete_file, fibonacci, generate_random_numbers, rerverse_text, sha256_hash, timestamp_iso, to_uppercase};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender as MpscSender, SyncSender};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::datetime::{run_date_request, DateRequest};
+use crate::error_responses::ApiError;
+use crate::metrics::METRICS;
+use crate::stats::STATS;
+use crate::i18n::Message;
+use crate::grep::{grep_files, GrepOptions};
+use crate::random_gen::{generate_random, RandomSpec};
+use crate::text_ops::{apply_pipeline, TextOp};
+use crate::text_stats::{text_stats, StatsOptions};
+use crate::storage::{versioning_enabled, InputSource};
+use crate::codec::{run_codec, CodecOutput, CodecStep};
+use crate::compression::{run_compression, CompressSpec};
+use crate::benchmarks::{run_benchmark, Benchmark};
+use crate::mandelbrot::{render_tile, Tile};
+use crate::calc::{evaluate, Value};
+use crate::csv_ops::{run_csv, CsvSpec};
+use crate::json_ops::{run_json, JsonSpec};
+use crate::endpoints::{create_file, delete_file, fibonacci, get_file_version, list_file_versions, purge_file_versions, rerverse_text, restore_file_version, sha256_hash, timestamp_iso, to_uppercase, WriteMode};
 
 #[derive(Debug, Clone)]
 pub enum TaskType {
     Reverse(String),
     Toupper(String),
+    Text {ops : Vec<TextOp>, text : String},
+    TextStats {input : InputSource, options : StatsOptions},
+    Codec {input : InputSource, steps : Vec<CodecStep>, output : CodecOutput},
+    Compress(CompressSpec),
+    Bench(Benchmark),
+    Mandelbrot(Tile),
+    Calc {expr : String, variables : HashMap<String, Value>},
+    Csv {input : InputSource, spec : CsvSpec},
+    Json {input : InputSource, spec : JsonSpec},
     Sha256(String),
     Fibonacci(u64),
     Sleep(u64),
     TimeStamp,
-    Random {count : usize, min : i32, max : i32},
-    CreateFile {name : String, content : String},
+    Date(DateRequest),
+    Random(RandomSpec),
+    CreateFile {name : String, content : String, mode : WriteMode},
     DeleteFile(String),
+    ListVersions(String),
+    GetVersion {name : String, version : u64},
+    RestoreVersion {name : String, version : u64},
+    PurgeVersions {name : String, keep : Option<usize>, older_than : Option<u64>},
+    Grep {pattern : String, name : Option<String>, options : GrepOptions, cancel : CancelToken},
     Simulate {delay: u64, inner: Box<TaskType>},
-    Help
+}
+
+impl TaskType {
+    // Tareas cuyo resultado ya es un documento JSON y se entrega como valor dentro de la respuesta
+    pub fn replies_json(&self) -> bool {
+        matches!(self, TaskType::Random(_) | TaskType::TextStats { .. })
+    }
+
+    // Nombre estable del tipo de tarea, para las mètricas
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskType::Reverse(_) => "reverse",
+            TaskType::Toupper(_) => "toupper",
+            TaskType::Text { .. } => "text",
+            TaskType::TextStats { .. } => "textstats",
+            TaskType::Codec { .. } => "codec",
+            TaskType::Compress(_) => "compress",
+            TaskType::Bench(_) => "bench",
+            TaskType::Mandelbrot(_) => "mandelbrot",
+            TaskType::Calc { .. } => "calc",
+            TaskType::Csv { .. } => "csv",
+            TaskType::Json { .. } => "json",
+            TaskType::Sha256(_) => "sha256",
+            TaskType::Fibonacci(_) => "fibonacci",
+            TaskType::Sleep(_) => "sleep",
+            TaskType::TimeStamp => "timestamp",
+            TaskType::Date(_) => "date",
+            TaskType::Random(_) => "random",
+            TaskType::CreateFile { .. } => "createfile",
+            TaskType::DeleteFile(_) => "deletefile",
+            TaskType::ListVersions(_) => "listversions",
+            TaskType::GetVersion { .. } => "getversion",
+            TaskType::RestoreVersion { .. } => "restoreversion",
+            TaskType::PurgeVersions { .. } => "purgeversions",
+            TaskType::Grep { .. } => "grep",
+            TaskType::Simulate { .. } => "simulate",
+        }
+    }
+}
+
+// Bandera compartida para cancelar una tarea larga. Las tareas la revisan mientras trabajan
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken(Arc::new(AtomicBool::new(false)))
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Trabajos cancelables en curso, identificados por el paràmetro `job` que elige el cliente
+static JOBS: OnceLock<Mutex<HashMap<String, CancelToken>>> = OnceLock::new();
+
+fn jobs() -> &'static Mutex<HashMap<String, CancelToken>> {
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Registra un trabajo. Devuelve None si ya hay otro en curso con el mismo id
+pub fn register_job(id: &str) -> Option<CancelToken> {
+    let mut jobs = jobs().lock().unwrap();
+    if jobs.contains_key(id) {
+        return None;
+    }
+    let token = CancelToken::new();
+    jobs.insert(id.to_string(), token.clone());
+    Some(token)
+}
+
+pub fn finish_job(id: &str) {
+    jobs().lock().unwrap().remove(id);
+}
+
+pub fn cancel_job(id: &str) -> bool {
+    match jobs().lock().unwrap().get(id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+// Los workers responden con el resultado (texto o mensaje del catàlogo, que se traduce en la conexiòn)
+// o con el error que se le devuelve al cliente
+pub type TaskResult = Result<Message, ApiError>;
+
+// Mensajes que un worker puede adelantar sin que la conexiòn los lea. Con el canal acotado un /random
+// por streaming se detiene cuando el cliente lee màs lento, en vez de acumular toda la salida en memoria
+pub const REPLY_BUFFER: usize = 16;
+
+pub fn reply_channel() -> (SyncSender<TaskResult>, Receiver<TaskResult>) {
+    mpsc::sync_channel(REPLY_BUFFER)
 }
 
 #[derive(Debug)]
 pub struct Task {
     pub description : String,
     pub task_type : TaskType,
-    pub response_tx: MpscSender<String>,
+    pub response_tx: SyncSender<TaskResult>,
+    // Momento en que se encolò, para separar la espera en la cola del tiempo de ejecuciòn
+    pub enqueued: Instant,
+}
+
+// Encola una tarea llevando la cuenta de la profundidad de la cola
+pub fn submit(sender: &MpscSender<Task>, task: Task) -> Result<(), ApiError> {
+    METRICS.enqueued();
+    // La cola no tiene lìmite: `send` sòlo falla si los workers ya no reciben tareas
+    sender.send(task).map_err(|_| {
+        METRICS.dequeued();
+        ApiError::internal(Message::key("workers_stopped"))
+    })
 }
 
 #[derive(Clone)]
 pub struct  WorkerStatus {
     pub id: usize, 
     pub busy: bool,
-    pub description: String
+    pub description: String,
+    // Tiempo total procesando tareas y tareas terminadas desde el arranque
+    pub busy_time: Duration,
+    pub completed: u64,
 }
 
 pub fn start_workers(rx: Arc<Mutex<Receiver<Task>>>, states : Arc<Mutex<Vec<WorkerStatus>>>){
@@ -45,6 +196,7 @@ pub fn start_workers(rx: Arc<Mutex<Receiver<Task>>>, states : Arc<Mutex<Vec<Work
                 };
 
                 if let Some(task) = task {
+                    METRICS.dequeued();
                     {
                         let mut state = states_clone.lock().unwrap();
                         if let Some(worker) = state.iter_mut().find(|w| w.id == id) {
@@ -53,11 +205,21 @@ pub fn start_workers(rx: Arc<Mutex<Receiver<Task>>>, states : Arc<Mutex<Vec<Work
                         }
                     }
 
-                    process_task(task);
+                    let name = task.task_type.name();
+                    let started = Instant::now();
+                    let wait = started.duration_since(task.enqueued);
+                    // Una tarea que entra en pànico no se lleva al worker: el canal de respuesta se suelta
+                    // (la conexiòn responde 500) y el worker sigue atendiendo la cola
+                    let ok = panic::catch_unwind(AssertUnwindSafe(|| process_task(task))).unwrap_or(false);
+                    let elapsed = started.elapsed();
+                    METRICS.record_task(name, elapsed);
+                    STATS.record_task(name, ok, wait, elapsed);
 
                     {
                         let mut state = states_clone.lock().unwrap();
                         if let Some(worker) = state.iter_mut().find(|w| w.id == id) {
+                            worker.busy_time += elapsed;
+                            worker.completed += 1;
                             worker.busy = false;
                             worker.description = "idle".to_string();
                         }
@@ -66,48 +228,99 @@ pub fn start_workers(rx: Arc<Mutex<Receiver<Task>>>, states : Arc<Mutex<Vec<Work
             }
         });
 
-        states.lock().unwrap().push(WorkerStatus { id: id, busy: false, description: "idle".to_string() });
+        states.lock().unwrap().push(WorkerStatus { id, busy: false, description: "idle".to_string(), busy_time: Duration::ZERO, completed: 0 });
     }
 }
 
-pub fn process_task(task : Task) {
+// Devuelve false si la tarea respondiò con un error
+pub fn process_task(task : Task) -> bool {
     match task.task_type {
         TaskType::Reverse(ref s) => {
             let reversed: String = rerverse_text(s);
-            let _ = task.response_tx.send(reversed);
+            reply(&task.response_tx, Ok(reversed.into()))
         }TaskType::Toupper(ref s) => {
             let upper = to_uppercase(s);
-            let _ = task.response_tx.send(upper);
+            reply(&task.response_tx, Ok(upper.into()))
+        }TaskType::Text { ref ops, ref text } => {
+            let result = apply_pipeline(ops, text);
+            reply(&task.response_tx, Ok(result.into()))
+        }TaskType::TextStats { ref input, ref options } => {
+            let result = text_stats(input, options).map(|report| Message::from(report.to_string()));
+            reply(&task.response_tx, result)
+        }TaskType::Codec { ref input, ref steps, ref output } => {
+            let result = run_codec(input, steps, output);
+            reply(&task.response_tx, result)
+        }TaskType::Compress(ref spec) => {
+            let result = run_compression(spec);
+            reply(&task.response_tx, result)
+        }TaskType::Bench(ref benchmark) => {
+            let result = run_benchmark(benchmark);
+            reply(&task.response_tx, Ok(result))
+        }TaskType::Mandelbrot(ref tile) => {
+            let result = render_tile(tile);
+            reply(&task.response_tx, Ok(result))
+        }TaskType::Calc { ref expr, ref variables } => {
+            let result = evaluate(expr, variables).map(|value| value.to_string().into()).map_err(ApiError::invalid_input);
+            reply(&task.response_tx, result)
+        }TaskType::Csv { ref input, ref spec } => {
+            let result = run_csv(input, spec);
+            reply(&task.response_tx, result)
+        }TaskType::Json { ref input, ref spec } => {
+            let result = run_json(input, spec);
+            reply(&task.response_tx, result)
         }TaskType::Sha256(ref s) => {
             let hash = sha256_hash(s);
-            let _ = task.response_tx.send(hash);
+            reply(&task.response_tx, Ok(hash.into()))
         }TaskType::Fibonacci(n) => {
             let result = fibonacci(n);
-            let _ = task.response_tx.send(result.to_string());
+            reply(&task.response_tx, Ok(result.to_string().into()))
         }TaskType::Sleep(n) => {
             std::thread::sleep(std::time::Duration::from_secs(n));
-            let _ = task.response_tx.send(format!("Simulado por {} segundos", n));
+            reply(&task.response_tx, Ok(Message::key("slept").arg("seconds", n)))
         }TaskType::TimeStamp => {
             let iso = timestamp_iso();
-            let _ = task.response_tx.send(iso);
-        }TaskType::Random { count, min, max } => {
-            let values = generate_random_numbers(count, min, max);
-            let str = format!("{:?}", values);
-            let _ = task.response_tx.send(str);
-        }TaskType::CreateFile { ref name, ref content } => {
-            let result = create_file(name, content);
-            let str = match result {
-                Ok(msg) => format!("{}", msg),
-                Err(msg) => format!("{}", msg)
+            reply(&task.response_tx, Ok(iso.into()))
+        }TaskType::Date(ref request) => {
+            let result = run_date_request(request).map(Message::from).map_err(|msg| ApiError::invalid("input", msg));
+            reply(&task.response_tx, result)
+        }TaskType::Random(ref spec) => {
+            // Con cantidades grandes cada bloque se envìa por separado, si no se junta todo en un solo mensaje
+            let result = if spec.streams() {
+                generate_random(spec, |chunk| task.response_tx.send(Ok(chunk.into())).is_ok())
+            } else {
+                let mut values = String::new();
+                generate_random(spec, |chunk| {
+                    values.push_str(&chunk);
+                    true
+                }).map(|_| {
+                    let _ = task.response_tx.send(Ok(values.into()));
+                })
             };
-            let _ = task.response_tx.send(str);
+            match result {
+                Ok(_) => true,
+                Err(error) => reply(&task.response_tx, Err(error)),
+            }
+        }TaskType::CreateFile { ref name, ref content, mode } => {
+            let result = create_file(name, content, mode, versioning_enabled());
+            reply(&task.response_tx, result)
         }TaskType::DeleteFile(ref name) => {
-            let result = delete_file(name);
-            let str = match result {
-                Ok(msg) => format!("{}", msg),
-                Err(msg) => format!("{}", msg)
-            };
-            let _ = task.response_tx.send(str);
+            let result = delete_file(name, versioning_enabled());
+            reply(&task.response_tx, result)
+        }TaskType::ListVersions(ref name) => {
+            let result = list_file_versions(name);
+            reply(&task.response_tx, result)
+        }TaskType::GetVersion { ref name, version } => {
+            let result = get_file_version(name, version);
+            reply(&task.response_tx, result)
+        }TaskType::RestoreVersion { ref name, version } => {
+            let result = restore_file_version(name, version, versioning_enabled());
+            reply(&task.response_tx, result)
+        }TaskType::PurgeVersions { ref name, keep, older_than } => {
+            let result = purge_file_versions(name, keep, older_than);
+            reply(&task.response_tx, result)
+        }TaskType::Grep { ref pattern, ref name, ref options, ref cancel } => {
+            let result = grep_files(pattern, name.as_deref(), options, cancel);
+            reply(&task.response_tx, result)
         }TaskType::Simulate { delay, inner } => {
             std::thread::sleep(std::time::Duration::from_secs(delay));
 
@@ -115,156 +328,254 @@ pub fn process_task(task : Task) {
             let new_task = Task {
                 description : task.description,
                 task_type : *inner,
-                response_tx : task.response_tx
+                response_tx : task.response_tx,
+                enqueued : task.enqueued,
             };
 
-            process_task(new_task);
-        } TaskType::Help => {
-            let ayuda = format!("\"endpoints\" : [
-            {{\"path\" : \"reverse\", 
-            \"description\" : \"Invierte el texto recibido\", 
-            \"params\" : [\"text: texto que se desea invertir\"], 
-            \"example\" : \"/reverse?text=abc\"}},
-            {{\"path\" : \"toupper\", \"description\" : \"Convierte el texto a mayúsculas\", \"params\" : [\"text: texto a convertir\"], \"example\" : \"/toupper?text=hola\"}},
-            {{\"path\" : \"sha256\", \"description\" : \"Devuelve el hash SHA-256 del texto\", \"params\" : [\"text: texto a hashear\"], \"example\" : \"/sha256?text=hola\"}},
-            {{\"path\" : \"fibonacci\", \"description\" : \"Calcula el n-ésimo número de Fibonacci (recursivo)\", \"params\" : [\"num: número a calcular\"], \"example\" : \"/fibonacci?num=10\"}},
-            {{\"path\" : \"random\", \"description\" : \"Genera una lista de números aleatorios\", \"params\" : [\"count: cantidad\", \"min: mínimo\", \"max: máximo\"], \"example\" : \"/random?count=5&min=10&max=100\"}},
-            {{\"path\" : \"timestamp\", \"description\" : \"Devuelve la hora actual en formato ISO\", \"params\" : [], \"example\" : \"/timestamp\"}},
-            {{\"path\" : \"sleep\", \"description\" : \"Simula una espera bloqueante de N segundos\", \"params\" : [\"seconds: segundos a esperar\"], \"example\" : \"/sleep?seconds=3\"}},
-            {{\"path\" : \"createfile\", \"description\" : \"Crea un archivo con el contenido indicado\", \"params\" : [\"name: nombre del archivo\", \"content: contenido\"], \"example\" : \"/createfile?name=miarchivo&content=hola\"}},
-            {{\"path\" : \"deletefile\", \"description\" : \"Elimina un archivo existente\", \"params\" : [\"name: nombre del archivo\"], \"example\" : \"/deletefile?name=miarchivo\"}},
-            {{\"path\" : \"simulate\", \"description\" : \"Simula un endpoint como reverse, toupper, etc., con retardo\", \"params\" : [\"seconds: retardo\", \"task: nombre del endpoint interno\", \"otros: según la tarea\"], \"example\" : \"/simulate?seconds=2&task=reverse&text=hola\"}},
-            {{\"path\" : \"loadtest\", \"description\" : \"Encola múltiples tareas para medir carga del sistema\", \"params\" : [\"task: tipo de tarea\", \"count: cuántas tareas\", \"text: valor base si aplica\"], \"example\" : \"/loadtest?task=reverse&count=5&text=hola\"}},
-            {{\"path\" : \"help\", \"description\" : \"Devuelve este manual de uso de endpoints\", \"params\" : [], \"example\" : \"/help\"}},
-            ]");
-            let _ = task.response_tx.send(ayuda);
+            process_task(new_task)
         }
     }
 }
 
+fn reply(response_tx: &SyncSender<TaskResult>, result: TaskResult) -> bool {
+    let ok = result.is_ok();
+    let _ = response_tx.send(result);
+    ok
+}
+
 #[cfg(test)]
 mod test {
     use std::time::Instant;
-    use std::{sync::mpsc::channel};
     use super::*;
 
     use super::{process_task, TaskType};
+    use crate::random_gen::{Distribution, RandomKind};
+    use crate::codec::Encoding;
+    use crate::csv_ops::parse_csv_params;
+    use crate::json_ops::parse_json_params;
 
     #[test]
     fn test_reverser_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description : "Invertir cadena".into(),
             task_type : TaskType::Reverse("abc".into()),
             response_tx : tx,
+            enqueued : Instant::now(),
         };
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert_eq!(result, "cba");
     }
 
+    #[test]
+    fn test_text_pipeline_task() {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : "Pipeline de texto".into(),
+            task_type : TaskType::Text { ops: vec![TextOp::Trim, TextOp::Slugify], text: "  Árbol de Navidad ".into() },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert_eq!(rx.recv().unwrap().unwrap().to_string(), "arbol-de-navidad");
+    }
+
+    #[test]
+    fn test_textstats_task() {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : "Estadìsticas".into(),
+            task_type : TaskType::TextStats { input: InputSource::Inline("hola hola mundo".into()), options: StatsOptions::default() },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        let report: serde_json::Value = serde_json::from_str(&rx.recv().unwrap().unwrap().to_string()).unwrap();
+        assert_eq!(report["top_words"][0], serde_json::json!({"word": "hola", "count": 2}));
+    }
+
+    #[test]
+    fn test_codec_task_marks_invalid_input() {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : "Decodificar".into(),
+            task_type : TaskType::Codec { input: InputSource::Inline("aG9sYQ=".into()), steps: vec![CodecStep::Decode(Encoding::Base64)], output: CodecOutput::Text },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert_eq!(rx.recv().unwrap().unwrap_err().code(), "invalid_input");
+    }
+
+    #[test]
+    fn test_bench_task() {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : "Primos".into(),
+            task_type : TaskType::Bench(Benchmark::Primes(100)),
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert!(rx.recv().unwrap().unwrap().to_string().starts_with("25 primos menores o iguales a 100"));
+    }
+
+    #[test]
+    fn test_calc_task() {
+        let (tx, rx) = reply_channel();
+        let task = Task {
+            description : "Calcular".into(),
+            task_type : TaskType::Calc { expr: "x * (2 + 3)".into(), variables: HashMap::from([("x".to_string(), Value::Int(4))]) },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert_eq!(rx.recv().unwrap().unwrap().to_string(), "20");
+
+        let (tx, rx) = reply_channel();
+        process_task(Task { description : "Calcular".into(), task_type : TaskType::Calc { expr: "1 / 0".into(), variables: HashMap::new() }, response_tx : tx, enqueued : Instant::now() });
+        assert_eq!(rx.recv().unwrap().unwrap_err().code(), "invalid_input");
+    }
+
+    #[test]
+    fn test_csv_task() {
+        let (tx, rx) = reply_channel();
+        let spec = parse_csv_params(&HashMap::from([("sort".to_string(), "-n".to_string())])).unwrap();
+        let task = Task {
+            description : "CSV".into(),
+            task_type : TaskType::Csv { input: InputSource::Inline("n\n1\n3\n2\n".into()), spec },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert_eq!(rx.recv().unwrap().unwrap().to_string(), "n\n3\n2\n1\n");
+    }
+
+    #[test]
+    fn test_json_task() {
+        let (tx, rx) = reply_channel();
+        let spec = parse_json_params(&HashMap::from([("op".to_string(), "query".to_string()), ("path".to_string(), "$.a[*]".to_string())])).unwrap();
+        let task = Task {
+            description : "JSON".into(),
+            task_type : TaskType::Json { input: InputSource::Inline("{\"a\": [1, 2]}".into()), spec },
+            response_tx : tx,
+            enqueued : Instant::now(),
+        };
+        process_task(task);
+        assert_eq!(rx.recv().unwrap().unwrap().to_string(), "[1,2]");
+    }
+
     #[test]
     fn test_toupper_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description : "Mayusculas".into(),
             task_type : TaskType::Toupper("hola".into()),
             response_tx : tx,
+            enqueued : Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert_eq!(result, "HOLA");
     }
 
     #[test]
     fn test_fibonacci_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description: "Fibonacci de 6".into(),
             task_type : TaskType::Fibonacci(6),
-            response_tx : tx
+            response_tx : tx,
+            enqueued : Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert_eq!(result, "8");
     }
 
     #[test]
     fn test_random_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description: "Random".into(),
-            task_type: TaskType::Random { count: 3, min: 1, max: 10 },
+            task_type: TaskType::Random(RandomSpec { kind: RandomKind::Numbers { count: 3, dist: Distribution::UniformInt { min: 1, max: 10 } }, seed: None }),
             response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert!(result.contains("[") && result.contains("]"));
     }
     
+    #[test]
+    fn test_streamed_random_waits_for_the_reader() {
+        let (tx, rx) = reply_channel();
+        let spec = RandomSpec { kind: RandomKind::Numbers { count: 1_000_000, dist: Distribution::UniformInt { min: 1, max: 10 } }, seed: Some(1) };
+        assert!(spec.streams());
+        let worker = std::thread::spawn(move || process_task(Task { description: "Random".into(), task_type: TaskType::Random(spec), response_tx: tx, enqueued: Instant::now() }));
+
+        // Sin lector el worker se detiene con el canal lleno en vez de generar todo
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(!worker.is_finished());
+        assert!(rx.try_iter().count() <= REPLY_BUFFER);
+
+        // Al soltar el receptor el envìo falla y el worker termina
+        drop(rx);
+        worker.join().unwrap();
+    }
+
     #[test]
     fn test_timestamp_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description: "timestamp".into(),
             task_type: TaskType::TimeStamp,
-            response_tx: tx
+            response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert!(result.contains("T"));
     }
 
     #[test]
     fn test_simulate_reverse_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description: "Simular Reverse".into(),
             task_type: TaskType::Simulate { delay: 1, inner: Box::new(TaskType::Reverse("xyz".into())) },
-            response_tx: tx
+            response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         let start = Instant::now();
         process_task(task);
         let elapsed = start.elapsed().as_secs();
 
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert_eq!(result, "zyx");
         assert!(elapsed >= 1);
     }
 
-    #[test]
-    fn test_help_task() {
-        let (tx, rx) = channel();
-        let task = Task {
-            description: "Ayuda".into(),
-            task_type: TaskType::Help,
-            response_tx: tx
-        };
-
-        process_task(task);
-        let result = rx.recv().unwrap();
-        assert!(result.contains("\"path\" : \"reverse\""));
-        assert!(result.contains("\"path\" : \"loadtest\""));
-    }
-
     #[test]
     fn test_sleep_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let start = Instant::now();
 
         let task = Task {
             description: "Simular espera".into(),
             task_type: TaskType::Sleep(1),
             response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
 
         let elapsed = start.elapsed().as_secs();
         assert!(elapsed >= 1, "el tiempo de espera fue menor a 1s");
@@ -273,48 +584,62 @@ mod test {
 
     #[test]
     fn test_create_file_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
         let task = Task {
             description: "Crear archivo".into(),
-            task_type: TaskType::CreateFile { name: "test_file".into(), content: "contenido de prueba".into() },
-            response_tx: tx
+            task_type: TaskType::CreateFile { name: "test_file".into(), content: "contenido de prueba".into(), mode: WriteMode::Create },
+            response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().map(|m| m.to_string()).unwrap_or_else(|e| e.message());
         assert!(result.contains("creado exitosamente") || result.contains("ya existe"));
     }
 
     #[test]
     fn test_delete_file_task() {
-        let (tx1, rx1) = channel();
+        let (tx1, rx1) = reply_channel();
 
         //Creamos el archivo para luego eliminarlo
         let create_task = Task {
             description: "Crear archivo".into(),
-            task_type: TaskType::CreateFile { name: "test_file".into(), content: "contenido temporal".into() },
-            response_tx: tx1
+            task_type: TaskType::CreateFile { name: "test_file".into(), content: "contenido temporal".into(), mode: WriteMode::Create },
+            response_tx: tx1,
+            enqueued: Instant::now(),
         };
 
         process_task(create_task);
         let _ = rx1.recv().unwrap();
 
         //En este caso vamos a eliminar el archivo
-        let (tx2, rx2) = channel();
+        let (tx2, rx2) = reply_channel();
         let delete_task = Task {
             description: "Eliminar archivo".into(),
             task_type: TaskType::DeleteFile("test_file".into()),
-            response_tx: tx2
+            response_tx: tx2,
+            enqueued: Instant::now(),
         };
 
         process_task(delete_task);
-        let result = rx2.recv().unwrap();
+        let result = rx2.recv().unwrap().map(|m| m.to_string()).unwrap_or_else(|e| e.message());
         assert!(result.contains("eliminado exitosamente") || result.contains("no existe"));
     }
 
+    #[test]
+    fn test_cancel_job_registry() {
+        let token = register_job("test_job").unwrap();
+        assert!(register_job("test_job").is_none());
+        assert!(cancel_job("test_job"));
+        assert!(token.is_cancelled());
+
+        finish_job("test_job");
+        assert!(!cancel_job("test_job"));
+    }
+
     #[test]
     fn test_sha256_task() {
-        let (tx, rx) = channel();
+        let (tx, rx) = reply_channel();
 
         let input_text = "hola";
         let expected_hash = sha256_hash(input_text);
@@ -322,10 +647,11 @@ mod test {
             description: "Hashear texto".into(),
             task_type: TaskType::Sha256(input_text.to_string()),
             response_tx: tx,
+            enqueued: Instant::now(),
         };
 
         process_task(task);
-        let result = rx.recv().unwrap();
+        let result = rx.recv().unwrap().unwrap().to_string();
         assert_eq!(result, expected_hash);
         assert_eq!(result.len(), 64);
     }
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+
+// Transformaciones de texto conscientes de Unicode (grafemas, mayùsculas/minùsculas, normalizaciòn)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextOp {
+    Reverse,
+    Upper,
+    Lower,
+    Title,
+    CaseFold,
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+    Trim,
+    Slugify,
+}
+
+pub const TEXT_OP_NAMES: &str = "reverse, upper, lower, title, casefold, nfc, nfd, nfkc, nfkd, trim, slugify";
+
+impl TextOp {
+    pub fn parse(name: &str) -> Option<TextOp> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "reverse" => Some(TextOp::Reverse),
+            "upper" | "toupper" => Some(TextOp::Upper),
+            "lower" | "tolower" => Some(TextOp::Lower),
+            "title" | "titlecase" => Some(TextOp::Title),
+            "casefold" => Some(TextOp::CaseFold),
+            "nfc" => Some(TextOp::Nfc),
+            "nfd" => Some(TextOp::Nfd),
+            "nfkc" => Some(TextOp::Nfkc),
+            "nfkd" => Some(TextOp::Nfkd),
+            "trim" => Some(TextOp::Trim),
+            "slugify" | "slug" => Some(TextOp::Slugify),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, input: &str) -> String {
+        match self {
+            TextOp::Reverse => reverse_graphemes(input),
+            TextOp::Upper => input.to_uppercase(),
+            TextOp::Lower => input.to_lowercase(),
+            TextOp::Title => title_case(input),
+            TextOp::CaseFold => caseless::default_case_fold_str(input),
+            TextOp::Nfc => input.nfc().collect(),
+            TextOp::Nfd => input.nfd().collect(),
+            TextOp::Nfkc => input.nfkc().collect(),
+            TextOp::Nfkd => input.nfkd().collect(),
+            TextOp::Trim => input.trim().to_string(),
+            TextOp::Slugify => slugify(input),
+        }
+    }
+}
+
+// Aplica las operaciones en orden: "trim,nfc,lower" => lower(nfc(trim(texto)))
+pub fn apply_pipeline(ops: &[TextOp], input: &str) -> String {
+    ops.iter().fold(input.to_string(), |text, op| op.apply(&text))
+}
+
+pub fn parse_pipeline(ops: &str) -> Result<Vec<TextOp>, Message> {
+    let parsed: Vec<TextOp> = ops.split(',').map(|name| TextOp::parse(name).ok_or(name.trim().to_string())).collect::<Result<_, _>>()
+        .map_err(|name| Message::key("text_unknown_op").arg("name", name).arg("available", TEXT_OP_NAMES))?;
+    if parsed.is_empty() {
+        return Err(Message::key("text_empty_ops"));
+    }
+    Ok(parsed)
+}
+
+// Operaciones de un endpoint de texto (/tolower, /normalize?form=nfd, /text?ops=...) y el texto a transformar.
+// Devuelve None si el nombre no es un endpoint de texto, asì /simulate y /loadtest pueden reutilizarla
+pub fn parse_text_request(endpoint: &str, params: &HashMap<String, String>) -> Option<Result<(Vec<TextOp>, String), ApiError>> {
+    let ops = match endpoint {
+        "tolower" => Ok(vec![TextOp::Lower]),
+        "titlecase" => Ok(vec![TextOp::Title]),
+        "casefold" => Ok(vec![TextOp::CaseFold]),
+        "trim" => Ok(vec![TextOp::Trim]),
+        "slugify" => Ok(vec![TextOp::Slugify]),
+        "normalize" => match params.get("form").map(|f| f.to_ascii_lowercase()).as_deref() {
+            None | Some("nfc") => Ok(vec![TextOp::Nfc]),
+            Some("nfd") => Ok(vec![TextOp::Nfd]),
+            Some("nfkc") => Ok(vec![TextOp::Nfkc]),
+            Some("nfkd") => Ok(vec![TextOp::Nfkd]),
+            Some(_) => Err(ApiError::invalid_choice("form", "'nfc', 'nfd', 'nfkc', 'nfkd'")),
+        },
+        "text" => match params.get("ops") {
+            Some(ops) => parse_pipeline(ops).map_err(|e| ApiError::invalid("ops", e)),
+            None => Err(ApiError::missing("ops")),
+        },
+        _ => return None,
+    };
+
+    Some(ops.and_then(|ops| match params.get("text") {
+        Some(text) => Ok((ops, text.clone())),
+        None => Err(ApiError::missing("text")),
+    }))
+}
+
+// Invierte por grupos de grafemas, asì los acentos combinados y los emoji compuestos no se rompen
+pub fn reverse_graphemes(input: &str) -> String {
+    input.graphemes(true).rev().collect()
+}
+
+// Primera letra de cada palabra en mayùscula y el resto en minùscula
+pub fn title_case(input: &str) -> String {
+    input.split_word_bounds().map(|word| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if first.is_alphanumeric() => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+            Some(_) => word.to_string(),
+            None => String::new(),
+        }
+    }).collect()
+}
+
+// "¡Hola, Señor Niño!" => "hola-senor-nino"
+pub fn slugify(input: &str) -> String {
+    let mut slug = String::with_capacity(input.len());
+    let mut pending_dash = false;
+
+    for c in input.nfkd().filter(|c| !is_combining_mark(*c)).flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            if pending_dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_dash = false;
+            slug.push(c);
+        } else {
+            pending_dash = true;
+        }
+    }
+
+    slug
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::error_responses::ApiError;
+
+    use super::{apply_pipeline, parse_pipeline, parse_text_request, reverse_graphemes, slugify, title_case, TextOp};
+
+    #[test]
+    fn test_reverse_keeps_graphemes() {
+        // "e" + acento combinado y una familia de emoji (con ZWJ)
+        assert_eq!(reverse_graphemes("cafe\u{301}"), "e\u{301}fac");
+        assert_eq!(reverse_graphemes("a👨‍👩‍👧b"), "b👨‍👩‍👧a");
+        assert_eq!(reverse_graphemes("hola"), "aloh");
+    }
+
+    #[test]
+    fn test_case_operations() {
+        assert_eq!(TextOp::Lower.apply("ÁRBOL"), "árbol");
+        assert_eq!(title_case("hola mUNDO, ¿qué tal?"), "Hola Mundo, ¿Qué Tal?");
+        assert_eq!(TextOp::CaseFold.apply("Straße"), "strasse");
+    }
+
+    #[test]
+    fn test_normalization_forms() {
+        let composed = "\u{e9}";
+        let decomposed = "e\u{301}";
+        assert_eq!(TextOp::Nfd.apply(composed), decomposed);
+        assert_eq!(TextOp::Nfc.apply(decomposed), composed);
+        assert_eq!(TextOp::Nfkc.apply("ﬁ²"), "fi2");
+        assert_eq!(TextOp::Nfkd.apply("ﬁ"), "fi");
+    }
+
+    #[test]
+    fn test_trim_slugify_and_pipeline() {
+        assert_eq!(TextOp::Trim.apply("\u{3000} hola \n"), "hola");
+        assert_eq!(slugify("  ¡Hola, Señor Niño!  "), "hola-senor-nino");
+
+        let ops = parse_pipeline("trim,title,reverse").unwrap();
+        assert_eq!(apply_pipeline(&ops, "  hola mundo "), "odnuM aloH");
+        assert!(parse_pipeline("trim,volar").unwrap_err().to_string().contains("volar"));
+    }
+
+    #[test]
+    fn test_parse_text_request() {
+        let mut params = HashMap::new();
+        params.insert("text".to_string(), "Hola".to_string());
+        params.insert("form".to_string(), "NFKD".to_string());
+
+        assert_eq!(parse_text_request("normalize", &params), Some(Ok((vec![TextOp::Nfkd], "Hola".to_string()))));
+        assert_eq!(parse_text_request("text", &params).unwrap().unwrap_err(), ApiError::missing("ops"));
+        assert!(parse_text_request("reverse", &params).is_none());
+
+        params.remove("text");
+        assert_eq!(parse_text_request("tolower", &params).unwrap().unwrap_err(), ApiError::missing("text"));
+    }
+}
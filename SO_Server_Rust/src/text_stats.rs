@@ -0,0 +1,226 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read};
+
+use serde_json::{json, Value};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error_responses::ApiError;
+use crate::i18n::Message;
+use crate::storage::InputSource;
+
+// Estadìsticas de texto para /textstats: conteos, frecuencia de palabras y n-gramas, como un objeto JSON.
+// El texto se procesa por bloques, asì un archivo de muchos megas no se carga completo en memoria
+
+pub const MAX_TOP: usize = 1000;
+pub const MAX_NGRAM: usize = 10;
+const CHUNK_BYTES: usize = 64 * 1024;
+// Si un bloque no tiene espacios (una sola "palabra" enorme) se corta de todas formas a este tamaño
+const MAX_PENDING_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsOptions {
+    pub top: usize,
+    pub ngram: usize,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions { top: 10, ngram: 3 }
+    }
+}
+
+pub fn parse_stats_params(params: &HashMap<String, String>) -> Result<StatsOptions, ApiError> {
+    let mut options = StatsOptions::default();
+    if let Some(top) = params.get("top") {
+        options.top = top.parse::<usize>().ok().filter(|t| *t <= MAX_TOP)
+            .ok_or(ApiError::not_in_range("top", 0, MAX_TOP))?;
+    }
+    if let Some(n) = params.get("ngram") {
+        options.ngram = n.parse::<usize>().ok().filter(|n| (1..=MAX_NGRAM).contains(n))
+            .ok_or(ApiError::not_in_range("ngram", 1, MAX_NGRAM))?;
+    }
+    Ok(options)
+}
+
+struct Stats {
+    bytes: u64,
+    chars: u64,
+    lines: u64,
+    words: u64,
+    word_chars: u64,
+    last_byte: Option<u8>,
+    frequencies: HashMap<String, u64>,
+    ngrams: HashMap<String, u64>,
+    total_ngrams: u64,
+    // Ùltimos caracteres vistos, para que los n-gramas crucen de un bloque al siguiente
+    window: VecDeque<char>,
+    last_was_space: bool,
+}
+
+impl Stats {
+    fn new() -> Self {
+        Stats {
+            bytes: 0, chars: 0, lines: 0, words: 0, word_chars: 0, last_byte: None,
+            frequencies: HashMap::new(), ngrams: HashMap::new(), total_ngrams: 0,
+            window: VecDeque::new(), last_was_space: true,
+        }
+    }
+
+    // `segment` siempre termina en un lìmite de palabra (espacio ascii) salvo al cortar por MAX_PENDING_BYTES
+    fn feed(&mut self, segment: &[u8], ngram: usize) {
+        self.bytes += segment.len() as u64;
+        self.lines += segment.iter().filter(|b| **b == b'\n').count() as u64;
+        if let Some(last) = segment.last() {
+            self.last_byte = Some(*last);
+        }
+
+        let text = String::from_utf8_lossy(segment);
+        self.chars += text.chars().count() as u64;
+
+        for word in text.unicode_words() {
+            self.words += 1;
+            self.word_chars += word.chars().count() as u64;
+            *self.frequencies.entry(word.to_lowercase()).or_insert(0) += 1;
+        }
+
+        // N-gramas de caracteres: minùsculas y cualquier secuencia de espacios cuenta como uno solo
+        for c in text.chars().flat_map(|c| c.to_lowercase()) {
+            let c = if c.is_whitespace() {
+                if self.last_was_space {
+                    continue;
+                }
+                self.last_was_space = true;
+                ' '
+            } else {
+                self.last_was_space = false;
+                c
+            };
+
+            if self.window.len() == ngram {
+                self.window.pop_front();
+            }
+            self.window.push_back(c);
+            if self.window.len() == ngram && self.window.iter().any(|c| *c != ' ') {
+                *self.ngrams.entry(self.window.iter().collect()).or_insert(0) += 1;
+                self.total_ngrams += 1;
+            }
+        }
+    }
+
+    fn report(&self, options: &StatsOptions) -> Value {
+        // Una ùltima lìnea sin '\n' tambièn cuenta
+        let lines = self.lines + u64::from(self.last_byte.is_some_and(|b| b != b'\n'));
+        let average = if self.words == 0 { 0.0 } else { self.word_chars as f64 / self.words as f64 };
+
+        json!({
+            "bytes": self.bytes,
+            "chars": self.chars,
+            "words": self.words,
+            "lines": lines,
+            "distinct_words": self.frequencies.len(),
+            "average_word_length": (average * 100.0).round() / 100.0,
+            "ngram_size": options.ngram,
+            "ngrams": self.total_ngrams,
+            "distinct_ngrams": self.ngrams.len(),
+            "top_words": top_entries(&self.frequencies, options.top, "word"),
+            "top_ngrams": top_entries(&self.ngrams, options.top, "ngram"),
+        })
+    }
+}
+
+// Las N entradas màs frecuentes, empates en orden alfabètico: [{"word": "de", "count": 12}, ...]
+fn top_entries(counts: &HashMap<String, u64>, top: usize, key: &str) -> Vec<Value> {
+    let mut entries: Vec<(&String, &u64)> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    entries.iter().take(top).map(|(entry, count)| json!({key: entry, "count": count})).collect()
+}
+
+pub fn analyze_reader<R: Read>(mut reader: R, options: &StatsOptions) -> io::Result<Value> {
+    let mut stats = Stats::new();
+    let mut pending: Vec<u8> = Vec::with_capacity(CHUNK_BYTES);
+    let mut chunk = vec![0u8; CHUNK_BYTES];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        pending.extend_from_slice(&chunk[..read]);
+
+        // Se procesa hasta el ùltimo espacio ascii para no partir palabras ni caracteres UTF-8
+        let split = match pending.iter().rposition(|b| b.is_ascii_whitespace()) {
+            Some(pos) => pos + 1,
+            None if pending.len() >= MAX_PENDING_BYTES => pending.iter().rposition(|b| b & 0xC0 != 0x80).unwrap_or(pending.len()),
+            None => continue,
+        };
+        stats.feed(&pending[..split], options.ngram);
+        pending.drain(..split);
+    }
+
+    stats.feed(&pending, options.ngram);
+    Ok(stats.report(options))
+}
+
+// /textstats?text=... | /textstats?name=archivo | POST /textstats con el texto en el cuerpo
+pub fn text_stats(input: &InputSource, options: &StatsOptions) -> Result<Value, ApiError> {
+    input.open().and_then(|reader| analyze_reader(reader, options).map_err(|_| ApiError::internal(Message::key("input_read_failed"))))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use crate::storage::{write_atomic, InputSource};
+
+    use super::{analyze_reader, parse_stats_params, text_stats, StatsOptions};
+
+    #[test]
+    fn test_counts_and_frequencies() {
+        let options = StatsOptions { top: 2, ngram: 2 };
+        let report = text_stats(&InputSource::Inline("La casa y la  CASA\nde la niña".to_string()), &options).unwrap();
+
+        assert_eq!((report["bytes"].as_u64(), report["chars"].as_u64()), (Some(30), Some(29)));
+        assert_eq!((report["words"].as_u64(), report["lines"].as_u64(), report["distinct_words"].as_u64()), (Some(8), Some(2), Some(5)));
+        assert_eq!(report["top_words"], json!([{"word": "la", "count": 3}, {"word": "casa", "count": 2}]));
+        assert_eq!(report["top_ngrams"], json!([{"ngram": "a ", "count": 5}, {"ngram": "la", "count": 3}]));
+    }
+
+    #[test]
+    fn test_streaming_matches_single_pass() {
+        // Un lector que entrega de a 7 bytes debe dar el mismo resultado que todo el texto junto
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(7);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let text = "árbol niño 東京 Ünïcödé\n".repeat(20_000);
+        let options = StatsOptions::default();
+        let whole = analyze_reader(text.as_bytes(), &options).unwrap();
+        assert_eq!(analyze_reader(Trickle(text.as_bytes()), &options).unwrap(), whole);
+        assert_eq!(whole["lines"], 20000);
+    }
+
+    #[test]
+    fn test_stored_file_and_params() {
+        write_atomic(std::path::Path::new("archivos/textstats_test.txt"), b"uno dos dos", true).unwrap();
+        let report = text_stats(&InputSource::Stored("textstats_test".to_string()), &StatsOptions::default()).unwrap();
+        assert_eq!(report["top_words"], json!([{"word": "dos", "count": 2}, {"word": "uno", "count": 1}]));
+        assert!(text_stats(&InputSource::Stored("no_existe_stats".to_string()), &StatsOptions::default()).is_err());
+        let _ = std::fs::remove_file("archivos/textstats_test.txt");
+
+        let mut params = HashMap::new();
+        params.insert("ngram".to_string(), "0".to_string());
+        assert!(parse_stats_params(&params).is_err());
+        params.insert("ngram".to_string(), "4".to_string());
+        assert_eq!(parse_stats_params(&params).unwrap(), StatsOptions { top: 10, ngram: 4 });
+    }
+}
@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod test {
-    use std::{thread, sync::{mpsc::channel, Arc, Mutex}, time::Duration};
-    use so_server_rust::task_queue::{start_workers, Task};
+    use std::{thread, sync::{mpsc::channel, Arc, Mutex}, time::{Duration, Instant}};
+    use so_server_rust::random_gen::{Distribution, RandomKind, RandomSpec};
+    use so_server_rust::task_queue::{reply_channel, start_workers, Task, TaskType};
 
     #[test]
     fn test_start_workers_executes_task_and_updates_status() {
@@ -13,16 +14,17 @@ mod test {
         start_workers(new_rx.clone(), states.clone());
 
         // Se encola una tarea
-        let (resp_tx, resp_rx) = channel();
+        let (resp_tx, resp_rx) = reply_channel();
         let task = Task {
             description: "Test de reverse".into(),
             task_type: so_server_rust::task_queue::TaskType::Reverse("abc".to_string()),
-            response_tx: resp_tx
+            response_tx: resp_tx,
+            enqueued: Instant::now(),
         };
         tx.send(task).unwrap();
 
         let result = resp_rx.recv_timeout(Duration::from_secs(1)).expect("No se recibio ninguna respuesta");
-        assert_eq!(result, "cba");
+        assert_eq!(result.unwrap().to_string(), "cba");
 
         thread::sleep(Duration::from_millis(100));
 
@@ -32,4 +34,26 @@ mod test {
         let idle_workers: Vec<_> = status.iter().filter(|w| w.description == "idle").collect();
         assert!(!idle_workers.is_empty(), "No hay workers marcados como 'idle'");
     }
+
+    #[test]
+    fn test_worker_survives_panicking_task() {
+        let (tx, rx) = channel();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        start_workers(Arc::new(Mutex::new(rx)), states.clone());
+
+        // Una especificaciòn armada a mano, sin validar, con un rango vacìo: la tarea entra en pànico
+        let panicking = RandomSpec { kind: RandomKind::Numbers { count: 1, dist: Distribution::UniformInt { min: 5, max: 1 } }, seed: None };
+        for _ in 0..8 {
+            let (resp_tx, resp_rx) = reply_channel();
+            tx.send(Task { description: "Pànico".into(), task_type: TaskType::Random(panicking.clone()), response_tx: resp_tx, enqueued: Instant::now() }).unwrap();
+            assert!(resp_rx.recv_timeout(Duration::from_secs(1)).is_err());
+        }
+
+        // Los 4 workers siguen vivos y libres
+        let (resp_tx, resp_rx) = reply_channel();
+        tx.send(Task { description: "Test de reverse".into(), task_type: TaskType::Reverse("abc".into()), response_tx: resp_tx, enqueued: Instant::now() }).unwrap();
+        assert_eq!(resp_rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap().to_string(), "cba");
+        thread::sleep(Duration::from_millis(100));
+        assert!(states.lock().unwrap().iter().all(|w| !w.busy));
+    }
 }